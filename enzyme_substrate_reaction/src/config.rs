@@ -0,0 +1,128 @@
+use game_of_life_core::config::{
+    exit_on_error, parse_flags, parse_value, Batching, ConfigError, Executor,
+};
+
+pub const USAGE: &str = "Options:
+    --threads <count>            compute threads (default: one per core)
+    --batching <strategy>        auto, fixed:<items> or per-thread:<batches> for every parallel
+                                 query (default auto)
+    --executor <kind>            multi or single threaded Update schedule, single also runs
+                                 parallel queries serially on one thread (default multi)
+    --frames <count>             stop after this many updates (default: once every
+                                 substrate is consumed)
+    --total-time-output <file>   write the time the updates took, without the scene setup
+    --system-timings-output <file>
+                                 write min, mean, p50 and p99 per system, as .csv or .json
+    --trace-output <file>        write a Chrome trace of every system run and parallel
+                                 batch, per thread
+    --help                       print this message";
+
+const FLAGS: [&str; 7] = [
+    "--threads",
+    "--batching",
+    "--executor",
+    "--frames",
+    "--total-time-output",
+    "--system-timings-output",
+    "--trace-output",
+];
+
+/// Run parameters of the enzyme simulation, parsed the same way as the Game of Life
+/// backends' options.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Config {
+    pub threads: Option<usize>,
+    pub batching: Batching,
+    pub executor: Executor,
+    pub frames: Option<u64>,
+    pub total_time_output: Option<String>,
+    pub system_timings_output: Option<String>,
+    pub trace_output: Option<String>,
+}
+
+impl Config {
+    /// Parses `--flag value` and `--flag=value` options, starting from `self`.
+    pub fn parse_args(
+        mut self,
+        args: impl IntoIterator<Item = String>,
+    ) -> Result<Self, ConfigError> {
+        parse_flags(args, &FLAGS, |flag, value| {
+            match flag {
+                "--threads" => self.threads = Some(parse_value(flag, value)?),
+                "--batching" => self.batching = parse_value(flag, value)?,
+                "--executor" => self.executor = parse_value(flag, value)?,
+                "--frames" => self.frames = Some(parse_value(flag, value)?),
+                "--total-time-output" => self.total_time_output = Some(value.to_string()),
+                "--system-timings-output" => self.system_timings_output = Some(value.to_string()),
+                "--trace-output" => self.trace_output = Some(value.to_string()),
+                _ => unreachable!("{} is listed in FLAGS", flag),
+            }
+            Ok(())
+        })?;
+        Ok(self)
+    }
+
+    /// The size of the compute pool, `None` for one thread per core.
+    pub fn compute_threads(&self) -> Option<usize> {
+        self.executor.compute_threads(self.threads)
+    }
+
+    /// Parses the process arguments on top of `self`, printing the usage and exiting on errors.
+    pub fn parse_env_or_exit(self) -> Self {
+        exit_on_error(self.parse_args(std::env::args().skip(1)), USAGE)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse_args() {
+        let config = Config::default()
+            .parse_args(args(&[
+                "--threads=2",
+                "--batching",
+                "fixed:1024",
+                "--executor=single",
+                "--frames",
+                "10",
+                "--total-time-output",
+                "total.txt",
+                "--system-timings-output=systems.csv",
+                "--trace-output",
+                "trace.json",
+            ]))
+            .unwrap();
+        assert_eq!(config.system_timings_output.as_deref(), Some("systems.csv"));
+        assert_eq!(config.trace_output.as_deref(), Some("trace.json"));
+        assert_eq!(config.threads, Some(2));
+        assert_eq!(config.batching, Batching::Fixed(1024));
+        assert_eq!(config.executor, Executor::SingleThreaded);
+        assert_eq!(config.compute_threads(), Some(1));
+        assert_eq!(config.frames, Some(10));
+        assert_eq!(config.total_time_output.as_deref(), Some("total.txt"));
+
+        assert_eq!(
+            Config::default().parse_args(args(&["--cells", "4"])),
+            Err(ConfigError::UnknownFlag("--cells".to_string()))
+        );
+        assert_eq!(
+            Config::default().parse_args(args(&["--threads", "many"])),
+            Err(ConfigError::InvalidValue {
+                flag: "--threads".to_string(),
+                value: "many".to_string()
+            })
+        );
+        assert_eq!(
+            Config::default().parse_args(args(&["--system-timings-output"])),
+            Err(ConfigError::MissingValue(
+                "--system-timings-output".to_string()
+            ))
+        );
+    }
+}
@@ -1,11 +1,21 @@
 use bevy::prelude::*;
+use config::Config;
 use plugin::EnzymeSubstrateReactionPlugin;
 
+mod config;
 mod plugin;
 
 fn main() {
+    let config = Config::default().parse_env_or_exit();
+
+    let mut task_pool_options = TaskPoolOptions::default();
+    if let Some(threads) = config.compute_threads() {
+        task_pool_options.compute.min_threads = threads;
+        task_pool_options.compute.max_threads = threads;
+    }
+
     App::new()
-        .add_plugins(MinimalPlugins)
-        .add_plugins(EnzymeSubstrateReactionPlugin)
+        .add_plugins(MinimalPlugins.set(TaskPoolPlugin { task_pool_options }))
+        .add_plugins(EnzymeSubstrateReactionPlugin { config })
         .run();
 }
@@ -3,13 +3,38 @@ use std::{ops::Sub, time::Instant};
 use bevy::prelude::*;
 use rand::Rng;
 
-pub struct EnzymeSubstrateReactionPlugin;
+use crate::config::Config;
+use parallel::{Batching, Instruments, SystemTimings, Tracing};
+
+mod parallel;
+
+#[derive(Default)]
+pub struct EnzymeSubstrateReactionPlugin {
+    pub config: Config,
+}
 
 impl Plugin for EnzymeSubstrateReactionPlugin {
     fn build(&self, app: &mut App) {
+        if self.config.system_timings_output.is_some() {
+            app.init_resource::<SystemTimings>();
+        }
+        if self.config.trace_output.is_some() {
+            app.init_resource::<Tracing>();
+        }
+        parallel::set_executor(app, self.config.executor);
+
         app.init_resource::<SimulationLogFlag>()
+            .insert_resource(Batching(self.config.batching))
+            .insert_resource(MaxFrames(self.config.frames))
+            .insert_resource(StartTime(Instant::now()))
+            .insert_resource(OutputFiles {
+                total_time: self.config.total_time_output.clone(),
+                system_timings: self.config.system_timings_output.clone(),
+                trace: self.config.trace_output.clone(),
+            })
             // .add_systems(Startup, setup_system)
             .add_systems(Startup, huge_scene_setup)
+            .add_systems(First, start_clock.run_if(run_once()))
             .add_systems(
                 Update,
                 (
@@ -31,6 +56,26 @@ impl Default for SimulationLogFlag {
     }
 }
 
+/// How many updates to run before exiting, `None` to run until every substrate is
+/// consumed.
+#[derive(Resource)]
+struct MaxFrames(Option<u64>);
+
+#[derive(Resource)]
+struct StartTime(Instant);
+
+/// Restarts the total time on the first frame, once the scene spawned at startup exists.
+fn start_clock(mut start_time: ResMut<StartTime>) {
+    start_time.0 = Instant::now();
+}
+
+#[derive(Resource)]
+struct OutputFiles {
+    total_time: Option<String>,
+    system_timings: Option<String>,
+    trace: Option<String>,
+}
+
 // Component representing the concentration of a molecule
 #[derive(Component)]
 struct Concentration(f32);
@@ -101,7 +146,9 @@ fn binding_system(
     mut enzyme_query: Query<(&mut ActiveSite, &Concentration, &Enzyme)>,
     substrate_query: Query<(&Concentration, &Substrate)>,
     log_flag: Res<SimulationLogFlag>,
+    mut instruments: Instruments,
 ) {
+    let start = Instant::now();
     for (mut active_site, enzyme_concentration, _) in enzyme_query.iter_mut() {
         if active_site.0 {
             for (substrate_concentration, _) in substrate_query.iter() {
@@ -115,6 +162,7 @@ fn binding_system(
             }
         }
     }
+    instruments.finish("binding_system", start);
 }
 
 fn reaction_system(
@@ -122,42 +170,52 @@ fn reaction_system(
     mut substrate_query: Query<(Entity, &mut Concentration), (Without<Product>, With<Substrate>)>,
     mut product_query: Query<(Entity, &mut Concentration), With<Product>>,
     log_flag: Res<SimulationLogFlag>,
+    batching: Res<Batching>,
+    mut instruments: Instruments,
 ) {
+    let start = Instant::now();
+    let batch = instruments.batch("reaction_system");
     enzyme_query
         .par_iter()
-        .for_each(|(active_site, reaction_rate, km)| {
-            if !active_site.0 {
-                return; // Only proceed if the active site is occupied
-            }
+        .batching_strategy(batching.strategy())
+        .for_each(batch.wrap(
+            |(active_site, reaction_rate, km): (&ActiveSite, &ReactionRate, &MichaelisConstant)| {
+                if !active_site.0 {
+                    return; // Only proceed if the active site is occupied
+                }
 
-            substrate_query.par_iter_mut().for_each(
-                |(substrate_entity, mut substrate_concentration)| {
-                    if substrate_concentration.0 > 0.0 {
-                        let rate = reaction_rate.0 * substrate_concentration.0
-                            / (km.0 + substrate_concentration.0); // Michaelis-Menten kinetics equation v = Vmax * [S] / (Km + [S])
-                        substrate_concentration.0 -= rate; // Consume the substrate
-                        if log_flag.0 {
-                            println!(
-                                "Substrate {} concentration: {}",
-                                substrate_entity, substrate_concentration.0
-                            );
+                substrate_query
+                    .par_iter_mut()
+                    .batching_strategy(batching.strategy())
+                    .for_each(|(substrate_entity, mut substrate_concentration)| {
+                        if substrate_concentration.0 > 0.0 {
+                            let rate = reaction_rate.0 * substrate_concentration.0
+                                / (km.0 + substrate_concentration.0); // Michaelis-Menten kinetics equation v = Vmax * [S] / (Km + [S])
+                            substrate_concentration.0 -= rate; // Consume the substrate
+                            if log_flag.0 {
+                                println!(
+                                    "Substrate {} concentration: {}",
+                                    substrate_entity, substrate_concentration.0
+                                );
+                            }
+
+                            product_query
+                                .par_iter_mut()
+                                .batching_strategy(batching.strategy())
+                                .for_each(|(product_entity, mut product_concentration)| {
+                                    product_concentration.0 += rate; // Produce the product
+                                    if log_flag.0 {
+                                        println!(
+                                            "Product {} concentration: {}",
+                                            product_entity, product_concentration.0
+                                        );
+                                    }
+                                });
                         }
-
-                        product_query.par_iter_mut().for_each(
-                            |(product_entity, mut product_concentration)| {
-                                product_concentration.0 += rate; // Produce the product
-                                if log_flag.0 {
-                                    println!(
-                                        "Product {} concentration: {}",
-                                        product_entity, product_concentration.0
-                                    );
-                                }
-                            },
-                        );
-                    }
-                },
-            );
-        });
+                    });
+            },
+        ));
+    instruments.finish("reaction_system", start);
 
     // for (active_site, reaction_rate, km) in enzyme_query.iter() {
     //     if !active_site.0 {
@@ -190,7 +248,12 @@ fn reaction_system(
     // }
 }
 
-fn release_system(mut enzyme_query: Query<&mut ActiveSite>, log_flag: Res<SimulationLogFlag>) {
+fn release_system(
+    mut enzyme_query: Query<&mut ActiveSite>,
+    log_flag: Res<SimulationLogFlag>,
+    mut instruments: Instruments,
+) {
+    let start = Instant::now();
     for mut active_site in enzyme_query.iter_mut() {
         if !active_site.0 {
             active_site.0 = true; // Release the product from the enzyme (i.e. free the active site)
@@ -199,19 +262,41 @@ fn release_system(mut enzyme_query: Query<&mut ActiveSite>, log_flag: Res<Simula
             }
         }
     }
+    instruments.finish("release_system", start);
 }
 
-fn check_if_substrates_are_consumed(substrate_query: Query<&Concentration, With<Substrate>>) {
+fn check_if_substrates_are_consumed(
+    substrate_query: Query<&Concentration, With<Substrate>>,
+    mut frames: Local<u64>,
+    max_frames: Res<MaxFrames>,
+    start_time: Res<StartTime>,
+    output_files: Res<OutputFiles>,
+    (timings, tracing): (Option<Res<SystemTimings>>, Option<Res<Tracing>>),
+) {
     let positive_substrates_concentration_count = substrate_query
         .iter()
         .filter(|substrate_concentration| substrate_concentration.0 > 0.0)
         .count();
 
-    if positive_substrates_concentration_count <= 0 {
+    *frames += 1;
+
+    if positive_substrates_concentration_count <= 0
+        || max_frames.0.is_some_and(|max_frames| *frames >= max_frames)
+    {
+        let total_time = start_time.0.elapsed();
         println!(
             "End: {:?}",
             std::time::UNIX_EPOCH.elapsed().unwrap().as_millis()
         );
+        if let Some(filename) = &output_files.total_time {
+            std::fs::write(filename, format!("{:?}", total_time)).expect("Unable to write data");
+        }
+        if let (Some(timings), Some(filename)) = (&timings, &output_files.system_timings) {
+            timings.0.save(filename).expect("Unable to write data");
+        }
+        if let (Some(tracing), Some(filename)) = (&tracing, &output_files.trace) {
+            tracing.0.save(filename).expect("Unable to write data");
+        }
         std::process::exit(0);
     }
 }
@@ -222,6 +307,7 @@ mod tests {
     fn setup_test_app() -> App {
         let mut app = App::new();
         app.insert_resource(SimulationLogFlag(false))
+            .insert_resource(Batching(Default::default()))
             .add_plugins(MinimalPlugins);
         app
     }
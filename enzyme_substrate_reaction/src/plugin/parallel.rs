@@ -0,0 +1,73 @@
+//! The Bevy 0.14 resources and system params for the enzyme options shared with the Game of
+//! Life plugin. Batching, timings and traces are handled by `game_of_life_core`.
+
+use std::time::Instant;
+
+use bevy::{
+    ecs::{query::BatchingStrategy, schedule::ExecutorKind, system::SystemParam},
+    prelude::*,
+};
+use game_of_life_core::{
+    config::{self, Executor},
+    timing,
+    trace::{self, BatchSpan, TraceRecorder},
+};
+
+/// How every parallel query is split into batches, from `--batching`.
+#[derive(Resource, Debug, Clone, Copy)]
+pub struct Batching(pub config::Batching);
+
+impl Batching {
+    pub fn strategy(&self) -> BatchingStrategy {
+        let limits = self.0.batch_size_limits();
+        BatchingStrategy::new()
+            .min_batch_size(limits.start)
+            .max_batch_size(limits.end)
+            .batches_per_thread(self.0.batches_per_thread())
+    }
+}
+
+/// Runs `Update` on the single threaded executor for `--executor single`. `main` also
+/// shrinks the compute pool to one thread, which makes `par_iter` run inline.
+pub fn set_executor(app: &mut App, executor: Executor) {
+    if executor == Executor::SingleThreaded {
+        app.edit_schedule(Update, |schedule| {
+            schedule.set_executor_kind(ExecutorKind::SingleThreaded);
+        });
+    }
+}
+
+/// How long each system took every update, only inserted when `--system-timings-output`
+/// is given.
+#[derive(Resource, Default)]
+pub struct SystemTimings(pub timing::SystemTimings);
+
+/// Every system run and parallel batch per thread, only inserted when `--trace-output` is
+/// given.
+#[derive(Resource, Default)]
+pub struct Tracing(pub TraceRecorder);
+
+/// Where the systems report how long they took, to whichever of `--system-timings-output`
+/// and `--trace-output` are on.
+#[derive(SystemParam)]
+pub struct Instruments<'w> {
+    timings: Option<ResMut<'w, SystemTimings>>,
+    tracing: Option<Res<'w, Tracing>>,
+}
+
+impl Instruments<'_> {
+    /// A span to move into a `par_iter` closure, timing each batch of `system`.
+    pub fn batch(&self, system: &'static str) -> BatchSpan<'_> {
+        BatchSpan::new(self.tracing.as_deref().map(|tracing| &tracing.0), system)
+    }
+
+    /// Records a run of `system` from `start` until now.
+    pub fn finish(&mut self, system: &'static str, start: Instant) {
+        trace::record_system(
+            self.timings.as_deref_mut().map(|timings| &mut timings.0),
+            self.tracing.as_deref().map(|tracing| &tracing.0),
+            system,
+            start,
+        );
+    }
+}
@@ -0,0 +1,241 @@
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+    process::Command,
+    str::FromStr,
+    time::Duration,
+};
+
+use game_of_life_core::{
+    config::Executor,
+    cycle::{self, Stabilization},
+    timing,
+};
+
+/// One of the Game of Life implementations, run as its own release binary.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backend {
+    Bevy,
+    /// The Bevy binary on the single threaded executor, the ECS with no parallelism.
+    BevySerial,
+    NoEcs,
+    Hecs,
+}
+
+impl Backend {
+    pub const ALL: [Backend; 4] = [
+        Backend::Bevy,
+        Backend::BevySerial,
+        Backend::NoEcs,
+        Backend::Hecs,
+    ];
+
+    /// The backend whose binary this one runs.
+    pub fn program(self) -> Backend {
+        match self {
+            Backend::BevySerial => Backend::Bevy,
+            backend => backend,
+        }
+    }
+
+    /// Options this backend always runs with, after the shared ones.
+    pub fn args(self) -> &'static [&'static str] {
+        match self {
+            Backend::BevySerial => &["--executor", "single"],
+            _ => &[],
+        }
+    }
+
+    /// The Bevy executor this backend runs its systems on given the shared `--executor`,
+    /// `None` for the backends without a Bevy schedule.
+    pub fn executor(self, configured: Executor) -> Option<Executor> {
+        match self {
+            Backend::Bevy => Some(configured),
+            Backend::BevySerial => Some(Executor::SingleThreaded),
+            Backend::NoEcs | Backend::Hecs => None,
+        }
+    }
+
+    /// The release binary `cargo build --release` produces for this backend.
+    pub fn default_binary(self) -> PathBuf {
+        let root = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("..");
+        match self.program() {
+            Backend::Bevy | Backend::BevySerial => root.join("target/release/ecs_multithreading"),
+            Backend::NoEcs => root.join("no_ecs/target/release/no_ecs"),
+            Backend::Hecs => root.join("game_of_life_hecs/target/release/game_of_life_hecs"),
+        }
+    }
+}
+
+impl fmt::Display for Backend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Backend::Bevy => "bevy",
+            Backend::BevySerial => "bevy_serial",
+            Backend::NoEcs => "no_ecs",
+            Backend::Hecs => "hecs",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for Backend {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "bevy" | "ecs" => Ok(Backend::Bevy),
+            "bevy_serial" | "bevy-serial" | "ecs_serial" => Ok(Backend::BevySerial),
+            "no_ecs" | "no-ecs" | "noecs" => Ok(Backend::NoEcs),
+            "hecs" => Ok(Backend::Hecs),
+            _ => Err(format!(
+                "unknown backend {:?}, expected bevy, bevy_serial, no_ecs or hecs",
+                s
+            )),
+        }
+    }
+}
+
+pub const USAGE: &str = "Backend options:
+    --backends <list>            comma separated backends to run
+                                 (default bevy,bevy_serial,no_ecs,hecs)
+    --bevy-bin <path>            Bevy binary, also run as bevy_serial with --executor single
+                                 (default target/release/ecs_multithreading)
+    --no-ecs-bin <path>          no_ecs binary (default no_ecs/target/release/no_ecs)
+    --hecs-bin <path>            hecs binary (default game_of_life_hecs/target/release/game_of_life_hecs)";
+
+/// Which backends to run and where their binaries are.
+pub struct Selection {
+    backends: Vec<Backend>,
+    binaries: Vec<(Backend, PathBuf)>,
+}
+
+impl Default for Selection {
+    fn default() -> Self {
+        Selection {
+            backends: Backend::ALL.to_vec(),
+            binaries: Vec::new(),
+        }
+    }
+}
+
+impl Selection {
+    /// Handles `--backends` and the `--*-bin` overrides, returning `Ok(false)` for any
+    /// other flag without touching `value`.
+    pub fn parse_flag(
+        &mut self,
+        flag: &str,
+        value: impl FnOnce() -> String,
+    ) -> Result<bool, String> {
+        match flag {
+            "--backends" => {
+                self.backends = value()
+                    .split(',')
+                    .map(str::parse)
+                    .collect::<Result<_, _>>()?;
+            }
+            "--bevy-bin" => self.binaries.push((Backend::Bevy, value().into())),
+            "--no-ecs-bin" => self.binaries.push((Backend::NoEcs, value().into())),
+            "--hecs-bin" => self.binaries.push((Backend::Hecs, value().into())),
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    /// The selected backends with their binaries, the last override winning.
+    pub fn backends(&self) -> Vec<(Backend, PathBuf)> {
+        self.backends
+            .iter()
+            .map(|backend| {
+                let binary = self
+                    .binaries
+                    .iter()
+                    .rev()
+                    .find(|(b, _)| *b == backend.program())
+                    .map_or_else(|| backend.default_binary(), |(_, binary)| binary.clone());
+                (*backend, binary)
+            })
+            .collect()
+    }
+}
+
+/// Runs `binary` with `args` and fails with its stderr if it does not exit cleanly.
+pub fn execute(program: impl fmt::Display, binary: &Path, args: &[String]) -> Result<(), String> {
+    let output = Command::new(binary)
+        .args(args)
+        .output()
+        .map_err(|error| format!("unable to run {}: {}", binary.display(), error))?;
+    if !output.status.success() {
+        return Err(format!(
+            "{} exited with {}\n{}",
+            program,
+            output.status,
+            String::from_utf8_lossy(&output.stderr)
+        ));
+    }
+    Ok(())
+}
+
+/// A unique path in the temp directory for files a backend writes on our behalf.
+pub fn scratch_file(program: impl fmt::Display, name: &str) -> String {
+    std::env::temp_dir()
+        .join(format!(
+            "game_of_life_bench_{}_{}_{}",
+            program,
+            std::process::id(),
+            name
+        ))
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// Timings reported by a single run of a backend.
+#[derive(Debug, Clone)]
+pub struct Run {
+    pub total: Duration,
+    pub generations: Vec<Duration>,
+    /// How the run settled, when it was run with `--cycles` and did.
+    pub stabilization: Option<Stabilization>,
+}
+
+/// Runs `binary` with the shared `--flag value` options and reads back the timings it wrote.
+pub fn run(backend: Backend, binary: &Path, args: &[String]) -> Result<Run, String> {
+    let durations_output = scratch_file(backend, "durations.txt");
+    let total_time_output = scratch_file(backend, "total_time.txt");
+    let stabilization_output = scratch_file(backend, "stabilization.txt");
+
+    let mut args = args.to_vec();
+    args.extend(backend.args().iter().map(|arg| arg.to_string()));
+    args.extend([
+        "--durations-output".to_string(),
+        durations_output.clone(),
+        "--total-time-output".to_string(),
+        total_time_output.clone(),
+        "--stabilization-output".to_string(),
+        stabilization_output.clone(),
+    ]);
+    execute(backend, binary, &args)?;
+
+    let generations = timing::load_durations(&durations_output)
+        .map_err(|error| format!("{} did not write its durations: {}", backend, error))?;
+    let total = std::fs::read_to_string(&total_time_output)
+        .ok()
+        .and_then(|total| timing::parse_duration(&total))
+        .ok_or_else(|| format!("{} did not write its total time", backend))?;
+    // Only written with `--cycles` on.
+    let stabilization = if Path::new(&stabilization_output).exists() {
+        cycle::load(&stabilization_output)
+            .map_err(|error| format!("{} wrote an invalid stabilization: {}", backend, error))?
+    } else {
+        None
+    };
+    let _ = std::fs::remove_file(durations_output);
+    let _ = std::fs::remove_file(total_time_output);
+    let _ = std::fs::remove_file(stabilization_output);
+
+    Ok(Run {
+        total,
+        generations,
+        stabilization,
+    })
+}
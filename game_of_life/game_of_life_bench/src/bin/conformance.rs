@@ -0,0 +1,77 @@
+use game_of_life_bench::{
+    backend::{self, Selection},
+    conformance::{self, CATALOGUE},
+};
+
+const CONFORMANCE_USAGE: &str = "Conformance options:
+    --cases <list>               comma separated case names to run (default: all)";
+
+fn exit_with_usage(error: &str) -> ! {
+    eprintln!(
+        "{}\n\nUsage: conformance [options]\n\n{}\n\n{}",
+        error,
+        CONFORMANCE_USAGE,
+        backend::USAGE
+    );
+    std::process::exit(1);
+}
+
+fn main() {
+    let mut selection = Selection::default();
+    let mut cases: Vec<String> = Vec::new();
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let (flag, inline_value) = match arg.split_once('=') {
+            Some((flag, value)) => (flag.to_string(), Some(value.to_string())),
+            None => (arg.clone(), None),
+        };
+        let mut value = || {
+            inline_value
+                .clone()
+                .or_else(|| args.next())
+                .unwrap_or_else(|| exit_with_usage(&format!("missing value for {}", flag)))
+        };
+
+        match selection.parse_flag(&flag, &mut value) {
+            Ok(true) => continue,
+            Ok(false) => {}
+            Err(error) => exit_with_usage(&error),
+        }
+        match flag.as_str() {
+            "--cases" => cases = value().split(',').map(str::to_string).collect(),
+            _ => exit_with_usage(&format!("unknown option {}", flag)),
+        }
+    }
+
+    if let Some(unknown) = cases
+        .iter()
+        .find(|name| !CATALOGUE.iter().any(|case| case.name == name.as_str()))
+    {
+        exit_with_usage(&format!("unknown case {}", unknown));
+    }
+
+    let backends = selection.backends();
+    let mut failed = 0;
+    for case in CATALOGUE
+        .iter()
+        .filter(|case| cases.is_empty() || cases.iter().any(|name| name == case.name))
+    {
+        let failures = conformance::check(case, &backends);
+        if failures.is_empty() {
+            println!("{:<16} ok", case.name);
+            continue;
+        }
+
+        failed += 1;
+        println!("{:<16} FAILED", case.name);
+        for failure in failures {
+            println!("  {}", failure.to_string().replace('\n', "\n  "));
+        }
+    }
+
+    if failed > 0 {
+        println!("{} of the cases failed", failed);
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,212 @@
+use game_of_life_bench::scaling::{self, Setup, Step, Target};
+use game_of_life_core::{
+    config::{Batching, ParseBatchingError},
+    Config, Soup,
+};
+
+const SCALING_USAGE: &str = "Scaling options:
+    --target <name>              plugin to run, game_of_life or enzyme (default game_of_life)
+    --max-threads <count>        largest compute pool, swept as 1, 2, 4, ... (default: one per core)
+    --warmup <count>             unmeasured runs per thread count (default 1)
+    --repetitions <count>        measured runs per thread count (default 3)
+    --results <file>             where to write the results, .json or .csv (default scaling.json)
+    --game-of-life-bin <path>    Game of Life binary (default target/release/ecs_multithreading)
+    --enzyme-bin <path>          enzyme binary (default ../enzyme_substrate_reaction/target/release/enzyme_substrate_reaction)
+
+Every other option is passed on to the plugin unchanged, except --threads which the sweep sets.
+--batching is passed on too and recorded with the results.";
+
+struct Sweep {
+    target: Target,
+    batching: Batching,
+    max_threads: usize,
+    warmup: usize,
+    repetitions: usize,
+    results: String,
+    binaries: Vec<(Target, String)>,
+    forwarded: Vec<String>,
+}
+
+fn exit_with_usage(error: &str) -> ! {
+    eprintln!("{}\n\nUsage: scaling [options]\n\n{}", error, SCALING_USAGE);
+    std::process::exit(1);
+}
+
+/// Splits the sweep options from the ones forwarded to the plugin.
+fn parse_args(mut args: impl Iterator<Item = String>) -> Sweep {
+    let mut sweep = Sweep {
+        target: Target::GameOfLife,
+        batching: Batching::default(),
+        max_threads: std::thread::available_parallelism().map_or(1, |threads| threads.get()),
+        warmup: 1,
+        repetitions: 3,
+        results: "scaling.json".to_string(),
+        binaries: Vec::new(),
+        forwarded: Vec::new(),
+    };
+
+    while let Some(arg) = args.next() {
+        let (flag, inline_value) = match arg.split_once('=') {
+            Some((flag, value)) => (flag.to_string(), Some(value.to_string())),
+            None => (arg.clone(), None),
+        };
+        let mut value = || {
+            inline_value
+                .clone()
+                .or_else(|| args.next())
+                .unwrap_or_else(|| exit_with_usage(&format!("missing value for {}", flag)))
+        };
+        let count = |value: String| {
+            value
+                .parse()
+                .unwrap_or_else(|_| exit_with_usage(&format!("invalid count {:?}", value)))
+        };
+
+        match flag.as_str() {
+            "--help" | "-h" => {
+                println!("Usage: scaling [options]\n\n{}", SCALING_USAGE);
+                std::process::exit(0);
+            }
+            "--target" => {
+                sweep.target = value()
+                    .parse()
+                    .unwrap_or_else(|error: String| exit_with_usage(&error))
+            }
+            "--max-threads" => sweep.max_threads = count(value()),
+            "--warmup" => sweep.warmup = count(value()),
+            "--repetitions" => sweep.repetitions = count(value()),
+            "--results" => sweep.results = value(),
+            "--game-of-life-bin" => sweep.binaries.push((Target::GameOfLife, value())),
+            "--enzyme-bin" => sweep.binaries.push((Target::Enzyme, value())),
+            "--batching" => {
+                let strategy = value();
+                sweep.batching = strategy
+                    .parse()
+                    .unwrap_or_else(|error: ParseBatchingError| {
+                        exit_with_usage(&error.to_string())
+                    });
+                sweep.forwarded.push(format!("--batching={}", strategy));
+            }
+            "--threads" => exit_with_usage("--threads is set by the sweep, use --max-threads"),
+            _ => sweep.forwarded.push(arg),
+        }
+    }
+
+    sweep
+}
+
+fn main() {
+    let mut sweep = parse_args(std::env::args().skip(1));
+    if sweep.max_threads == 0 {
+        exit_with_usage("--max-threads has to be at least 1");
+    }
+    if sweep.repetitions == 0 {
+        exit_with_usage("--repetitions has to be at least 1");
+    }
+    let binary = sweep
+        .binaries
+        .iter()
+        .rev()
+        .find(|(target, _)| *target == sweep.target)
+        .map_or_else(
+            || sweep.target.default_binary(),
+            |(_, binary)| binary.into(),
+        );
+
+    // Every thread count has to start from the same soup, so pin the seed up front.
+    if sweep.target == Target::GameOfLife {
+        let config = Config::default()
+            .parse_args(sweep.forwarded.clone())
+            .unwrap_or_else(|error| exit_with_usage(&error.to_string()));
+        if config.seed.is_none() && config.pattern.is_none() {
+            let seed = Soup::from_entropy(config.density).seed;
+            sweep.forwarded.push(format!("--seed={}", seed));
+        }
+    }
+    println!(
+        "Scaling {} with {} batching over {:?} compute threads, {} warmup and {} measured runs each",
+        sweep.target,
+        sweep.batching,
+        scaling::thread_counts(sweep.max_threads),
+        sweep.warmup,
+        sweep.repetitions
+    );
+
+    let mut steps: Vec<Step> = Vec::new();
+    for threads in scaling::thread_counts(sweep.max_threads) {
+        let mut runs = Vec::with_capacity(sweep.repetitions);
+        for repetition in 0..sweep.warmup + sweep.repetitions {
+            let total = scaling::run(sweep.target, &binary, &sweep.forwarded, threads)
+                .unwrap_or_else(|error| {
+                    eprintln!("{}", error);
+                    std::process::exit(1);
+                });
+            if repetition >= sweep.warmup {
+                runs.push(total);
+            }
+        }
+        steps.push(Step { threads, runs });
+
+        let step = scaling::scaling(&steps)[steps.len() - 1];
+        println!(
+            "{:>4} threads: mean {:?}, speedup {:.2}x, efficiency {:.0}%, serial fraction {}",
+            threads,
+            steps[steps.len() - 1].summary().mean,
+            step.speedup,
+            step.efficiency * 100.0,
+            step.serial_fraction
+                .map_or("-".to_string(), |fraction| format!("{:.3}", fraction))
+        );
+    }
+
+    let setup = Setup {
+        target: sweep.target,
+        batching: sweep.batching,
+        args: &sweep.forwarded,
+        warmup: sweep.warmup,
+    };
+    scaling::save(&sweep.results, &setup, &steps).expect("Unable to write results");
+    println!("Results written to {}", sweep.results);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> impl Iterator<Item = String> {
+        args.iter()
+            .map(|arg| arg.to_string())
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+
+    #[test]
+    fn test_parse_args_splits_forwarded_options() {
+        let sweep = parse_args(args(&[
+            "--target=enzyme",
+            "--frames",
+            "5",
+            "--max-threads",
+            "6",
+            "--enzyme-bin",
+            "/bin/enzyme",
+            "--batching",
+            "per-thread:4",
+            "--repetitions=2",
+        ]));
+
+        assert_eq!(sweep.target, Target::Enzyme);
+        assert_eq!(sweep.batching, Batching::PerThread(4));
+        assert_eq!(sweep.max_threads, 6);
+        assert_eq!(sweep.warmup, 1);
+        assert_eq!(sweep.repetitions, 2);
+        assert_eq!(
+            sweep.binaries,
+            vec![(Target::Enzyme, "/bin/enzyme".to_string())]
+        );
+        assert_eq!(
+            sweep.forwarded,
+            vec!["--frames", "5", "--batching=per-thread:4"]
+        );
+    }
+}
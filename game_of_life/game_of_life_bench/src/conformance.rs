@@ -0,0 +1,304 @@
+//! A catalogue of still lifes, oscillators and spaceships whose state after a whole
+//! number of periods is known, run through every backend and compared cell by cell.
+
+use std::{collections::HashSet, path::Path};
+
+use game_of_life_core::{Boundary, Pattern};
+
+use crate::backend::{self, Backend};
+
+pub type Cells = HashSet<(i32, i32)>;
+
+/// One pattern placed on a grid and run for a whole number of periods.
+#[derive(Debug, Clone, Copy)]
+pub struct Case {
+    pub name: &'static str,
+    /// File name inside `game_of_life/fixtures`.
+    pub fixture: &'static str,
+    pub width: u32,
+    pub height: u32,
+    pub offset: (i32, i32),
+    pub boundary: Boundary,
+    pub period: u64,
+    /// How far the pattern travels every period, `(0, 0)` for anything but spaceships.
+    pub displacement: (i32, i32),
+    pub generations: u64,
+}
+
+const fn case(
+    name: &'static str,
+    fixture: &'static str,
+    (width, height): (u32, u32),
+    offset: (i32, i32),
+    period: u64,
+    generations: u64,
+) -> Case {
+    Case {
+        name,
+        fixture,
+        width,
+        height,
+        offset,
+        boundary: Boundary::Dead,
+        period,
+        displacement: (0, 0),
+        generations,
+    }
+}
+
+pub const CATALOGUE: [Case; 14] = [
+    case("block", "block.cells", (8, 8), (2, 2), 1, 4),
+    case("beehive", "beehive.cells", (10, 9), (2, 2), 1, 4),
+    case("loaf", "loaf.rle", (8, 8), (2, 2), 1, 4),
+    case("boat", "boat.rle", (7, 7), (2, 2), 1, 4),
+    case("tub", "tub.rle", (7, 7), (2, 2), 1, 4),
+    case("blinker", "blinker.cells", (9, 9), (2, 2), 2, 4),
+    case("toad", "toad.cells", (8, 8), (2, 2), 2, 4),
+    case("beacon", "beacon.rle", (8, 8), (2, 2), 2, 4),
+    case("pulsar", "pulsar.rle", (17, 17), (2, 2), 3, 6),
+    case(
+        "pentadecathlon",
+        "pentadecathlon.rle",
+        (22, 15),
+        (6, 6),
+        15,
+        15,
+    ),
+    Case {
+        displacement: (1, 1),
+        ..case("glider", "glider.rle", (14, 14), (1, 1), 4, 32)
+    },
+    Case {
+        displacement: (-2, 0),
+        ..case("lwss", "lwss.rle", (20, 9), (13, 2), 4, 16)
+    },
+    Case {
+        boundary: Boundary::Torus,
+        displacement: (1, 1),
+        ..case("glider_torus", "glider.rle", (8, 8), (4, 4), 4, 32)
+    },
+    Case {
+        boundary: Boundary::Torus,
+        displacement: (-2, 0),
+        ..case("lwss_torus", "lwss.rle", (10, 7), (1, 1), 4, 20)
+    },
+];
+
+pub fn fixtures_dir() -> &'static Path {
+    Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/../fixtures"))
+}
+
+impl Case {
+    pub fn load(&self) -> Pattern {
+        let path = fixtures_dir().join(self.fixture);
+        Pattern::load(&path)
+            .unwrap_or_else(|error| panic!("Unable to load {}: {}", path.display(), error))
+    }
+
+    pub fn initial(&self) -> Cells {
+        let (offset_x, offset_y) = self.offset;
+        self.load()
+            .cells_at_offset(offset_x, offset_y, self.width, self.height)
+    }
+
+    /// The initial cells moved by one `displacement` per elapsed period.
+    pub fn expected(&self) -> Cells {
+        let periods = (self.generations / self.period) as i32;
+        let (dx, dy) = (self.displacement.0 * periods, self.displacement.1 * periods);
+        self.initial()
+            .into_iter()
+            .filter_map(|(x, y)| {
+                self.boundary
+                    .resolve(x + dx, y + dy, self.width, self.height)
+            })
+            .collect()
+    }
+
+    /// Runs the case through `binary` and reads back the final grid.
+    pub fn run(&self, backend: Backend, binary: &Path) -> Result<Cells, String> {
+        let output = backend::scratch_file(backend, &format!("{}.rle", self.name));
+        let args = [
+            "--width".to_string(),
+            self.width.to_string(),
+            "--height".to_string(),
+            self.height.to_string(),
+            "--generations".to_string(),
+            self.generations.to_string(),
+            "--boundary".to_string(),
+            self.boundary.to_string(),
+            "--pattern".to_string(),
+            fixtures_dir()
+                .join(self.fixture)
+                .to_string_lossy()
+                .into_owned(),
+            format!("--pattern-x={}", self.offset.0),
+            format!("--pattern-y={}", self.offset.1),
+            "--output".to_string(),
+            output.clone(),
+        ];
+        backend::run(backend, binary, &args)?;
+
+        let pattern = Pattern::load(&output)
+            .map_err(|error| format!("unable to read the grid {} wrote: {}", backend, error))?;
+        let _ = std::fs::remove_file(&output);
+        Ok(pattern.cells_at_offset(0, 0, self.width, self.height))
+    }
+}
+
+/// Draws both grids on top of each other: `O` alive in both, `+` only alive in
+/// `actual`, `-` only alive in `expected`.
+pub fn diff(expected: &Cells, actual: &Cells, width: u32, height: u32) -> String {
+    let mut output = String::new();
+    for y in 0..height as i32 {
+        for x in 0..width as i32 {
+            output.push(
+                match (expected.contains(&(x, y)), actual.contains(&(x, y))) {
+                    (true, true) => 'O',
+                    (false, true) => '+',
+                    (true, false) => '-',
+                    (false, false) => '.',
+                },
+            );
+        }
+        output.push('\n');
+    }
+    output
+}
+
+#[derive(Debug)]
+pub enum Failure {
+    /// The backend could not be run or did not write its grid.
+    Error { backend: Backend, error: String },
+    /// The backend's grid is not the expected one.
+    Unexpected { backend: Backend, diff: String },
+    /// Two backends disagree with each other.
+    Divergence {
+        backend: Backend,
+        reference: Backend,
+        diff: String,
+    },
+}
+
+impl std::fmt::Display for Failure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            // The error already names the backend or its binary.
+            Failure::Error { error, .. } => write!(f, "{}", error),
+            Failure::Unexpected { backend, diff } => {
+                write!(
+                    f,
+                    "{} differs from the expected grid (- missing, + extra):\n{}",
+                    backend, diff
+                )
+            }
+            Failure::Divergence {
+                backend,
+                reference,
+                diff,
+            } => write!(
+                f,
+                "{} diverges from {} (- only in {}, + only in {}):\n{}",
+                backend, reference, reference, backend, diff
+            ),
+        }
+    }
+}
+
+/// Runs one case through every backend, checking each result against the expected grid
+/// and against the first backend that ran.
+pub fn check(case: &Case, backends: &[(Backend, std::path::PathBuf)]) -> Vec<Failure> {
+    let expected = case.expected();
+    let mut failures = Vec::new();
+    let mut reference: Option<(Backend, Cells)> = None;
+
+    for (backend, binary) in backends {
+        let actual = match case.run(*backend, binary) {
+            Ok(actual) => actual,
+            Err(error) => {
+                failures.push(Failure::Error {
+                    backend: *backend,
+                    error,
+                });
+                continue;
+            }
+        };
+
+        if actual != expected {
+            failures.push(Failure::Unexpected {
+                backend: *backend,
+                diff: diff(&expected, &actual, case.width, case.height),
+            });
+        }
+        match &reference {
+            Some((reference, cells)) if *cells != actual => failures.push(Failure::Divergence {
+                backend: *backend,
+                reference: *reference,
+                diff: diff(cells, &actual, case.width, case.height),
+            }),
+            Some(_) => {}
+            None => reference = Some((*backend, actual)),
+        }
+    }
+
+    failures
+}
+
+#[cfg(test)]
+mod tests {
+    use game_of_life_core::Rule;
+
+    use super::*;
+
+    /// A plain set-based stepper, only here to check the catalogue itself.
+    fn step(cells: &Cells, case: &Case) -> Cells {
+        let rule = Rule::default();
+        let mut next = Cells::new();
+        for y in 0..case.height as i32 {
+            for x in 0..case.width as i32 {
+                let neighbors = case
+                    .boundary
+                    .neighbors(x, y, case.width, case.height)
+                    .filter(|neighbor| cells.contains(neighbor))
+                    .count();
+                if rule.next_state(cells.contains(&(x, y)), neighbors as u16) {
+                    next.insert((x, y));
+                }
+            }
+        }
+        next
+    }
+
+    #[test]
+    fn test_catalogue_expectations() {
+        for case in CATALOGUE {
+            assert_eq!(case.generations % case.period, 0, "{}", case.name);
+            let initial = case.initial();
+            assert_eq!(
+                initial.len(),
+                case.load().population(),
+                "{} does not fit its grid",
+                case.name
+            );
+
+            let mut cells = initial;
+            for _ in 0..case.generations {
+                cells = step(&cells, &case);
+            }
+            let expected = case.expected();
+            assert_eq!(
+                cells,
+                expected,
+                "{}:\n{}",
+                case.name,
+                diff(&expected, &cells, case.width, case.height)
+            );
+        }
+    }
+
+    #[test]
+    fn test_diff() {
+        let expected: Cells = [(0, 0), (1, 0)].into_iter().collect();
+        let actual: Cells = [(1, 0), (0, 1)].into_iter().collect();
+        assert_eq!(diff(&expected, &actual, 3, 2), "-O.\n+..\n");
+    }
+}
@@ -0,0 +1,4 @@
+pub mod backend;
+pub mod conformance;
+pub mod results;
+pub mod scaling;
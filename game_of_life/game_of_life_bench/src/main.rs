@@ -0,0 +1,208 @@
+use game_of_life_bench::{
+    backend::{self, Selection},
+    results::{self, BackendResult},
+};
+use game_of_life_core::{
+    config::{ConfigError, USAGE},
+    Config, Soup,
+};
+
+const BENCH_USAGE: &str = "Benchmark options:
+    --warmup <count>             unmeasured runs per backend (default 1)
+    --repetitions <count>        measured runs per backend (default 5)
+    --results <file>             where to write the results, .json or .csv (default bench.json)
+
+Every other option is passed on to the backends unchanged.";
+
+struct Bench {
+    selection: Selection,
+    warmup: usize,
+    repetitions: usize,
+    results: String,
+    forwarded: Vec<String>,
+}
+
+fn exit_with_usage(error: &str) -> ! {
+    eprintln!(
+        "{}\n\nUsage: game_of_life_bench [options]\n\n{}\n\n{}\n\n{}",
+        error,
+        BENCH_USAGE,
+        backend::USAGE,
+        USAGE
+    );
+    std::process::exit(1);
+}
+
+/// Splits the benchmark options from the ones forwarded to every backend.
+fn parse_args(mut args: impl Iterator<Item = String>) -> Bench {
+    let mut bench = Bench {
+        selection: Selection::default(),
+        warmup: 1,
+        repetitions: 5,
+        results: "bench.json".to_string(),
+        forwarded: Vec::new(),
+    };
+
+    while let Some(arg) = args.next() {
+        let (flag, inline_value) = match arg.split_once('=') {
+            Some((flag, value)) => (flag.to_string(), Some(value.to_string())),
+            None => (arg.clone(), None),
+        };
+        let mut value = || {
+            inline_value
+                .clone()
+                .or_else(|| args.next())
+                .unwrap_or_else(|| exit_with_usage(&format!("missing value for {}", flag)))
+        };
+        let count = |value: String| {
+            value
+                .parse()
+                .unwrap_or_else(|_| exit_with_usage(&format!("invalid count {:?}", value)))
+        };
+
+        match bench.selection.parse_flag(&flag, &mut value) {
+            Ok(true) => continue,
+            Ok(false) => {}
+            Err(error) => exit_with_usage(&error),
+        }
+        match flag.as_str() {
+            "--warmup" => bench.warmup = count(value()),
+            "--repetitions" => bench.repetitions = count(value()),
+            "--results" => bench.results = value(),
+            _ => bench.forwarded.push(arg),
+        }
+    }
+
+    bench
+}
+
+fn main() {
+    let mut bench = parse_args(std::env::args().skip(1));
+    if bench.repetitions == 0 {
+        exit_with_usage("--repetitions has to be at least 1");
+    }
+    let mut config = Config::default()
+        .parse_args(bench.forwarded.clone())
+        .unwrap_or_else(|error| match error {
+            ConfigError::HelpRequested => {
+                println!(
+                    "Usage: game_of_life_bench [options]\n\n{}\n\n{}\n\n{}",
+                    BENCH_USAGE,
+                    backend::USAGE,
+                    USAGE
+                );
+                std::process::exit(0);
+            }
+            error => exit_with_usage(&error.to_string()),
+        });
+
+    // Every backend has to start from the same soup, so pin the seed up front.
+    if config.seed.is_none() && config.pattern.is_none() {
+        let seed = Soup::from_entropy(config.density).seed;
+        config.seed = Some(seed);
+        bench.forwarded.push(format!("--seed={}", seed));
+    }
+    println!(
+        "Benchmarking {}x{} cells for {} generations, seed {:?}, {} batching, {} warmup and {} measured runs",
+        config.width,
+        config.height,
+        config.generations,
+        config.seed,
+        config.batching,
+        bench.warmup,
+        bench.repetitions
+    );
+
+    let mut results = Vec::new();
+    for (backend, binary) in &bench.selection.backends() {
+        let mut runs = Vec::with_capacity(bench.repetitions);
+        for repetition in 0..bench.warmup + bench.repetitions {
+            let run = backend::run(*backend, binary, &bench.forwarded).unwrap_or_else(|error| {
+                eprintln!("{}", error);
+                std::process::exit(1);
+            });
+            if repetition >= bench.warmup {
+                runs.push(run);
+            }
+        }
+
+        let result = BackendResult {
+            backend: *backend,
+            runs,
+        };
+        let total = result.total_summary();
+        let generation = result.generation_summary();
+        println!(
+            "{:>11}: total mean {:?} (min {:?}, max {:?}), generation mean {:?} p50 {:?} p99 {:?}",
+            backend.to_string(),
+            total.mean,
+            total.min,
+            total.max,
+            generation.mean,
+            generation.p50,
+            generation.p99
+        );
+        if let Some(stabilization) = result.runs.first().and_then(|run| run.stabilization) {
+            println!(
+                "{:>11}  stabilized at generation {}: {} with period {}",
+                "",
+                stabilization.start(),
+                stabilization.kind(),
+                stabilization.period()
+            );
+        }
+        results.push(result);
+    }
+
+    results::save(&bench.results, &config, bench.warmup, &results)
+        .expect("Unable to write results");
+    println!("Results written to {}", bench.results);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use game_of_life_bench::backend::Backend;
+
+    use super::*;
+
+    fn args(args: &[&str]) -> impl Iterator<Item = String> {
+        args.iter()
+            .map(|arg| arg.to_string())
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+
+    #[test]
+    fn test_parse_args_splits_forwarded_options() {
+        let bench = parse_args(args(&[
+            "--size",
+            "50",
+            "--backends=no_ecs,hecs,bevy_serial",
+            "--warmup",
+            "0",
+            "--hecs-bin",
+            "/bin/hecs",
+            "--bevy-bin",
+            "/bin/bevy",
+            "--seed",
+            "3",
+            "--results",
+            "out.csv",
+        ]));
+
+        assert_eq!(
+            bench.selection.backends(),
+            vec![
+                (Backend::NoEcs, Backend::NoEcs.default_binary()),
+                (Backend::Hecs, PathBuf::from("/bin/hecs")),
+                (Backend::BevySerial, PathBuf::from("/bin/bevy")),
+            ]
+        );
+        assert_eq!(bench.warmup, 0);
+        assert_eq!(bench.repetitions, 5);
+        assert_eq!(bench.results, "out.csv");
+        assert_eq!(bench.forwarded, vec!["--size", "50", "--seed", "3"]);
+    }
+}
@@ -0,0 +1,247 @@
+use std::{fmt::Write as _, path::Path, time::Duration};
+
+use game_of_life_core::{cycle::Stabilization, timing::Summary, Config};
+
+use crate::backend::{Backend, Run};
+
+/// All measured repetitions of one backend, warmup runs excluded.
+pub struct BackendResult {
+    pub backend: Backend,
+    pub runs: Vec<Run>,
+}
+
+impl BackendResult {
+    pub fn total_summary(&self) -> Summary {
+        let totals: Vec<Duration> = self.runs.iter().map(|run| run.total).collect();
+        Summary::new(&totals)
+    }
+
+    pub fn generation_summary(&self) -> Summary {
+        let generations: Vec<Duration> = self
+            .runs
+            .iter()
+            .flat_map(|run| run.generations.iter().copied())
+            .collect();
+        Summary::new(&generations)
+    }
+}
+
+/// Writes the results as JSON or CSV depending on the extension of `filename`.
+pub fn save(
+    filename: &str,
+    config: &Config,
+    warmup: usize,
+    results: &[BackendResult],
+) -> std::io::Result<()> {
+    let contents = match Path::new(filename).extension().and_then(|e| e.to_str()) {
+        Some("csv") => to_csv(config, results),
+        _ => to_json(config, warmup, results),
+    };
+    std::fs::write(filename, contents)
+}
+
+/// One row per generation plus a `total` row per repetition, all in nanoseconds. The
+/// executor, topology, batching and thread count are repeated on every row so runs can be
+/// concatenated. The executor is empty for the backends without one and the thread count
+/// when the compute pool was left at its default.
+fn to_csv(config: &Config, results: &[BackendResult]) -> String {
+    let threads = config
+        .threads
+        .map_or(String::new(), |threads| threads.to_string());
+    let mut csv = String::from(
+        "backend,executor,topology,batching,threads,repetition,generation,nanoseconds\n",
+    );
+    for result in results {
+        let executor = result
+            .backend
+            .executor(config.executor)
+            .map_or(String::new(), |executor| executor.to_string());
+        for (repetition, run) in result.runs.iter().enumerate() {
+            for (generation, duration) in run.generations.iter().enumerate() {
+                let _ = writeln!(
+                    csv,
+                    "{},{},{},{},{},{},{},{}",
+                    result.backend,
+                    executor,
+                    config.topology,
+                    config.batching,
+                    threads,
+                    repetition,
+                    generation,
+                    duration.as_nanos()
+                );
+            }
+            let _ = writeln!(
+                csv,
+                "{},{},{},{},{},{},total,{}",
+                result.backend,
+                executor,
+                config.topology,
+                config.batching,
+                threads,
+                repetition,
+                run.total.as_nanos()
+            );
+        }
+    }
+    csv
+}
+
+pub(crate) fn summary_json(summary: &Summary) -> String {
+    format!(
+        "{{\"count\": {}, \"min_ns\": {}, \"mean_ns\": {}, \"p50_ns\": {}, \"p99_ns\": {}, \"max_ns\": {}, \"stddev_ns\": {}}}",
+        summary.count,
+        summary.min.as_nanos(),
+        summary.mean.as_nanos(),
+        summary.p50.as_nanos(),
+        summary.p99.as_nanos(),
+        summary.max.as_nanos(),
+        summary.stddev.as_nanos()
+    )
+}
+
+pub(crate) fn nanos_json(durations: &[Duration]) -> String {
+    let nanos: Vec<String> = durations.iter().map(|d| d.as_nanos().to_string()).collect();
+    format!("[{}]", nanos.join(", "))
+}
+
+fn optional_json<T: ToString>(value: Option<T>) -> String {
+    value.map_or("null".to_string(), |value| value.to_string())
+}
+
+fn stabilization_json(stabilization: Option<Stabilization>) -> String {
+    match stabilization {
+        Some(stabilization) => format!(
+            "{{\"kind\": \"{}\", \"start\": {}, \"period\": {}}}",
+            stabilization.kind(),
+            stabilization.start(),
+            stabilization.period()
+        ),
+        None => "null".to_string(),
+    }
+}
+
+fn to_json(config: &Config, warmup: usize, results: &[BackendResult]) -> String {
+    let mut json = String::from("{\n");
+    let _ = writeln!(
+        json,
+        "  \"config\": {{\"width\": {}, \"height\": {}, \"generations\": {}, \"seed\": {}, \"density\": {}, \"rule\": \"{}\", \"neighborhood\": \"{}\", \"topology\": \"{}\", \"boundary\": \"{}\", \"universe\": \"{}\", \"threads\": {}, \"batching\": \"{}\", \"cycles\": \"{}\"}},",
+        config.width,
+        config.height,
+        config.generations,
+        optional_json(config.seed),
+        config.density,
+        config.rule,
+        config.neighborhood,
+        config.topology,
+        config.boundary,
+        config.extent,
+        optional_json(config.threads),
+        config.batching,
+        config.cycles
+    );
+    let _ = writeln!(json, "  \"warmup\": {},", warmup);
+    json.push_str("  \"backends\": [\n");
+    for (index, result) in results.iter().enumerate() {
+        json.push_str("    {\n");
+        let _ = writeln!(json, "      \"backend\": \"{}\",", result.backend);
+        let _ = writeln!(
+            json,
+            "      \"executor\": {},",
+            optional_json(
+                result
+                    .backend
+                    .executor(config.executor)
+                    .map(|executor| format!("\"{}\"", executor))
+            )
+        );
+        let _ = writeln!(
+            json,
+            "      \"total\": {},",
+            summary_json(&result.total_summary())
+        );
+        let _ = writeln!(
+            json,
+            "      \"generation\": {},",
+            summary_json(&result.generation_summary())
+        );
+        json.push_str("      \"runs\": [\n");
+        for (repetition, run) in result.runs.iter().enumerate() {
+            let _ = write!(
+                json,
+                "        {{\"total_ns\": {}, \"stabilization\": {}, \"generation_ns\": {}}}",
+                run.total.as_nanos(),
+                stabilization_json(run.stabilization),
+                nanos_json(&run.generations)
+            );
+            json.push_str(if repetition + 1 < result.runs.len() {
+                ",\n"
+            } else {
+                "\n"
+            });
+        }
+        json.push_str("      ]\n");
+        json.push_str(if index + 1 < results.len() {
+            "    },\n"
+        } else {
+            "    }\n"
+        });
+    }
+    json.push_str("  ]\n}\n");
+    json
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_csv_records_the_run_config() {
+        let config = Config::default()
+            .parse_args(["--batching=fixed:256", "--threads=4", "--topology=hex"].map(String::from))
+            .unwrap();
+        let results = [BackendResult {
+            backend: Backend::NoEcs,
+            runs: vec![Run {
+                total: Duration::from_nanos(30),
+                generations: vec![Duration::from_nanos(10), Duration::from_nanos(20)],
+                stabilization: None,
+            }],
+        }];
+
+        let csv = to_csv(&config, &results);
+        assert_eq!(
+            csv.lines().collect::<Vec<_>>(),
+            [
+                "backend,executor,topology,batching,threads,repetition,generation,nanoseconds",
+                "no_ecs,,hexagonal,fixed:256,4,0,0,10",
+                "no_ecs,,hexagonal,fixed:256,4,0,1,20",
+                "no_ecs,,hexagonal,fixed:256,4,0,total,30",
+            ]
+        );
+        let [mut result] = results;
+        result.backend = Backend::BevySerial;
+        let csv = to_csv(&Config::default(), &[result]);
+        assert_eq!(
+            csv.lines().nth(1),
+            Some("bevy_serial,single,square,auto,,0,0,10")
+        );
+    }
+
+    #[test]
+    fn test_json_records_the_executor_per_backend() {
+        let results =
+            [Backend::Bevy, Backend::BevySerial, Backend::NoEcs].map(|backend| BackendResult {
+                backend,
+                runs: Vec::new(),
+            });
+
+        let json = to_json(&Config::default(), 0, &results);
+        let config_line = json.lines().nth(1).unwrap();
+        assert!(config_line.contains("\"topology\": \"square\""));
+        assert!(!config_line.contains("executor"));
+        assert!(json.contains("\"backend\": \"bevy\",\n      \"executor\": \"multi\","));
+        assert!(json.contains("\"backend\": \"bevy_serial\",\n      \"executor\": \"single\","));
+        assert!(json.contains("\"backend\": \"no_ecs\",\n      \"executor\": null,"));
+    }
+}
@@ -0,0 +1,287 @@
+//! Runs one Bevy plugin on compute pools of 1, 2, 4, ... threads and works out how well
+//! it scales against the single-threaded run.
+
+use std::{
+    fmt::{self, Write as _},
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::Duration,
+};
+
+use game_of_life_core::{
+    config::Batching,
+    timing::{self, Summary},
+};
+
+use crate::{
+    backend,
+    results::{nanos_json, summary_json},
+};
+
+/// A Bevy app that takes `--threads` and `--total-time-output`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Target {
+    GameOfLife,
+    Enzyme,
+}
+
+impl Target {
+    /// The release binary `cargo build --release` produces for this target.
+    pub fn default_binary(self) -> PathBuf {
+        let root = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("..");
+        match self {
+            Target::GameOfLife => root.join("target/release/ecs_multithreading"),
+            Target::Enzyme => {
+                root.join("../enzyme_substrate_reaction/target/release/enzyme_substrate_reaction")
+            }
+        }
+    }
+}
+
+impl fmt::Display for Target {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Target::GameOfLife => "game_of_life",
+            Target::Enzyme => "enzyme",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for Target {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "game_of_life" | "game-of-life" | "gol" | "bevy" => Ok(Target::GameOfLife),
+            "enzyme" | "enzyme_substrate_reaction" => Ok(Target::Enzyme),
+            _ => Err(format!(
+                "unknown target {:?}, expected game_of_life or enzyme",
+                s
+            )),
+        }
+    }
+}
+
+/// Powers of two up to `max`, and `max` itself when it is not one.
+pub fn thread_counts(max: usize) -> Vec<usize> {
+    let mut counts = Vec::new();
+    let mut threads = 1;
+    while threads < max {
+        counts.push(threads);
+        threads *= 2;
+    }
+    counts.push(max.max(1));
+    counts
+}
+
+/// Runs `binary` on a compute pool of `threads` and reads back its total time.
+pub fn run(
+    target: Target,
+    binary: &Path,
+    args: &[String],
+    threads: usize,
+) -> Result<Duration, String> {
+    let total_time_output = backend::scratch_file(target, "total_time.txt");
+
+    let mut args = args.to_vec();
+    args.extend([
+        "--threads".to_string(),
+        threads.to_string(),
+        "--total-time-output".to_string(),
+        total_time_output.clone(),
+    ]);
+    backend::execute(target, binary, &args)?;
+
+    let total = std::fs::read_to_string(&total_time_output)
+        .ok()
+        .and_then(|total| timing::parse_duration(&total))
+        .ok_or_else(|| format!("{} did not write its total time", target))?;
+    let _ = std::fs::remove_file(total_time_output);
+    Ok(total)
+}
+
+/// The measured runs at one thread count, warmup runs excluded.
+pub struct Step {
+    pub threads: usize,
+    pub runs: Vec<Duration>,
+}
+
+impl Step {
+    pub fn summary(&self) -> Summary {
+        Summary::new(&self.runs)
+    }
+}
+
+/// How one thread count compares to a single thread, from the mean total times.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Scaling {
+    pub threads: usize,
+    /// `T1 / Tp`.
+    pub speedup: f64,
+    /// The speedup per thread, 1 for perfect scaling.
+    pub efficiency: f64,
+    /// The Karp–Flatt metric `(1/S - 1/p) / (1 - 1/p)`, the fraction of the work that
+    /// behaved as serial. Undefined for a single thread.
+    pub serial_fraction: Option<f64>,
+}
+
+impl Scaling {
+    pub fn new(threads: usize, serial: Duration, parallel: Duration) -> Self {
+        let speedup = serial.as_secs_f64() / parallel.as_secs_f64();
+        let p = threads as f64;
+        Scaling {
+            threads,
+            speedup,
+            efficiency: speedup / p,
+            serial_fraction: (threads > 1).then(|| (1.0 / speedup - 1.0 / p) / (1.0 - 1.0 / p)),
+        }
+    }
+}
+
+/// Every step against the single-threaded one, which the sweep always starts with.
+pub fn scaling(steps: &[Step]) -> Vec<Scaling> {
+    let Some(serial) = steps.iter().find(|step| step.threads == 1) else {
+        return Vec::new();
+    };
+    let serial = serial.summary().mean;
+    steps
+        .iter()
+        .map(|step| Scaling::new(step.threads, serial, step.summary().mean))
+        .collect()
+}
+
+/// How the sweep was run, recorded next to its results.
+pub struct Setup<'a> {
+    pub target: Target,
+    pub batching: Batching,
+    /// The options every run was given besides `--threads`.
+    pub args: &'a [String],
+    pub warmup: usize,
+}
+
+/// Writes the sweep as JSON or CSV depending on the extension of `filename`.
+pub fn save(filename: &str, setup: &Setup, steps: &[Step]) -> std::io::Result<()> {
+    let contents = match Path::new(filename).extension().and_then(|e| e.to_str()) {
+        Some("csv") => to_csv(setup, steps),
+        _ => to_json(setup, steps),
+    };
+    std::fs::write(filename, contents)
+}
+
+fn optional_float(value: Option<f64>) -> String {
+    value.map_or("null".to_string(), |value| format!("{:.6}", value))
+}
+
+/// One row per thread count, times in nanoseconds. The target and batching are repeated
+/// on every row so sweeps can be concatenated.
+fn to_csv(setup: &Setup, steps: &[Step]) -> String {
+    let mut csv = String::from(
+        "target,batching,threads,runs,mean_ns,min_ns,max_ns,speedup,efficiency,serial_fraction\n",
+    );
+    for (step, scaling) in steps.iter().zip(scaling(steps)) {
+        let summary = step.summary();
+        let _ = writeln!(
+            csv,
+            "{},{},{},{},{},{},{},{:.6},{:.6},{}",
+            setup.target,
+            setup.batching,
+            step.threads,
+            summary.count,
+            summary.mean.as_nanos(),
+            summary.min.as_nanos(),
+            summary.max.as_nanos(),
+            scaling.speedup,
+            scaling.efficiency,
+            scaling
+                .serial_fraction
+                .map_or(String::new(), |e| format!("{:.6}", e))
+        );
+    }
+    csv
+}
+
+fn to_json(setup: &Setup, steps: &[Step]) -> String {
+    let args: Vec<String> = setup
+        .args
+        .iter()
+        .map(|arg| format!("\"{}\"", arg.replace('\\', "\\\\").replace('"', "\\\"")))
+        .collect();
+    let mut json = String::from("{\n");
+    let _ = writeln!(json, "  \"target\": \"{}\",", setup.target);
+    let _ = writeln!(json, "  \"batching\": \"{}\",", setup.batching);
+    let _ = writeln!(json, "  \"args\": [{}],", args.join(", "));
+    let _ = writeln!(json, "  \"warmup\": {},", setup.warmup);
+    json.push_str("  \"steps\": [\n");
+    for (index, (step, scaling)) in steps.iter().zip(scaling(steps)).enumerate() {
+        let _ = write!(
+            json,
+            "    {{\"threads\": {}, \"speedup\": {:.6}, \"efficiency\": {:.6}, \"serial_fraction\": {}, \"total\": {}, \"runs_ns\": {}}}",
+            step.threads,
+            scaling.speedup,
+            scaling.efficiency,
+            optional_float(scaling.serial_fraction),
+            summary_json(&step.summary()),
+            nanos_json(&step.runs)
+        );
+        json.push_str(if index + 1 < steps.len() { ",\n" } else { "\n" });
+    }
+    json.push_str("  ]\n}\n");
+    json
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_thread_counts() {
+        assert_eq!(thread_counts(1), [1]);
+        assert_eq!(thread_counts(0), [1]);
+        assert_eq!(thread_counts(8), [1, 2, 4, 8]);
+        assert_eq!(thread_counts(12), [1, 2, 4, 8, 12]);
+    }
+
+    #[test]
+    fn test_scaling_metrics() {
+        let seconds = |s: f64| Duration::from_secs_f64(s);
+        let steps = [
+            Step {
+                threads: 1,
+                runs: vec![seconds(7.0), seconds(9.0)],
+            },
+            Step {
+                threads: 4,
+                runs: vec![seconds(2.5)],
+            },
+        ];
+        let scaling = scaling(&steps);
+        assert_eq!(scaling[0].speedup, 1.0);
+        assert_eq!(scaling[0].serial_fraction, None);
+        assert!((scaling[1].speedup - 3.2).abs() < 1e-9);
+        assert!((scaling[1].efficiency - 0.8).abs() < 1e-9);
+        // (1/3.2 - 1/4) / (1 - 1/4)
+        assert!((scaling[1].serial_fraction.unwrap() - 1.0 / 12.0).abs() < 1e-9);
+
+        let args = ["--frames=5".to_string(), "--batching=fixed:256".to_string()];
+        let setup = Setup {
+            target: Target::Enzyme,
+            batching: Batching::Fixed(256),
+            args: &args[..1],
+            warmup: 1,
+        };
+        let csv = to_csv(&setup, &steps);
+        assert_eq!(
+            csv.lines().nth(2),
+            Some(
+                "enzyme,fixed:256,4,1,2500000000,2500000000,2500000000,3.200000,0.800000,0.083333"
+            )
+        );
+        let json = to_json(&setup, &steps);
+        assert!(json.contains("\"target\": \"enzyme\""));
+        assert!(json.contains("\"batching\": \"fixed:256\""));
+        assert!(json.contains("\"args\": [\"--frames=5\"]"));
+        assert!(json.contains("\"threads\": 1, \"speedup\": 1.000000, \"efficiency\": 1.000000, \"serial_fraction\": null"));
+        assert_eq!("gol".parse(), Ok(Target::GameOfLife));
+    }
+}
@@ -0,0 +1,295 @@
+use std::{collections::HashMap, fmt, str::FromStr};
+
+use crate::{neighborhood::Offset, topology::Stencil};
+
+/// How neighbour lookups behave past the edges of a `width x height` grid.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Boundary {
+    /// Cells outside the grid are always dead.
+    #[default]
+    Dead,
+    /// Both pairs of edges wrap around.
+    Torus,
+    /// Cells outside the grid mirror the cells just inside the edge.
+    Mirror,
+    /// Left and right edges wrap, top and bottom wrap with the x axis flipped.
+    KleinBottle,
+}
+
+impl Boundary {
+    pub const ALL: [Boundary; 4] = [
+        Boundary::Dead,
+        Boundary::Torus,
+        Boundary::Mirror,
+        Boundary::KleinBottle,
+    ];
+
+    /// Maps a possibly out-of-range coordinate onto the grid.
+    ///
+    /// Returns `None` when the coordinate has no cell behind it, which only
+    /// happens for `Boundary::Dead`.
+    pub fn resolve(self, x: i32, y: i32, width: u32, height: u32) -> Option<(i32, i32)> {
+        let width = width as i32;
+        let height = height as i32;
+        if width == 0 || height == 0 {
+            return None;
+        }
+
+        let inside = |value: i32, size: i32| value >= 0 && value < size;
+        match self {
+            Boundary::Dead => (inside(x, width) && inside(y, height)).then_some((x, y)),
+            Boundary::Torus => Some((x.rem_euclid(width), y.rem_euclid(height))),
+            Boundary::Mirror => Some((reflect(x, width), reflect(y, height))),
+            Boundary::KleinBottle => {
+                let x = if y.div_euclid(height) % 2 != 0 {
+                    width - 1 - x.rem_euclid(width)
+                } else {
+                    x.rem_euclid(width)
+                };
+                Some((x, y.rem_euclid(height)))
+            }
+        }
+    }
+
+    /// The eight neighbours of `(x, y)` after resolving, skipping the ones that fall off a
+    /// dead edge. A cell can appear more than once, or be its own neighbour, on small or
+    /// mirrored grids.
+    pub fn neighbors(
+        self,
+        x: i32,
+        y: i32,
+        width: u32,
+        height: u32,
+    ) -> impl Iterator<Item = (i32, i32)> {
+        (-1..=1)
+            .flat_map(|dy| (-1..=1).map(move |dx| (dx, dy)))
+            .filter(|offset| *offset != (0, 0))
+            .filter_map(move |(dx, dy)| self.resolve(x + dx, y + dy, width, height))
+    }
+
+    /// The cells at `offsets` around `(x, y)` after resolving, with their weights, skipping
+    /// the ones that fall off a dead edge.
+    pub fn neighbors_in(
+        self,
+        x: i32,
+        y: i32,
+        width: u32,
+        height: u32,
+        offsets: &[Offset],
+    ) -> impl Iterator<Item = ((i32, i32), u16)> + '_ {
+        offsets.iter().filter_map(move |((dx, dy), weight)| {
+            self.resolve(x + dx, y + dy, width, height)
+                .map(|cell| (cell, *weight))
+        })
+    }
+
+    /// Every cell that counts `(x, y)` among its neighbours in `stencil`, together with the
+    /// total weight it gives it. A change to `(x, y)` moves exactly these neighbour counts.
+    pub fn counted_by(
+        self,
+        x: i32,
+        y: i32,
+        width: u32,
+        height: u32,
+        stencil: &Stencil,
+    ) -> Vec<((i32, i32), u16)> {
+        let (w, h) = (width as i32, height as i32);
+        if w == 0 || h == 0 {
+            return Vec::new();
+        }
+
+        // Coordinates that resolve to `x` are `x` itself modulo the width or, within range
+        // of a mirrored or flipped edge, its mirror image; likewise for `y`.
+        let range = stencil.range();
+        let near_edge = |value: i32, size: i32| value < range || value >= size - range;
+        let mut xs = vec![x];
+        let mut ys = vec![y];
+        match self {
+            Boundary::Dead | Boundary::Torus => {}
+            Boundary::Mirror => {
+                if near_edge(x, w) {
+                    xs.push(-1 - x);
+                }
+                if near_edge(y, h) {
+                    ys.push(-1 - y);
+                }
+            }
+            Boundary::KleinBottle => {
+                if near_edge(y, h) {
+                    xs.push(w - 1 - x);
+                }
+            }
+        }
+
+        let mut weights: HashMap<(i32, i32), u16> = HashMap::new();
+        let mut candidates = Vec::with_capacity(xs.len() * ys.len());
+        for (parity, offsets) in stencil.by_parity().into_iter().enumerate() {
+            for ((dx, dy), weight) in offsets {
+                candidates.clear();
+                for source_x in &xs {
+                    for source_y in &ys {
+                        let candidate =
+                            ((source_x - dx).rem_euclid(w), (source_y - dy).rem_euclid(h));
+                        // Only cells of the matching parity use these offsets.
+                        if (candidate.0 + candidate.1).rem_euclid(2) as usize == parity
+                            && !candidates.contains(&candidate)
+                        {
+                            candidates.push(candidate);
+                        }
+                    }
+                }
+                for (cell_x, cell_y) in &candidates {
+                    if self.resolve(cell_x + dx, cell_y + dy, width, height) == Some((x, y)) {
+                        *weights.entry((*cell_x, *cell_y)).or_default() += weight;
+                    }
+                }
+            }
+        }
+
+        let mut cells: Vec<((i32, i32), u16)> = weights.into_iter().collect();
+        cells.sort_unstable();
+        cells
+    }
+}
+
+/// Reflects `value` back into `0..size`, repeating the edge cell (`-1 -> 0`, `size -> size - 1`).
+fn reflect(value: i32, size: i32) -> i32 {
+    let period = 2 * size;
+    let value = value.rem_euclid(period);
+    if value < size {
+        value
+    } else {
+        period - 1 - value
+    }
+}
+
+impl fmt::Display for Boundary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Boundary::Dead => "dead",
+            Boundary::Torus => "torus",
+            Boundary::Mirror => "mirror",
+            Boundary::KleinBottle => "klein",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseBoundaryError(pub String);
+
+impl fmt::Display for ParseBoundaryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "unknown boundary {:?}, expected one of dead, torus, mirror, klein",
+            self.0
+        )
+    }
+}
+
+impl std::error::Error for ParseBoundaryError {}
+
+impl FromStr for Boundary {
+    type Err = ParseBoundaryError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "dead" => Ok(Boundary::Dead),
+            "torus" | "wrap" => Ok(Boundary::Torus),
+            "mirror" | "reflect" => Ok(Boundary::Mirror),
+            "klein" | "kleinbottle" | "klein-bottle" => Ok(Boundary::KleinBottle),
+            _ => Err(ParseBoundaryError(s.to_string())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{neighborhood::Neighborhood, topology::Topology};
+
+    #[test]
+    fn test_dead() {
+        assert_eq!(Boundary::Dead.resolve(0, 0, 4, 3), Some((0, 0)));
+        assert_eq!(Boundary::Dead.resolve(-1, 0, 4, 3), None);
+        assert_eq!(Boundary::Dead.resolve(0, 3, 4, 3), None);
+    }
+
+    #[test]
+    fn test_torus() {
+        assert_eq!(Boundary::Torus.resolve(-1, -1, 4, 3), Some((3, 2)));
+        assert_eq!(Boundary::Torus.resolve(4, 3, 4, 3), Some((0, 0)));
+    }
+
+    #[test]
+    fn test_mirror() {
+        assert_eq!(Boundary::Mirror.resolve(-1, 1, 4, 3), Some((0, 1)));
+        assert_eq!(Boundary::Mirror.resolve(4, 3, 4, 3), Some((3, 2)));
+        assert_eq!(Boundary::Mirror.resolve(-2, 1, 4, 3), Some((1, 1)));
+    }
+
+    #[test]
+    fn test_klein_bottle() {
+        assert_eq!(Boundary::KleinBottle.resolve(-1, 1, 4, 3), Some((3, 1)));
+        assert_eq!(Boundary::KleinBottle.resolve(0, -1, 4, 3), Some((3, 2)));
+        assert_eq!(Boundary::KleinBottle.resolve(1, 3, 4, 3), Some((2, 0)));
+    }
+
+    #[test]
+    fn test_counted_by_inverts_neighbors() {
+        let neighborhoods: [Neighborhood; 4] = [
+            Neighborhood::default(),
+            Neighborhood::Moore(2),
+            Neighborhood::VonNeumann(3),
+            "mask:00100/00030/12000/00000/00002".parse().unwrap(),
+        ];
+        let stencils = neighborhoods.iter().map(Stencil::from).chain(
+            [Topology::Hexagonal, Topology::Triangular]
+                .map(|topology| topology.stencil(&Neighborhood::default())),
+        );
+        for stencil in stencils {
+            for boundary in Boundary::ALL {
+                for (width, height) in [(1, 1), (2, 2), (4, 3), (5, 1), (7, 6)] {
+                    for y in 0..height as i32 {
+                        for x in 0..width as i32 {
+                            let counted_by = boundary.counted_by(x, y, width, height, &stencil);
+                            for cell_y in 0..height as i32 {
+                                for cell_x in 0..width as i32 {
+                                    let expected: u16 = boundary
+                                        .neighbors_in(
+                                            cell_x,
+                                            cell_y,
+                                            width,
+                                            height,
+                                            stencil.at(cell_x, cell_y),
+                                        )
+                                        .filter(|(neighbor, _)| *neighbor == (x, y))
+                                        .map(|(_, weight)| weight)
+                                        .sum();
+                                    let actual = counted_by
+                                        .iter()
+                                        .find(|(cell, _)| *cell == (cell_x, cell_y))
+                                        .map_or(0, |(_, weight)| *weight);
+                                    assert_eq!(
+                                        actual, expected,
+                                        "{:?} {} {}x{}: ({}, {}) counted by ({}, {})",
+                                        stencil, boundary, width, height, x, y, cell_x, cell_y
+                                    );
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_parse() {
+        for boundary in Boundary::ALL {
+            assert_eq!(boundary.to_string().parse::<Boundary>(), Ok(boundary));
+        }
+        assert!("sphere".parse::<Boundary>().is_err());
+    }
+}
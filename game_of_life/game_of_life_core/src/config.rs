@@ -0,0 +1,1061 @@
+use std::{fmt, ops::Range, str::FromStr};
+
+use crate::{cycle::CycleDetector, neighborhood, Boundary, Neighborhood, Rule, Soup, Topology};
+
+pub const USAGE: &str = "Options:
+    --width <cells>              grid width (default 600)
+    --height <cells>             grid height (default 400)
+    --size <cells>               sets both width and height
+    --generations <count>        generations to simulate, e.g. 500 or 2^40 (default 100)
+    --seed <number>              seed for the initial soup (default: random)
+    --threads <count>            compute threads, ignored by single-threaded backends
+    --cell-index <kind>          map or dense cell lookup in the Bevy backend (default map)
+    --neighbors <mode>           brute or incremental neighbour counts in the Bevy backend
+    --batching <strategy>        auto, fixed:<items> or per-thread:<batches> for the Bevy
+                                 backend's parallel queries (default auto)
+    --executor <kind>            multi or single threaded Bevy Update schedule, single also
+                                 runs parallel queries serially on one thread (default multi)
+    --density <0..1>             chance of a cell starting alive (default 0.5)
+    --rule <rulestring>          birth/survival rule, e.g. B36/S23, B2/S/3, B34..45/S33..57
+                                 or R5,C0,M1,S34..58,B34..45,NM (default B3/S23)
+    --neighborhood <kind>        moore:R, vonneumann:R or a weighted mask:121/202/121
+                                 (default moore:1)
+    --topology <tiling>          square, hexagonal or triangular cells (default square)
+    --boundary <mode>            dead, torus, mirror or klein (default dead)
+    --universe <extent>          bounded grid or unbounded plane of chunks (default bounded)
+    --pattern <file>             start from a .rle, .cells, .lif or .txt pattern
+    --pattern-x <cells>          x offset of the pattern (default 0)
+    --pattern-y <cells>          y offset of the pattern (default 0)
+    --durations-output <file>    write per-generation durations
+    --total-time-output <file>   write the time the generation loop took, without setup
+                                 or output
+    --output <file>              save the final grid as a pattern file
+    --cycles <mode>              off, report or stop when the run dies out or starts
+                                 repeating (default off)
+    --cycle-window <count>       generations remembered to spot repeats (default 256)
+    --stabilization-output <file>
+                                 write how and when the run stabilised
+    --stats-output <file>        write population, births, deaths and bounds per
+                                 generation, as .csv or .json
+    --snapshot-output <file>     save the whole grid and rule at the end of a Bevy run
+    --resume <file>              continue a Bevy run from a saved snapshot
+    --system-timings-output <file>
+                                 write min, mean, p50 and p99 per Bevy system, as .csv
+                                 or .json
+    --trace-output <file>        write a Chrome trace of every Bevy system run and parallel
+                                 batch, per thread
+    --history-budget <bytes>     memory for rewinding a Bevy run, e.g. 65536, 512K or
+                                 64M (default off)
+    --help                       print this message";
+
+const FLAGS: [&str; 31] = [
+    "--width",
+    "--height",
+    "--size",
+    "--generations",
+    "--seed",
+    "--threads",
+    "--cell-index",
+    "--neighbors",
+    "--batching",
+    "--executor",
+    "--density",
+    "--rule",
+    "--neighborhood",
+    "--topology",
+    "--boundary",
+    "--universe",
+    "--pattern",
+    "--pattern-x",
+    "--pattern-y",
+    "--durations-output",
+    "--total-time-output",
+    "--output",
+    "--cycles",
+    "--cycle-window",
+    "--stabilization-output",
+    "--stats-output",
+    "--snapshot-output",
+    "--resume",
+    "--history-budget",
+    "--system-timings-output",
+    "--trace-output",
+];
+
+/// How the Bevy backend stores the grid that neighbour counting reads from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CellIndex {
+    /// A `HashMap` keyed by position.
+    #[default]
+    Map,
+    /// A flat `Vec<u8>` indexed by `y * width + x`.
+    Dense,
+}
+
+impl fmt::Display for CellIndex {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CellIndex::Map => write!(f, "map"),
+            CellIndex::Dense => write!(f, "dense"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseCellIndexError(pub String);
+
+impl fmt::Display for ParseCellIndexError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown cell index {:?}, expected map or dense", self.0)
+    }
+}
+
+impl std::error::Error for ParseCellIndexError {}
+
+impl FromStr for CellIndex {
+    type Err = ParseCellIndexError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "map" | "hashmap" => Ok(CellIndex::Map),
+            "dense" | "vec" => Ok(CellIndex::Dense),
+            _ => Err(ParseCellIndexError(s.to_string())),
+        }
+    }
+}
+
+/// How the Bevy backend keeps `Neighbors` up to date.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NeighborCounting {
+    /// Recount all eight neighbours of every cell each generation.
+    #[default]
+    BruteForce,
+    /// Only cells whose state changed push +1/-1 to the cells around them.
+    Incremental,
+}
+
+impl fmt::Display for NeighborCounting {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NeighborCounting::BruteForce => write!(f, "brute"),
+            NeighborCounting::Incremental => write!(f, "incremental"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseNeighborCountingError(pub String);
+
+impl fmt::Display for ParseNeighborCountingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "unknown neighbour counting {:?}, expected brute or incremental",
+            self.0
+        )
+    }
+}
+
+impl std::error::Error for ParseNeighborCountingError {}
+
+impl FromStr for NeighborCounting {
+    type Err = ParseNeighborCountingError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "brute" | "brute-force" => Ok(NeighborCounting::BruteForce),
+            "incremental" => Ok(NeighborCounting::Incremental),
+            _ => Err(ParseNeighborCountingError(s.to_string())),
+        }
+    }
+}
+
+/// How a Bevy parallel query is split into batches for the compute threads.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Batching {
+    /// Bevy's own heuristic, one batch per thread sized from the query length.
+    #[default]
+    Auto,
+    /// Batches of exactly this many items.
+    Fixed(usize),
+    /// This many batches per compute thread, so faster threads can pick up the slack.
+    PerThread(usize),
+}
+
+impl fmt::Display for Batching {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Batching::Auto => write!(f, "auto"),
+            Batching::Fixed(items) => write!(f, "fixed:{}", items),
+            Batching::PerThread(batches) => write!(f, "per-thread:{}", batches),
+        }
+    }
+}
+
+impl Batching {
+    /// The smallest and largest batch in items, as Bevy's `BatchingStrategy` takes them.
+    pub fn batch_size_limits(&self) -> Range<usize> {
+        match self {
+            Batching::Fixed(items) => *items..*items,
+            Batching::Auto | Batching::PerThread(_) => 1..usize::MAX,
+        }
+    }
+
+    /// How many batches each compute thread gets at most.
+    pub fn batches_per_thread(&self) -> usize {
+        match self {
+            Batching::PerThread(batches) => *batches,
+            Batching::Auto | Batching::Fixed(_) => 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseBatchingError(pub String);
+
+impl fmt::Display for ParseBatchingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "unknown batching {:?}, expected auto, fixed:<items> or per-thread:<batches>",
+            self.0
+        )
+    }
+}
+
+impl std::error::Error for ParseBatchingError {}
+
+impl FromStr for Batching {
+    type Err = ParseBatchingError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = || ParseBatchingError(s.to_string());
+        let normalized = s.trim().to_ascii_lowercase();
+        let (kind, count) = match normalized.split_once(':') {
+            Some((kind, count)) => {
+                let count: usize = count.parse().map_err(|_| error())?;
+                if count == 0 {
+                    return Err(error());
+                }
+                (kind, Some(count))
+            }
+            None => (normalized.as_str(), None),
+        };
+        match (kind, count) {
+            ("auto" | "default", None) => Ok(Batching::Auto),
+            ("fixed", Some(items)) => Ok(Batching::Fixed(items)),
+            ("per-thread" | "per_thread", Some(batches)) => Ok(Batching::PerThread(batches)),
+            _ => Err(error()),
+        }
+    }
+}
+
+/// Which Bevy executor runs the `Update` schedule.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Executor {
+    /// Systems that do not conflict run at the same time, parallel queries use every
+    /// compute thread.
+    #[default]
+    MultiThreaded,
+    /// One system after another on the main thread, with a single compute thread so
+    /// parallel queries run serially too. The ECS without any parallelism.
+    SingleThreaded,
+}
+
+impl fmt::Display for Executor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Executor::MultiThreaded => write!(f, "multi"),
+            Executor::SingleThreaded => write!(f, "single"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseExecutorError(pub String);
+
+impl fmt::Display for ParseExecutorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown executor {:?}, expected multi or single", self.0)
+    }
+}
+
+impl std::error::Error for ParseExecutorError {}
+
+impl Executor {
+    /// The size of the compute pool for `--threads`, `None` for one thread per core. The
+    /// single threaded executor always gets one, whatever `--threads` says.
+    pub fn compute_threads(self, threads: Option<usize>) -> Option<usize> {
+        match self {
+            Executor::MultiThreaded => threads,
+            Executor::SingleThreaded => Some(1),
+        }
+    }
+}
+
+impl FromStr for Executor {
+    type Err = ParseExecutorError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "multi" | "multi-threaded" | "parallel" => Ok(Executor::MultiThreaded),
+            "single" | "single-threaded" | "serial" => Ok(Executor::SingleThreaded),
+            _ => Err(ParseExecutorError(s.to_string())),
+        }
+    }
+}
+
+/// Whether the simulation is confined to the `width x height` grid.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Extent {
+    /// A fixed grid whose edges behave according to the `Boundary`.
+    #[default]
+    Bounded,
+    /// An infinite plane that only stores chunks with alive cells. The grid size then
+    /// only bounds the initial soup.
+    Unbounded,
+}
+
+impl fmt::Display for Extent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Extent::Bounded => write!(f, "bounded"),
+            Extent::Unbounded => write!(f, "unbounded"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseExtentError(pub String);
+
+impl fmt::Display for ParseExtentError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "unknown universe {:?}, expected bounded or unbounded",
+            self.0
+        )
+    }
+}
+
+impl std::error::Error for ParseExtentError {}
+
+impl FromStr for Extent {
+    type Err = ParseExtentError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "bounded" | "grid" => Ok(Extent::Bounded),
+            "unbounded" | "infinite" | "sparse" => Ok(Extent::Unbounded),
+            _ => Err(ParseExtentError(s.to_string())),
+        }
+    }
+}
+
+/// What to do once the run dies out, freezes or starts repeating itself.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CycleDetection {
+    /// Do not hash generations at all.
+    #[default]
+    Off,
+    /// Report the stabilisation and keep running to `--generations`.
+    Report,
+    /// Report the stabilisation and stop the run there.
+    Stop,
+}
+
+impl fmt::Display for CycleDetection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CycleDetection::Off => write!(f, "off"),
+            CycleDetection::Report => write!(f, "report"),
+            CycleDetection::Stop => write!(f, "stop"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseCycleDetectionError(pub String);
+
+impl fmt::Display for ParseCycleDetectionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "unknown cycle detection {:?}, expected off, report or stop",
+            self.0
+        )
+    }
+}
+
+impl std::error::Error for ParseCycleDetectionError {}
+
+impl FromStr for CycleDetection {
+    type Err = ParseCycleDetectionError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "off" | "none" => Ok(CycleDetection::Off),
+            "report" | "detect" => Ok(CycleDetection::Report),
+            "stop" | "exit" => Ok(CycleDetection::Stop),
+            _ => Err(ParseCycleDetectionError(s.to_string())),
+        }
+    }
+}
+
+/// Run parameters shared by every Game of Life backend, so one script can sweep them all.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Config {
+    pub width: u32,
+    pub height: u32,
+    pub generations: u64,
+    pub seed: Option<u64>,
+    pub threads: Option<usize>,
+    pub cell_index: CellIndex,
+    pub neighbor_counting: NeighborCounting,
+    pub batching: Batching,
+    pub executor: Executor,
+    pub density: f64,
+    pub rule: Rule,
+    pub neighborhood: Neighborhood,
+    pub topology: Topology,
+    pub boundary: Boundary,
+    pub extent: Extent,
+    pub pattern: Option<String>,
+    pub pattern_offset: (i32, i32),
+    pub durations_output: Option<String>,
+    pub total_time_output: Option<String>,
+    pub output: Option<String>,
+    pub cycles: CycleDetection,
+    pub cycle_window: usize,
+    pub stabilization_output: Option<String>,
+    pub stats_output: Option<String>,
+    pub snapshot_output: Option<String>,
+    /// A snapshot to continue from, which replaces the grid, rule and soup options.
+    pub resume: Option<String>,
+    /// Bytes of past generations kept for rewinding, `None` to keep none.
+    pub history_budget: Option<usize>,
+    pub system_timings_output: Option<String>,
+    pub trace_output: Option<String>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            width: 600,
+            height: 400,
+            generations: 100,
+            seed: None,
+            threads: None,
+            cell_index: CellIndex::default(),
+            neighbor_counting: NeighborCounting::default(),
+            batching: Batching::default(),
+            executor: Executor::default(),
+            density: 0.5,
+            rule: Rule::default(),
+            neighborhood: Neighborhood::default(),
+            topology: Topology::default(),
+            boundary: Boundary::default(),
+            extent: Extent::default(),
+            pattern: None,
+            pattern_offset: (0, 0),
+            durations_output: None,
+            total_time_output: None,
+            output: None,
+            cycles: CycleDetection::default(),
+            cycle_window: 256,
+            stabilization_output: None,
+            stats_output: None,
+            snapshot_output: None,
+            resume: None,
+            history_budget: None,
+            system_timings_output: None,
+            trace_output: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigError {
+    HelpRequested,
+    UnknownFlag(String),
+    MissingValue(String),
+    InvalidValue {
+        flag: String,
+        value: String,
+    },
+    /// Options that are fine on their own but cannot be combined.
+    Unsupported(String),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::HelpRequested => write!(f, "help requested"),
+            ConfigError::UnknownFlag(flag) => write!(f, "unknown option {}", flag),
+            ConfigError::MissingValue(flag) => write!(f, "missing value for {}", flag),
+            ConfigError::InvalidValue { flag, value } => {
+                write!(f, "invalid value {:?} for {}", value, flag)
+            }
+            ConfigError::Unsupported(reason) => write!(f, "{}", reason),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+/// Splits `--flag value` and `--flag=value` options, handing every flag listed in `flags`
+/// and its value to `set`.
+pub fn parse_flags(
+    args: impl IntoIterator<Item = String>,
+    flags: &[&str],
+    mut set: impl FnMut(&str, &str) -> Result<(), ConfigError>,
+) -> Result<(), ConfigError> {
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let (flag, inline_value) = match arg.split_once('=') {
+            Some((flag, value)) => (flag.to_string(), Some(value.to_string())),
+            None => (arg, None),
+        };
+        if flag == "--help" || flag == "-h" {
+            return Err(ConfigError::HelpRequested);
+        }
+        if !flags.contains(&flag.as_str()) {
+            return Err(ConfigError::UnknownFlag(flag));
+        }
+
+        let value = inline_value
+            .or_else(|| args.next())
+            .ok_or_else(|| ConfigError::MissingValue(flag.clone()))?;
+        set(&flag, &value)?;
+    }
+    Ok(())
+}
+
+/// Parses the value of `flag`, reporting it as invalid when it does not parse.
+pub fn parse_value<T: FromStr>(flag: &str, value: &str) -> Result<T, ConfigError> {
+    value.parse().map_err(|_| ConfigError::InvalidValue {
+        flag: flag.to_string(),
+        value: value.to_string(),
+    })
+}
+
+/// Unwraps options parsed from the process arguments, printing `usage` and exiting on
+/// errors.
+pub fn exit_on_error<T>(parsed: Result<T, ConfigError>, usage: &str) -> T {
+    let program = std::env::args().next().unwrap_or_default();
+    match parsed {
+        Ok(parsed) => parsed,
+        Err(ConfigError::HelpRequested) => {
+            println!("Usage: {} [options]\n\n{}", program, usage);
+            std::process::exit(0);
+        }
+        Err(error) => {
+            eprintln!("{}\n\nUsage: {} [options]\n\n{}", error, program, usage);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// A side of the grid, which has to hold at least one cell.
+fn parse_side(flag: &str, value: &str) -> Result<u32, ConfigError> {
+    match parse_value(flag, value)? {
+        0 => Err(ConfigError::InvalidValue {
+            flag: flag.to_string(),
+            value: value.to_string(),
+        }),
+        side => Ok(side),
+    }
+}
+
+/// Accepts a plain count or a power written as `2^40`.
+fn parse_generations(flag: &str, value: &str) -> Result<u64, ConfigError> {
+    match value.split_once('^') {
+        Some((base, exponent)) => {
+            let base: u64 = parse_value(flag, base)?;
+            let exponent: u32 = parse_value(flag, exponent)?;
+            base.checked_pow(exponent)
+                .ok_or_else(|| ConfigError::InvalidValue {
+                    flag: flag.to_string(),
+                    value: value.to_string(),
+                })
+        }
+        None => parse_value(flag, value),
+    }
+}
+
+/// Accepts a byte count with an optional binary `K`, `M` or `G` suffix.
+fn parse_bytes(flag: &str, value: &str) -> Result<usize, ConfigError> {
+    let (number, shift) = match value.char_indices().last() {
+        Some((index, 'K' | 'k')) => (&value[..index], 10),
+        Some((index, 'M' | 'm')) => (&value[..index], 20),
+        Some((index, 'G' | 'g')) => (&value[..index], 30),
+        _ => (value, 0),
+    };
+    let number: usize = parse_value(flag, number)?;
+    number
+        .checked_mul(1 << shift)
+        .ok_or_else(|| ConfigError::InvalidValue {
+            flag: flag.to_string(),
+            value: value.to_string(),
+        })
+}
+
+impl Config {
+    /// Parses `--flag value` and `--flag=value` options, starting from `self`.
+    pub fn parse_args(
+        mut self,
+        args: impl IntoIterator<Item = String>,
+    ) -> Result<Self, ConfigError> {
+        parse_flags(args, &FLAGS, |flag, value| {
+            match flag {
+                "--width" => self.width = parse_side(flag, value)?,
+                "--height" => self.height = parse_side(flag, value)?,
+                "--size" => {
+                    self.width = parse_side(flag, value)?;
+                    self.height = self.width;
+                }
+                "--generations" => self.generations = parse_generations(flag, value)?,
+                "--seed" => self.seed = Some(parse_value(flag, value)?),
+                "--threads" => self.threads = Some(parse_value(flag, value)?),
+                "--cell-index" => self.cell_index = parse_value(flag, value)?,
+                "--neighbors" => self.neighbor_counting = parse_value(flag, value)?,
+                "--batching" => self.batching = parse_value(flag, value)?,
+                "--executor" => self.executor = parse_value(flag, value)?,
+                "--density" => {
+                    self.density = parse_value(flag, value)?;
+                    if !(0.0..=1.0).contains(&self.density) {
+                        return Err(ConfigError::InvalidValue {
+                            flag: flag.to_string(),
+                            value: value.to_string(),
+                        });
+                    }
+                }
+                // Larger than Life rulestrings also carry their neighbourhood.
+                "--rule" if value.contains(',') && !value.contains('/') => {
+                    (self.rule, self.neighborhood) = neighborhood::parse_larger_than_life(value)
+                        .map_err(|_| ConfigError::InvalidValue {
+                            flag: flag.to_string(),
+                            value: value.to_string(),
+                        })?
+                }
+                "--rule" => self.rule = parse_value(flag, value)?,
+                "--neighborhood" => self.neighborhood = parse_value(flag, value)?,
+                "--topology" => self.topology = parse_value(flag, value)?,
+                "--boundary" => self.boundary = parse_value(flag, value)?,
+                "--universe" => self.extent = parse_value(flag, value)?,
+                "--pattern" => self.pattern = Some(value.to_string()),
+                "--pattern-x" => self.pattern_offset.0 = parse_value(flag, value)?,
+                "--pattern-y" => self.pattern_offset.1 = parse_value(flag, value)?,
+                "--durations-output" => self.durations_output = Some(value.to_string()),
+                "--total-time-output" => self.total_time_output = Some(value.to_string()),
+                "--output" => self.output = Some(value.to_string()),
+                "--cycles" => self.cycles = parse_value(flag, value)?,
+                "--cycle-window" => {
+                    self.cycle_window = parse_value(flag, value)?;
+                    if self.cycle_window == 0 {
+                        return Err(ConfigError::InvalidValue {
+                            flag: flag.to_string(),
+                            value: value.to_string(),
+                        });
+                    }
+                }
+                "--stabilization-output" => self.stabilization_output = Some(value.to_string()),
+                "--stats-output" => self.stats_output = Some(value.to_string()),
+                "--snapshot-output" => self.snapshot_output = Some(value.to_string()),
+                "--resume" => self.resume = Some(value.to_string()),
+                "--history-budget" => self.history_budget = Some(parse_bytes(flag, value)?),
+                "--system-timings-output" => self.system_timings_output = Some(value.to_string()),
+                "--trace-output" => self.trace_output = Some(value.to_string()),
+                _ => unreachable!("{} is listed in FLAGS", flag),
+            }
+            Ok(())
+        })?;
+
+        // Cells are indexed as `y * width + x` in a `u32`.
+        if self.width.checked_mul(self.height).is_none() {
+            return Err(ConfigError::Unsupported(format!(
+                "a {}x{} grid has more cells than can be indexed",
+                self.width, self.height
+            )));
+        }
+        if self.extent == Extent::Unbounded && self.rule.states() > 2 {
+            return Err(ConfigError::Unsupported(format!(
+                "the unbounded universe only runs two-state rules, not {}",
+                self.rule
+            )));
+        }
+        // Only chunks near live cells are stepped, so empty space has to stay empty.
+        if self.extent == Extent::Unbounded && self.rule.is_birth(0) {
+            return Err(ConfigError::Unsupported(format!(
+                "the unbounded universe cannot run B0 rules like {}",
+                self.rule
+            )));
+        }
+        if self.extent == Extent::Unbounded && self.neighborhood != Neighborhood::default() {
+            return Err(ConfigError::Unsupported(format!(
+                "the unbounded universe only runs the moore:1 neighbourhood, not {}",
+                self.neighborhood
+            )));
+        }
+        if self.extent == Extent::Unbounded
+            && (self.snapshot_output.is_some() || self.resume.is_some())
+        {
+            return Err(ConfigError::Unsupported(
+                "snapshots only cover the bounded grid".to_string(),
+            ));
+        }
+        if self.extent == Extent::Unbounded && self.history_budget.is_some() {
+            return Err(ConfigError::Unsupported(
+                "rewinding only covers the bounded grid".to_string(),
+            ));
+        }
+        if self.topology != Topology::Square {
+            self.check_tiling()?;
+        }
+        Ok(self)
+    }
+
+    /// Hexagons and triangles only run on a bounded grid with range 1 neighbourhoods, and
+    /// cannot be mirrored. Wrapping triangles also needs even sides, or cells pointing up
+    /// would meet cells pointing up across the edge.
+    fn check_tiling(&self) -> Result<(), ConfigError> {
+        let unsupported = |what: String| {
+            Err(ConfigError::Unsupported(format!(
+                "the {} topology does not support {}",
+                self.topology, what
+            )))
+        };
+        if self.extent == Extent::Unbounded {
+            return unsupported("the unbounded universe".to_string());
+        }
+        if !self.topology.supports(&self.neighborhood) {
+            return unsupported(format!("the {} neighbourhood", self.neighborhood));
+        }
+        match self.boundary {
+            Boundary::Mirror | Boundary::KleinBottle => {
+                unsupported(format!("the {} boundary", self.boundary))
+            }
+            Boundary::Torus
+                if self.topology == Topology::Triangular
+                    && !(self.width.is_multiple_of(2) && self.height.is_multiple_of(2)) =>
+            {
+                unsupported(format!(
+                    "a torus with odd sides, {}x{}",
+                    self.width, self.height
+                ))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// The size of the Bevy compute pool, `None` for one thread per core.
+    pub fn compute_threads(&self) -> Option<usize> {
+        self.executor.compute_threads(self.threads)
+    }
+
+    /// The soup to start from, using a fresh seed when none was given.
+    pub fn soup(&self) -> Soup {
+        match self.seed {
+            Some(seed) => Soup::new(seed, self.density),
+            None => Soup::from_entropy(self.density),
+        }
+    }
+
+    /// A detector for the run, `None` when `--cycles` is off.
+    pub fn cycle_detector(&self) -> Option<CycleDetector> {
+        match self.cycles {
+            CycleDetection::Off => None,
+            CycleDetection::Report | CycleDetection::Stop => {
+                Some(CycleDetector::new(self.cycle_window))
+            }
+        }
+    }
+
+    /// Parses the process arguments on top of `self`, printing the usage and exiting on errors.
+    pub fn parse_env_or_exit(self) -> Self {
+        exit_on_error(self.parse_args(std::env::args().skip(1)), USAGE)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse_args() {
+        let config = Config::default()
+            .parse_args(args(&[
+                "--width",
+                "64",
+                "--height=32",
+                "--generations",
+                "10",
+                "--seed",
+                "42",
+                "--threads",
+                "4",
+                "--cell-index",
+                "dense",
+                "--neighbors",
+                "incremental",
+                "--batching",
+                "per-thread:4",
+                "--density",
+                "0.25",
+                "--rule",
+                "B36/S23",
+                "--boundary",
+                "torus",
+                "--universe",
+                "unbounded",
+                "--pattern",
+                "glider.rle",
+                "--pattern-x=-1",
+                "--pattern-y",
+                "2",
+                "--output",
+                "final.rle",
+                "--stats-output",
+                "stats.csv",
+                "--system-timings-output",
+                "systems.json",
+                "--trace-output",
+                "trace.json",
+            ]))
+            .unwrap();
+
+        assert_eq!(config.width, 64);
+        assert_eq!(config.height, 32);
+        assert_eq!(config.generations, 10);
+        assert_eq!(config.seed, Some(42));
+        assert_eq!(config.threads, Some(4));
+        assert_eq!(config.cell_index, CellIndex::Dense);
+        assert_eq!(config.neighbor_counting, NeighborCounting::Incremental);
+        assert_eq!(config.batching, Batching::PerThread(4));
+        assert_eq!(config.density, 0.25);
+        assert_eq!(config.rule, "B36/S23".parse().unwrap());
+        assert_eq!(config.boundary, Boundary::Torus);
+        assert_eq!(config.extent, Extent::Unbounded);
+        assert_eq!(config.pattern.as_deref(), Some("glider.rle"));
+        assert_eq!(config.pattern_offset, (-1, 2));
+        assert_eq!(config.output.as_deref(), Some("final.rle"));
+        assert_eq!(config.stats_output.as_deref(), Some("stats.csv"));
+        assert_eq!(
+            config.system_timings_output.as_deref(),
+            Some("systems.json")
+        );
+        assert_eq!(config.trace_output.as_deref(), Some("trace.json"));
+        assert_eq!(config.durations_output, None);
+    }
+
+    #[test]
+    fn test_generations_power() {
+        let config = Config::default()
+            .parse_args(args(&["--generations", "2^40"]))
+            .unwrap();
+        assert_eq!(config.generations, 1 << 40);
+        assert!(Config::default()
+            .parse_args(args(&["--generations", "2^64"]))
+            .is_err());
+    }
+
+    #[test]
+    fn test_size_sets_both_dimensions() {
+        let config = Config::default()
+            .parse_args(args(&["--size", "50"]))
+            .unwrap();
+        assert_eq!((config.width, config.height), (50, 50));
+    }
+
+    #[test]
+    fn test_neighborhood() {
+        let config = Config::default()
+            .parse_args(args(&[
+                "--neighborhood",
+                "vonneumann:2",
+                "--rule",
+                "B3..4/S2..5",
+            ]))
+            .unwrap();
+        assert_eq!(config.neighborhood, Neighborhood::VonNeumann(2));
+        assert_eq!(config.rule.to_string(), "B34/S2345");
+
+        let config = Config::default()
+            .parse_args(args(&["--rule", "R5,C0,M1,S34..58,B34..45,NM"]))
+            .unwrap();
+        assert_eq!(config.neighborhood, Neighborhood::Moore(5));
+        assert_eq!(config.rule, "B34..45/S33..57".parse().unwrap());
+    }
+
+    #[test]
+    fn test_topology() {
+        let parse = |list: &[&str]| Config::default().parse_args(args(list));
+        let config = parse(&["--topology", "hex", "--boundary", "torus"]).unwrap();
+        assert_eq!(config.topology, Topology::Hexagonal);
+
+        for list in [
+            &["--topology", "hex", "--universe", "unbounded"][..],
+            &["--topology", "hex", "--neighborhood", "moore:2"],
+            &["--topology", "tri", "--boundary", "mirror"],
+            &["--topology", "tri", "--boundary", "torus", "--size", "15"],
+        ] {
+            assert!(
+                matches!(parse(list), Err(ConfigError::Unsupported(_))),
+                "{:?}",
+                list
+            );
+        }
+        assert!(parse(&["--topology", "tri", "--boundary", "torus", "--size", "16"]).is_ok());
+    }
+
+    #[test]
+    fn test_cycles() {
+        let config = Config::default()
+            .parse_args(args(&[
+                "--cycles",
+                "stop",
+                "--cycle-window=32",
+                "--stabilization-output",
+                "settled.txt",
+            ]))
+            .unwrap();
+        assert_eq!(config.cycles, CycleDetection::Stop);
+        assert_eq!(config.cycle_window, 32);
+        assert_eq!(config.stabilization_output.as_deref(), Some("settled.txt"));
+        assert!(config.cycle_detector().is_some());
+        assert!(Config::default().cycle_detector().is_none());
+    }
+
+    #[test]
+    fn test_snapshots() {
+        let config = Config::default()
+            .parse_args(args(&[
+                "--resume",
+                "run.snap",
+                "--snapshot-output=next.snap",
+            ]))
+            .unwrap();
+        assert_eq!(config.resume.as_deref(), Some("run.snap"));
+        assert_eq!(config.snapshot_output.as_deref(), Some("next.snap"));
+    }
+
+    #[test]
+    fn test_batching() {
+        assert_eq!("auto".parse(), Ok(Batching::Auto));
+        assert_eq!("fixed:4096".parse(), Ok(Batching::Fixed(4096)));
+        assert_eq!("Per-Thread:8".parse(), Ok(Batching::PerThread(8)));
+        for strategy in [Batching::Auto, Batching::Fixed(64), Batching::PerThread(2)] {
+            assert_eq!(strategy.to_string().parse(), Ok(strategy));
+        }
+        for invalid in ["fixed", "fixed:0", "per-thread:x", "auto:3", "chunked:8"] {
+            assert!(invalid.parse::<Batching>().is_err(), "{}", invalid);
+        }
+        assert_eq!(Config::default().batching, Batching::Auto);
+
+        assert_eq!(Batching::Auto.batch_size_limits(), 1..usize::MAX);
+        assert_eq!(Batching::Fixed(64).batch_size_limits(), 64..64);
+        assert_eq!(Batching::Fixed(64).batches_per_thread(), 1);
+        assert_eq!(Batching::PerThread(2).batches_per_thread(), 2);
+    }
+
+    #[test]
+    fn test_executor() {
+        let parse = |list: &[&str]| Config::default().parse_args(args(list)).unwrap();
+        let config = parse(&["--threads", "4"]);
+        assert_eq!(config.executor, Executor::MultiThreaded);
+        assert_eq!(config.compute_threads(), Some(4));
+
+        let config = parse(&["--threads", "4", "--executor", "single"]);
+        assert_eq!(config.executor, Executor::SingleThreaded);
+        assert_eq!(config.compute_threads(), Some(1));
+
+        assert_eq!(parse(&[]).compute_threads(), None);
+        assert_eq!("serial".parse(), Ok(Executor::SingleThreaded));
+        assert!("simple".parse::<Executor>().is_err());
+    }
+
+    #[test]
+    fn test_history_budget() {
+        let parse = |value: &str| {
+            Config::default()
+                .parse_args(args(&["--history-budget", value]))
+                .map(|config| config.history_budget)
+        };
+        assert_eq!(parse("65536"), Ok(Some(65536)));
+        assert_eq!(parse("512K"), Ok(Some(512 << 10)));
+        assert_eq!(parse("64m"), Ok(Some(64 << 20)));
+        assert!(parse("M").is_err());
+        assert!(parse("2T").is_err());
+        assert_eq!(Config::default().history_budget, None);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let parse = |list: &[&str]| Config::default().parse_args(args(list));
+        assert_eq!(parse(&["--help"]), Err(ConfigError::HelpRequested));
+        assert_eq!(
+            parse(&["--colour", "red"]),
+            Err(ConfigError::UnknownFlag("--colour".to_string()))
+        );
+        assert_eq!(
+            parse(&["--colour"]),
+            Err(ConfigError::UnknownFlag("--colour".to_string()))
+        );
+        assert_eq!(
+            parse(&["--width"]),
+            Err(ConfigError::MissingValue("--width".to_string()))
+        );
+        assert!(matches!(
+            parse(&["--universe", "unbounded", "--rule", "B2/S/3"]),
+            Err(ConfigError::Unsupported(_))
+        ));
+        assert!(matches!(
+            parse(&["--universe", "unbounded", "--rule", "B012/S3"]),
+            Err(ConfigError::Unsupported(_))
+        ));
+        assert!(matches!(
+            parse(&["--universe", "unbounded", "--neighborhood", "moore:2"]),
+            Err(ConfigError::Unsupported(_))
+        ));
+        assert!(matches!(
+            parse(&["--universe", "unbounded", "--resume", "run.snap"]),
+            Err(ConfigError::Unsupported(_))
+        ));
+        assert!(matches!(
+            parse(&["--universe", "unbounded", "--history-budget", "1M"]),
+            Err(ConfigError::Unsupported(_))
+        ));
+        assert!(matches!(
+            parse(&["--width", "100000", "--height", "100000"]),
+            Err(ConfigError::Unsupported(_))
+        ));
+        for flag in ["--width", "--height", "--size"] {
+            assert_eq!(
+                parse(&[flag, "0"]),
+                Err(ConfigError::InvalidValue {
+                    flag: flag.to_string(),
+                    value: "0".to_string()
+                })
+            );
+        }
+        assert!(matches!(
+            parse(&["--cycle-window", "0"]),
+            Err(ConfigError::InvalidValue { .. })
+        ));
+        assert!(matches!(
+            parse(&["--rule", "R5,C0,M2"]),
+            Err(ConfigError::InvalidValue { .. })
+        ));
+        assert_eq!(
+            parse(&["--density", "1.5"]),
+            Err(ConfigError::InvalidValue {
+                flag: "--density".to_string(),
+                value: "1.5".to_string()
+            })
+        );
+    }
+}
@@ -0,0 +1,313 @@
+//! Spots when a run stops changing: it dies out, freezes into a still life or starts
+//! repeating as an oscillator.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt,
+    str::FromStr,
+};
+
+/// Mixes a single cell into a well spread 64-bit value, splitmix64's finaliser.
+fn mix(x: i32, y: i32, state: u8) -> u64 {
+    let mut z = (((x as u32 as u64) << 32) | y as u32 as u64)
+        .wrapping_add((state as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15));
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// Hashes the non-dead cells of a generation.
+///
+/// Cells are summed rather than chained, so backends can feed them in whatever order
+/// they store them and still agree on the hash.
+pub fn hash_cells(cells: impl IntoIterator<Item = (i32, i32, u8)>) -> u64 {
+    cells
+        .into_iter()
+        .filter(|(_, _, state)| *state != 0)
+        .fold(0, |hash, (x, y, state)| hash.wrapping_add(mix(x, y, state)))
+}
+
+/// How a run settled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stabilization {
+    /// Every cell died in generation `start`.
+    Extinct { start: u64 },
+    /// Generation `start` is repeated `period` generations later, every generation for a
+    /// still life. Spaceships on a torus come back to where they started and count too.
+    Cycle { start: u64, period: u64 },
+}
+
+impl Stabilization {
+    /// The first generation of the final state or cycle.
+    pub fn start(&self) -> u64 {
+        match self {
+            Stabilization::Extinct { start } | Stabilization::Cycle { start, .. } => *start,
+        }
+    }
+
+    /// How many generations the final cycle lasts, 1 for still lifes and extinction.
+    pub fn period(&self) -> u64 {
+        match self {
+            Stabilization::Extinct { .. } => 1,
+            Stabilization::Cycle { period, .. } => *period,
+        }
+    }
+
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Stabilization::Extinct { .. } => "extinct",
+            Stabilization::Cycle { period: 1, .. } => "still life",
+            Stabilization::Cycle { .. } => "oscillator",
+        }
+    }
+}
+
+/// Written as `<kind> <start> <period>`, e.g. `oscillator 37 2`, which is also what the
+/// backends put in `--stabilization-output`.
+impl fmt::Display for Stabilization {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} {}",
+            self.kind().replace(' ', "_"),
+            self.start(),
+            self.period()
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseStabilizationError(pub String);
+
+impl fmt::Display for ParseStabilizationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "invalid stabilization {:?}, expected <kind> <start> <period>",
+            self.0
+        )
+    }
+}
+
+impl std::error::Error for ParseStabilizationError {}
+
+impl FromStr for Stabilization {
+    type Err = ParseStabilizationError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = || ParseStabilizationError(s.to_string());
+        let fields: Vec<&str> = s.split_whitespace().collect();
+        let [kind, start, period] = fields[..] else {
+            return Err(error());
+        };
+        let start = start.parse().map_err(|_| error())?;
+        let period: u64 = period.parse().map_err(|_| error())?;
+        match kind {
+            "extinct" if period == 1 => Ok(Stabilization::Extinct { start }),
+            "still_life" if period == 1 => Ok(Stabilization::Cycle { start, period }),
+            "oscillator" if period > 1 => Ok(Stabilization::Cycle { start, period }),
+            _ => Err(error()),
+        }
+    }
+}
+
+/// Writes `result` in its `Display` form, or `none` when the run never settled.
+pub fn save(result: Option<Stabilization>, filename: &str) -> std::io::Result<()> {
+    match result {
+        Some(result) => std::fs::write(filename, result.to_string()),
+        None => std::fs::write(filename, "none"),
+    }
+}
+
+/// Reads back what `save` wrote.
+pub fn load(filename: &str) -> std::io::Result<Option<Stabilization>> {
+    let contents = std::fs::read_to_string(filename)?;
+    match contents.trim() {
+        "none" => Ok(None),
+        result => result
+            .parse()
+            .map(Some)
+            .map_err(|error| std::io::Error::new(std::io::ErrorKind::InvalidData, error)),
+    }
+}
+
+/// Remembers the hashes of the last `window` generations and reports the first one to
+/// come back, so cycles longer than the window go unnoticed.
+#[derive(Debug, Clone)]
+pub struct CycleDetector {
+    window: usize,
+    seen: HashMap<u64, u64>,
+    history: VecDeque<(u64, u64)>,
+    result: Option<Stabilization>,
+}
+
+impl CycleDetector {
+    pub fn new(window: usize) -> Self {
+        CycleDetector {
+            window: window.max(1),
+            seen: HashMap::new(),
+            history: VecDeque::new(),
+            result: None,
+        }
+    }
+
+    /// Records the state of `generation` and returns how the run settled the first time
+    /// it does, `None` before and after.
+    pub fn observe(
+        &mut self,
+        generation: u64,
+        hash: u64,
+        population: usize,
+    ) -> Option<Stabilization> {
+        if self.result.is_some() {
+            return None;
+        }
+
+        let result = if population == 0 {
+            Some(Stabilization::Extinct { start: generation })
+        } else {
+            self.seen.get(&hash).map(|start| Stabilization::Cycle {
+                start: *start,
+                period: generation - start,
+            })
+        };
+        if result.is_some() {
+            self.result = result;
+            return result;
+        }
+
+        self.seen.insert(hash, generation);
+        self.history.push_back((hash, generation));
+        if self.history.len() > self.window {
+            let (hash, generation) = self.history.pop_front().expect("the window is full");
+            if self.seen.get(&hash) == Some(&generation) {
+                self.seen.remove(&hash);
+            }
+        }
+        None
+    }
+
+    /// What `observe` reported, if it has.
+    pub fn result(&self) -> Option<Stabilization> {
+        self.result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn blinker(generation: u64) -> Vec<(i32, i32, u8)> {
+        if generation.is_multiple_of(2) {
+            vec![(0, 1, 1), (1, 1, 1), (2, 1, 1)]
+        } else {
+            vec![(1, 0, 1), (1, 1, 1), (1, 2, 1)]
+        }
+    }
+
+    #[test]
+    fn test_hash_ignores_order_and_dead_cells() {
+        let cells = blinker(0);
+        let mut reversed = cells.clone();
+        reversed.reverse();
+        reversed.push((5, 5, 0));
+        assert_eq!(hash_cells(cells.clone()), hash_cells(reversed));
+        assert_ne!(hash_cells(cells), hash_cells(blinker(1)));
+        assert_ne!(hash_cells([(0, 0, 1)]), hash_cells([(0, 0, 2)]));
+    }
+
+    #[test]
+    fn test_detects_oscillators_still_lifes_and_extinction() {
+        // Three generations of debris before the blinker.
+        let mut detector = CycleDetector::new(16);
+        let mut result = None;
+        for generation in 0..20 {
+            let cells = if generation < 3 {
+                vec![(generation as i32, 9, 1)]
+            } else {
+                blinker(generation)
+            };
+            result = result.or(detector.observe(generation, hash_cells(cells), 3));
+        }
+        assert_eq!(
+            result,
+            Some(Stabilization::Cycle {
+                start: 3,
+                period: 2
+            })
+        );
+        assert_eq!(detector.result(), result);
+
+        let mut detector = CycleDetector::new(16);
+        assert_eq!(detector.observe(0, 7, 4), None);
+        assert_eq!(
+            detector.observe(1, 7, 4),
+            Some(Stabilization::Cycle {
+                start: 0,
+                period: 1
+            })
+        );
+
+        let mut detector = CycleDetector::new(16);
+        assert_eq!(detector.observe(0, 7, 4), None);
+        assert_eq!(
+            detector.observe(1, 0, 0),
+            Some(Stabilization::Extinct { start: 1 })
+        );
+    }
+
+    #[test]
+    fn test_window_limits_the_period() {
+        let mut detector = CycleDetector::new(2);
+        let detected: Vec<_> = (0..12)
+            .filter_map(|generation| detector.observe(generation, generation % 3, 1))
+            .collect();
+        assert!(detected.is_empty());
+
+        let mut detector = CycleDetector::new(3);
+        let detected: Vec<_> = (0..12)
+            .filter_map(|generation| detector.observe(generation, generation % 3, 1))
+            .collect();
+        assert_eq!(
+            detected,
+            [Stabilization::Cycle {
+                start: 0,
+                period: 3
+            }]
+        );
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for stabilization in [
+            Stabilization::Extinct { start: 12 },
+            Stabilization::Cycle {
+                start: 40,
+                period: 1,
+            },
+            Stabilization::Cycle {
+                start: 37,
+                period: 2,
+            },
+        ] {
+            assert_eq!(stabilization.to_string().parse(), Ok(stabilization));
+        }
+        assert_eq!(
+            Stabilization::Cycle {
+                start: 37,
+                period: 2
+            }
+            .to_string(),
+            "oscillator 37 2"
+        );
+        let filename = std::env::temp_dir().join("game_of_life_core_stabilization.txt");
+        let filename = filename.to_str().unwrap();
+        for result in [None, Some(Stabilization::Extinct { start: 3 })] {
+            save(result, filename).unwrap();
+            assert_eq!(load(filename).unwrap(), result);
+        }
+        std::fs::remove_file(filename).unwrap();
+        assert!("oscillator 37 1".parse::<Stabilization>().is_err());
+        assert!("still_life 40".parse::<Stabilization>().is_err());
+    }
+}
@@ -0,0 +1,224 @@
+//! Past generations of a bounded grid kept within a memory budget, so a run can be
+//! stepped back and forth.
+//!
+//! Only the latest generation is stored in full. Every step before it is kept as the
+//! cells it changed, each one a varint gap from the previous changed cell followed by
+//! the XOR of its old and new state. XOR undoes itself, so the same delta moves the grid
+//! a generation forwards or backwards.
+
+use std::collections::VecDeque;
+
+#[derive(Debug, Clone)]
+pub struct History {
+    budget: usize,
+    latest: Vec<u8>,
+    last: u64,
+    /// `deltas[i]` turns generation `first() + i` into the one after it.
+    deltas: VecDeque<Vec<u8>>,
+    delta_bytes: usize,
+}
+
+fn write_varint(bytes: &mut Vec<u8>, mut value: usize) {
+    while value >= 0x80 {
+        bytes.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    bytes.push(value as u8);
+}
+
+fn read_varint(bytes: &mut impl Iterator<Item = u8>) -> Option<usize> {
+    let mut value = 0;
+    let mut shift = 0;
+    loop {
+        let byte = bytes.next()?;
+        value |= ((byte & 0x7f) as usize) << shift;
+        if byte & 0x80 == 0 {
+            return Some(value);
+        }
+        shift += 7;
+    }
+}
+
+fn diff(before: &[u8], after: &[u8]) -> Vec<u8> {
+    let mut delta = Vec::new();
+    let mut previous = 0;
+    for (index, (before, after)) in before.iter().zip(after).enumerate() {
+        if before != after {
+            write_varint(&mut delta, index - previous);
+            delta.push(before ^ after);
+            previous = index;
+        }
+    }
+    delta
+}
+
+fn apply(cells: &mut [u8], delta: &[u8]) {
+    let mut bytes = delta.iter().copied();
+    let mut index = 0;
+    while let Some(gap) = read_varint(&mut bytes) {
+        index += gap;
+        cells[index] ^= bytes.next().expect("every gap is followed by a state");
+    }
+}
+
+impl History {
+    /// Starts the history at `generation`. `budget` is in bytes and covers the full copy
+    /// of the latest generation as well as the deltas.
+    pub fn new(budget: usize, generation: u64, cells: Vec<u8>) -> Self {
+        History {
+            budget,
+            latest: cells,
+            last: generation,
+            deltas: VecDeque::new(),
+            delta_bytes: 0,
+        }
+    }
+
+    /// The oldest generation still remembered.
+    pub fn first(&self) -> u64 {
+        self.last - self.deltas.len() as u64
+    }
+
+    /// The newest generation, the one `push` was last given.
+    pub fn last(&self) -> u64 {
+        self.last
+    }
+
+    pub fn contains(&self, generation: u64) -> bool {
+        (self.first()..=self.last).contains(&generation)
+    }
+
+    /// The memory used by the stored generations.
+    pub fn bytes(&self) -> usize {
+        self.latest.len() + self.delta_bytes
+    }
+
+    /// Records the generation after `last()`, forgetting the oldest ones until the
+    /// history fits in its budget again.
+    pub fn push(&mut self, cells: &[u8]) {
+        let delta = diff(&self.latest, cells);
+        self.latest.copy_from_slice(cells);
+        self.last += 1;
+        self.delta_bytes += delta.len();
+        self.deltas.push_back(delta);
+
+        while self.bytes() > self.budget {
+            match self.deltas.pop_front() {
+                Some(delta) => self.delta_bytes -= delta.len(),
+                None => break,
+            }
+        }
+    }
+
+    /// Forgets every generation after `generation`, so a rewound run can carry on from
+    /// there.
+    pub fn truncate(&mut self, generation: u64) {
+        while self.last > generation {
+            let Some(delta) = self.deltas.pop_back() else {
+                break;
+            };
+            apply(&mut self.latest, &delta);
+            self.delta_bytes -= delta.len();
+            self.last -= 1;
+        }
+    }
+
+    /// Moves `cells` from generation `from` to generation `to`, either way. Returns false
+    /// and leaves `cells` alone when either generation has been forgotten.
+    pub fn seek(&self, cells: &mut [u8], from: u64, to: u64) -> bool {
+        if !self.contains(from) || !self.contains(to) {
+            return false;
+        }
+        let first = self.first();
+        let steps = (from.min(to) - first) as usize..(from.max(to) - first) as usize;
+        for delta in self.deltas.range(steps) {
+            apply(cells, delta);
+        }
+        true
+    }
+
+    /// Every cell's state in `generation`, if it is still remembered.
+    pub fn cells_at(&self, generation: u64) -> Option<Vec<u8>> {
+        let mut cells = self.latest.clone();
+        self.seek(&mut cells, self.last, generation)
+            .then_some(cells)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A blinker on a 5x5 grid, with a slowly growing trail so each generation differs.
+    fn generation(n: u64) -> Vec<u8> {
+        let mut cells = vec![0; 25];
+        let blinker: [usize; 3] = if n.is_multiple_of(2) {
+            [11, 12, 13]
+        } else {
+            [7, 12, 17]
+        };
+        for index in blinker {
+            cells[index] = 1;
+        }
+        cells[(n % 5) as usize] = 2;
+        cells
+    }
+
+    #[test]
+    fn test_seek_both_ways() {
+        let mut history = History::new(1 << 20, 3, generation(3));
+        for n in 4..=20 {
+            history.push(&generation(n));
+        }
+        assert_eq!((history.first(), history.last()), (3, 20));
+        for n in 3..=20 {
+            assert_eq!(history.cells_at(n), Some(generation(n)));
+        }
+
+        let mut cells = generation(5);
+        assert!(history.seek(&mut cells, 5, 17));
+        assert_eq!(cells, generation(17));
+        assert!(history.seek(&mut cells, 17, 4));
+        assert_eq!(cells, generation(4));
+        assert!(!history.seek(&mut cells, 4, 21));
+        assert_eq!(cells, generation(4));
+        assert_eq!(history.cells_at(2), None);
+    }
+
+    #[test]
+    fn test_budget_forgets_the_oldest() {
+        let mut history = History::new(25 + 40, 0, generation(0));
+        for n in 1..=30 {
+            history.push(&generation(n));
+            assert!(history.bytes() <= 65);
+        }
+        assert_eq!(history.last(), 30);
+        assert!(history.first() > 0);
+        assert!(history.first() < 30);
+        assert_eq!(
+            history.cells_at(history.first()),
+            Some(generation(history.first()))
+        );
+
+        // Too small for a single delta, only the latest generation is kept.
+        let mut history = History::new(0, 0, generation(0));
+        history.push(&generation(1));
+        assert_eq!((history.first(), history.last()), (1, 1));
+    }
+
+    #[test]
+    fn test_truncate_branches_off() {
+        let mut history = History::new(1 << 20, 0, generation(0));
+        for n in 1..=10 {
+            history.push(&generation(n));
+        }
+        history.truncate(6);
+        assert_eq!(history.last(), 6);
+        assert_eq!(history.cells_at(6), Some(generation(6)));
+
+        let branch = vec![1; 25];
+        history.push(&branch);
+        assert_eq!(history.cells_at(7), Some(branch));
+        assert_eq!(history.cells_at(2), Some(generation(2)));
+    }
+}
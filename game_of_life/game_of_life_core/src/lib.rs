@@ -0,0 +1,23 @@
+pub mod boundary;
+pub mod config;
+pub mod cycle;
+pub mod history;
+pub mod neighborhood;
+pub mod pattern;
+pub mod rule;
+pub mod snapshot;
+pub mod soup;
+pub mod sparse;
+pub mod stats;
+pub mod timing;
+pub mod topology;
+pub mod trace;
+
+pub use boundary::Boundary;
+pub use config::Config;
+pub use neighborhood::Neighborhood;
+pub use pattern::Pattern;
+pub use rule::Rule;
+pub use snapshot::Snapshot;
+pub use soup::Soup;
+pub use topology::Topology;
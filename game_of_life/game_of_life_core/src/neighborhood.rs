@@ -0,0 +1,291 @@
+use std::{fmt, str::FromStr};
+
+use crate::rule::{self, Counts, ParseRuleError, Rule};
+
+/// The widest neighbourhood accepted, a `33 x 33` square for Moore.
+pub const MAX_RANGE: u8 = 16;
+
+/// A cell offset and how much an alive cell there adds to the neighbour count.
+pub type Offset = ((i32, i32), u16);
+
+/// Which cells around a cell count as its neighbours, and with what weight.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Neighborhood {
+    /// Every cell within `range` in both directions, the plain 3x3 square at range 1.
+    Moore(u8),
+    /// Every cell within Manhattan distance `range`.
+    VonNeumann(u8),
+    /// A `2 * range + 1` square of weights centred on the cell, row by row. The centre
+    /// weight counts the cell itself.
+    Weighted { range: u8, weights: Vec<u16> },
+}
+
+impl Default for Neighborhood {
+    fn default() -> Self {
+        Neighborhood::Moore(1)
+    }
+}
+
+impl Neighborhood {
+    pub fn range(&self) -> u8 {
+        match self {
+            Neighborhood::Moore(range)
+            | Neighborhood::VonNeumann(range)
+            | Neighborhood::Weighted { range, .. } => *range,
+        }
+    }
+
+    /// The offsets with a non-zero weight, row by row.
+    pub fn offsets(&self) -> Vec<Offset> {
+        let range = self.range() as i32;
+        let side = 2 * range + 1;
+        (-range..=range)
+            .flat_map(|dy| (-range..=range).map(move |dx| (dx, dy)))
+            .filter(|offset| *offset != (0, 0) || matches!(self, Neighborhood::Weighted { .. }))
+            .map(|(dx, dy)| {
+                let weight = match self {
+                    Neighborhood::Moore(_) => 1,
+                    Neighborhood::VonNeumann(_) => (dx.abs() + dy.abs() <= range) as u16,
+                    Neighborhood::Weighted { weights, .. } => {
+                        weights[((dy + range) * side + dx + range) as usize]
+                    }
+                };
+                ((dx, dy), weight)
+            })
+            .filter(|(_, weight)| *weight > 0)
+            .collect()
+    }
+
+    /// The largest count a cell can see, with every weighted neighbour alive.
+    pub fn max_count(&self) -> u32 {
+        self.offsets()
+            .iter()
+            .map(|(_, weight)| *weight as u32)
+            .sum()
+    }
+}
+
+impl fmt::Display for Neighborhood {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Neighborhood::Moore(range) => write!(f, "moore:{}", range),
+            Neighborhood::VonNeumann(range) => write!(f, "vonneumann:{}", range),
+            Neighborhood::Weighted { range, weights } => {
+                write!(f, "mask:")?;
+                let side = 2 * *range as usize + 1;
+                for (index, row) in weights.chunks(side).enumerate() {
+                    if index > 0 {
+                        write!(f, "/")?;
+                    }
+                    let row: Vec<String> = row.iter().map(u16::to_string).collect();
+                    write!(f, "{}", row.join(","))?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseNeighborhoodError(pub String);
+
+impl fmt::Display for ParseNeighborhoodError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "unknown neighbourhood {:?}, expected moore[:range], vonneumann[:range] \
+             or mask:<rows> with a range of at most {}",
+            self.0, MAX_RANGE
+        )
+    }
+}
+
+impl std::error::Error for ParseNeighborhoodError {}
+
+/// Parses the rows of a weighted mask, `1,2,1/2,0,2/1,2,1`, where rows without commas
+/// hold one digit per weight, `121/202/121`.
+fn parse_mask(rows: &str) -> Option<Neighborhood> {
+    let rows: Vec<Vec<u16>> = rows
+        .split('/')
+        .map(|row| {
+            if row.contains(',') {
+                row.split(',')
+                    .map(|weight| weight.trim().parse().ok())
+                    .collect()
+            } else {
+                row.trim()
+                    .chars()
+                    .map(|c| c.to_digit(10).map(|weight| weight as u16))
+                    .collect()
+            }
+        })
+        .collect::<Option<_>>()?;
+
+    let side = rows.len();
+    if side.is_multiple_of(2)
+        || side > 2 * MAX_RANGE as usize + 1
+        || rows.iter().any(|row| row.len() != side)
+    {
+        return None;
+    }
+    let weights: Vec<u16> = rows.concat();
+    // Counts are `u16`, so every neighbour being alive must still fit.
+    if weights.iter().map(|weight| *weight as u32).sum::<u32>() > u16::MAX as u32 {
+        return None;
+    }
+    Some(Neighborhood::Weighted {
+        range: (side / 2) as u8,
+        weights,
+    })
+}
+
+impl FromStr for Neighborhood {
+    type Err = ParseNeighborhoodError;
+
+    /// Parses `moore`, `moore:5`, `vonneumann:3` or `mask:121/202/121`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = || ParseNeighborhoodError(s.to_string());
+        let (kind, argument) = match s.trim().split_once(':') {
+            Some((kind, argument)) => (kind, Some(argument)),
+            None => (s.trim(), None),
+        };
+        let range = || match argument {
+            Some(range) => range
+                .trim()
+                .parse()
+                .ok()
+                .filter(|range| (1..=MAX_RANGE).contains(range))
+                .ok_or_else(error),
+            None => Ok(1),
+        };
+
+        match kind.to_ascii_lowercase().as_str() {
+            "moore" | "m" => Ok(Neighborhood::Moore(range()?)),
+            "vonneumann" | "von-neumann" | "vn" | "n" => Ok(Neighborhood::VonNeumann(range()?)),
+            "mask" | "weighted" => argument.and_then(parse_mask).ok_or_else(error),
+            _ => Err(error()),
+        }
+    }
+}
+
+/// Parses a Golly style Larger than Life rulestring, `R5,C0,M1,S34..58,B34..45,NM`, into
+/// the rule and neighbourhood it describes.
+///
+/// `C` is the number of states (0 and 2 both mean two), `M1` counts the cell itself, which
+/// is folded into the survival interval, and `N` is `M` for Moore or `N` for von Neumann.
+pub fn parse_larger_than_life(s: &str) -> Result<(Rule, Neighborhood), ParseRuleError> {
+    let mut range = 1;
+    let mut states = 2;
+    let mut middle = false;
+    let mut birth = (3, 3);
+    let mut survival = (2, 3);
+    let mut von_neumann = false;
+
+    for field in s.trim().split(',') {
+        let invalid = || ParseRuleError::InvalidField(field.to_string());
+        let mut chars = field.trim().chars();
+        let name = chars.next().map(|c| c.to_ascii_uppercase());
+        let value = chars.as_str();
+        match name {
+            Some('R') => {
+                range = value
+                    .parse()
+                    .ok()
+                    .filter(|range| (1..=MAX_RANGE).contains(range))
+                    .ok_or_else(invalid)?
+            }
+            Some('C') => states = value.parse::<u8>().map_err(|_| invalid())?.max(2),
+            Some('M') => {
+                middle = match value {
+                    "0" => false,
+                    "1" => true,
+                    _ => return Err(invalid()),
+                }
+            }
+            Some('S') => survival = rule::parse_interval(value)?,
+            Some('B') => birth = rule::parse_interval(value)?,
+            Some('N') => {
+                von_neumann = match value.to_ascii_uppercase().as_str() {
+                    "M" => false,
+                    "N" => true,
+                    _ => return Err(invalid()),
+                }
+            }
+            _ => return Err(invalid()),
+        }
+    }
+
+    // An alive cell counting itself sees one more than its neighbours alone.
+    if middle {
+        survival = (survival.0.saturating_sub(1), survival.1.saturating_sub(1));
+    }
+    let counts = |interval| Counts::from_intervals([interval]).expect("a single interval");
+    let rule = Rule::with_counts(counts(birth), counts(survival), states);
+    let neighborhood = if von_neumann {
+        Neighborhood::VonNeumann(range)
+    } else {
+        Neighborhood::Moore(range)
+    };
+    Ok((rule, neighborhood))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_offsets() {
+        let moore = Neighborhood::default().offsets();
+        assert_eq!(moore.len(), 8);
+        assert!(!moore.iter().any(|(offset, _)| *offset == (0, 0)));
+
+        assert_eq!(Neighborhood::Moore(5).offsets().len(), 120);
+        assert_eq!(Neighborhood::VonNeumann(1).offsets().len(), 4);
+        assert_eq!(Neighborhood::VonNeumann(3).max_count(), 24);
+
+        let mask: Neighborhood = "mask:121/202/121".parse().unwrap();
+        assert_eq!(mask.range(), 1);
+        assert_eq!(mask.max_count(), 12);
+        assert_eq!(mask.offsets()[1], ((0, -1), 2));
+    }
+
+    #[test]
+    fn test_parse() {
+        for neighborhood in [
+            Neighborhood::Moore(1),
+            Neighborhood::Moore(10),
+            Neighborhood::VonNeumann(3),
+            "mask:1,2,1/2,0,2/1,2,1".parse().unwrap(),
+        ] {
+            assert_eq!(
+                neighborhood.to_string().parse::<Neighborhood>(),
+                Ok(neighborhood)
+            );
+        }
+        assert_eq!("moore".parse(), Ok(Neighborhood::Moore(1)));
+        assert_eq!("vn:2".parse(), Ok(Neighborhood::VonNeumann(2)));
+        assert!("moore:0".parse::<Neighborhood>().is_err());
+        assert!("moore:17".parse::<Neighborhood>().is_err());
+        assert!("hexagonal".parse::<Neighborhood>().is_err());
+        // Masks must be odd-sized squares.
+        assert!("mask:11/11".parse::<Neighborhood>().is_err());
+        assert!("mask:111/11/111".parse::<Neighborhood>().is_err());
+    }
+
+    #[test]
+    fn test_parse_larger_than_life() {
+        let (bosco, neighborhood) = parse_larger_than_life("R5,C0,M1,S34..58,B34..45,NM").unwrap();
+        assert_eq!(neighborhood, Neighborhood::Moore(5));
+        assert_eq!(bosco.to_string(), "B34..45/S33..57");
+
+        let (rule, neighborhood) = parse_larger_than_life("R2,C3,M0,S2..4,B3..3,NN").unwrap();
+        assert_eq!(neighborhood, Neighborhood::VonNeumann(2));
+        assert_eq!(rule.states(), 3);
+        assert_eq!(rule.to_string(), "B3/S234/3");
+
+        assert_eq!(
+            parse_larger_than_life("R5,X1"),
+            Err(ParseRuleError::InvalidField("X1".to_string()))
+        );
+    }
+}
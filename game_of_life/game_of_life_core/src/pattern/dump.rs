@@ -0,0 +1,62 @@
+//! The `0`/`1` grid dump written by the no_ecs `save_cells_to_file`.
+
+use super::{Pattern, PatternError};
+
+pub fn parse(contents: &str) -> Result<Pattern, PatternError> {
+    let mut cells = Vec::new();
+    let (mut width, mut height) = (0u32, 0u32);
+
+    for (y, line) in contents.lines().enumerate() {
+        let line = line.trim_end();
+        for (x, character) in line.chars().enumerate() {
+            match character {
+                '1' => cells.push((x as u32, y as u32)),
+                '0' => {}
+                character => {
+                    return Err(PatternError::UnexpectedCharacter {
+                        line: y + 1,
+                        character,
+                    })
+                }
+            }
+        }
+        width = width.max(line.len() as u32);
+        height = y as u32 + 1;
+    }
+
+    Ok(Pattern {
+        width,
+        height,
+        cells,
+        rule: None,
+        topology: Default::default(),
+    })
+}
+
+pub fn write(pattern: &Pattern) -> String {
+    let mut rows = vec![vec!['0'; pattern.width as usize]; pattern.height as usize];
+    for (x, y) in pattern.cells.iter() {
+        rows[*y as usize][*x as usize] = '1';
+    }
+
+    let mut output = String::new();
+    for row in rows {
+        output.extend(row);
+        output.push('\n');
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let pattern = parse("0100\n0000\n0001\n").unwrap();
+        assert_eq!(pattern.width, 4);
+        assert_eq!(pattern.height, 3);
+        assert_eq!(pattern.cells, vec![(1, 0), (3, 2)]);
+        assert_eq!(write(&pattern), "0100\n0000\n0001\n");
+    }
+}
@@ -0,0 +1,84 @@
+//! The Life 1.06 coordinate list format, see <https://conwaylife.com/wiki/Life_1.06>.
+
+use super::{Pattern, PatternError};
+
+const HEADER: &str = "#Life 1.06";
+
+/// Coordinates may be negative, so the cells are shifted to put the top-left corner of
+/// their bounding box at `(0, 0)`.
+pub fn parse(contents: &str) -> Result<Pattern, PatternError> {
+    let mut coordinates = Vec::new();
+    for (index, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let invalid = || {
+            let character = line
+                .chars()
+                .find(|c| !c.is_ascii_digit() && *c != '-' && !c.is_whitespace())
+                .unwrap_or(' ');
+            PatternError::UnexpectedCharacter {
+                line: index + 1,
+                character,
+            }
+        };
+        let mut numbers = line.split_whitespace().map(|number| number.parse::<i32>());
+        match (numbers.next(), numbers.next(), numbers.next()) {
+            (Some(Ok(x)), Some(Ok(y)), None) => coordinates.push((x, y)),
+            _ => return Err(invalid()),
+        }
+    }
+
+    let min_x = coordinates.iter().map(|(x, _)| *x).min().unwrap_or(0);
+    let min_y = coordinates.iter().map(|(_, y)| *y).min().unwrap_or(0);
+    let max_x = coordinates.iter().map(|(x, _)| *x).max().unwrap_or(-1);
+    let max_y = coordinates.iter().map(|(_, y)| *y).max().unwrap_or(-1);
+
+    Ok(Pattern::from_grid(
+        (max_x - min_x + 1).max(0) as u32,
+        (max_y - min_y + 1).max(0) as u32,
+        coordinates.into_iter().map(|(x, y)| (x - min_x, y - min_y)),
+        None,
+    ))
+}
+
+pub fn write(pattern: &Pattern) -> String {
+    let mut output = format!("{}\n", HEADER);
+    for (x, y) in pattern.cells.iter() {
+        output.push_str(&format!("{} {}\n", x, y));
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_glider() {
+        let pattern = parse("#Life 1.06\n0 -1\n1 0\n-1 1\n0 1\n1 1\n").unwrap();
+        assert_eq!(pattern.width, 3);
+        assert_eq!(pattern.height, 3);
+        assert_eq!(pattern.cells, vec![(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)]);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let pattern = Pattern::from_grid(3, 2, [(0, 0), (2, 1)], None);
+        assert_eq!(write(&pattern), "#Life 1.06\n0 0\n2 1\n");
+        assert_eq!(parse(&write(&pattern)).unwrap(), pattern);
+    }
+
+    #[test]
+    fn test_parse_error() {
+        assert!(matches!(
+            parse("#Life 1.06\n0 0\n1 a\n"),
+            Err(PatternError::UnexpectedCharacter {
+                line: 3,
+                character: 'a'
+            })
+        ));
+    }
+}
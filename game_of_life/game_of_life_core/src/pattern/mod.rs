@@ -0,0 +1,165 @@
+use std::{collections::HashSet, fmt, path::Path};
+
+use crate::{rule::ParseRuleError, Rule, Topology};
+
+pub mod dump;
+pub mod life106;
+pub mod plaintext;
+pub mod rle;
+
+/// A finite set of alive cells, with `(0, 0)` at the top-left corner of its bounding box.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Pattern {
+    pub width: u32,
+    pub height: u32,
+    pub cells: Vec<(u32, u32)>,
+    pub rule: Option<Rule>,
+    /// The tiling the cells belong to, kept as a rulestring suffix by RLE files.
+    pub topology: Topology,
+}
+
+impl Pattern {
+    /// Builds a `width x height` pattern from the alive cells of a grid, e.g. for export.
+    pub fn from_grid(
+        width: u32,
+        height: u32,
+        alive_cells: impl IntoIterator<Item = (i32, i32)>,
+        rule: Option<Rule>,
+    ) -> Self {
+        let mut cells: Vec<(u32, u32)> = alive_cells
+            .into_iter()
+            .filter(|(x, y)| *x >= 0 && *y >= 0 && (*x as u32) < width && (*y as u32) < height)
+            .map(|(x, y)| (x as u32, y as u32))
+            .collect();
+        cells.sort_by_key(|(x, y)| (*y, *x));
+        cells.dedup();
+
+        Pattern {
+            width,
+            height,
+            cells,
+            rule,
+            topology: Topology::default(),
+        }
+    }
+
+    pub fn with_topology(self, topology: Topology) -> Self {
+        Pattern { topology, ..self }
+    }
+
+    /// Builds a pattern from cells anywhere on the plane, moving the top-left corner of
+    /// their bounding box to `(0, 0)`.
+    pub fn from_cells(cells: impl IntoIterator<Item = (i32, i32)>, rule: Option<Rule>) -> Self {
+        let cells: Vec<(i32, i32)> = cells.into_iter().collect();
+        let (Some(min_x), Some(min_y)) = (
+            cells.iter().map(|(x, _)| *x).min(),
+            cells.iter().map(|(_, y)| *y).min(),
+        ) else {
+            return Pattern {
+                rule,
+                ..Default::default()
+            };
+        };
+        let max_x = cells.iter().map(|(x, _)| *x).max().unwrap_or(min_x);
+        let max_y = cells.iter().map(|(_, y)| *y).max().unwrap_or(min_y);
+        Pattern::from_grid(
+            (max_x - min_x + 1) as u32,
+            (max_y - min_y + 1) as u32,
+            cells.into_iter().map(|(x, y)| (x - min_x, y - min_y)),
+            rule,
+        )
+    }
+
+    pub fn population(&self) -> usize {
+        self.cells.len()
+    }
+
+    /// Grid coordinates of the alive cells once the pattern's top-left corner is placed
+    /// at `(offset_x, offset_y)`. Cells falling outside a `width x height` grid are clipped.
+    pub fn cells_at_offset(
+        &self,
+        offset_x: i32,
+        offset_y: i32,
+        width: u32,
+        height: u32,
+    ) -> HashSet<(i32, i32)> {
+        self.cells
+            .iter()
+            .map(|(x, y)| (*x as i32 + offset_x, *y as i32 + offset_y))
+            .filter(|(x, y)| *x >= 0 && *y >= 0 && (*x as u32) < width && (*y as u32) < height)
+            .collect()
+    }
+
+    /// Loads a pattern file, picking the format from its extension: `.rle`, plaintext
+    /// `.cells`, Life 1.06 `.lif`/`.life`, or the `0`/`1` `.txt` dump.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, PatternError> {
+        let path = path.as_ref();
+        let contents = std::fs::read_to_string(path)?;
+        match extension(path).as_str() {
+            "rle" => rle::parse(&contents),
+            "cells" => plaintext::parse(&contents),
+            "lif" | "life" => life106::parse(&contents),
+            "txt" => dump::parse(&contents),
+            other => Err(PatternError::UnknownFormat(other.to_string())),
+        }
+    }
+
+    /// Saves the pattern, picking the format from the file extension.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), PatternError> {
+        let path = path.as_ref();
+        let contents = match extension(path).as_str() {
+            "rle" => rle::write(self),
+            "cells" => plaintext::write(self),
+            "lif" | "life" => life106::write(self),
+            "txt" => dump::write(self),
+            other => return Err(PatternError::UnknownFormat(other.to_string())),
+        };
+        std::fs::write(path, contents)?;
+        Ok(())
+    }
+}
+
+fn extension(path: &Path) -> String {
+    path.extension()
+        .map(|extension| extension.to_string_lossy().to_ascii_lowercase())
+        .unwrap_or_default()
+}
+
+#[derive(Debug)]
+pub enum PatternError {
+    Io(std::io::Error),
+    UnknownFormat(String),
+    InvalidHeader(String),
+    InvalidRule(ParseRuleError),
+    UnexpectedCharacter { line: usize, character: char },
+}
+
+impl fmt::Display for PatternError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PatternError::Io(error) => write!(f, "{}", error),
+            PatternError::UnknownFormat(extension) => {
+                write!(f, "unknown pattern format {:?}", extension)
+            }
+            PatternError::InvalidHeader(header) => write!(f, "invalid header {:?}", header),
+            PatternError::InvalidRule(error) => write!(f, "invalid rule: {}", error),
+            PatternError::UnexpectedCharacter { line, character } => {
+                write!(f, "unexpected character {:?} on line {}", character, line)
+            }
+        }
+    }
+}
+
+impl std::error::Error for PatternError {}
+
+impl From<std::io::Error> for PatternError {
+    fn from(error: std::io::Error) -> Self {
+        PatternError::Io(error)
+    }
+}
+
+impl From<ParseRuleError> for PatternError {
+    fn from(error: ParseRuleError) -> Self {
+        PatternError::InvalidRule(error)
+    }
+}
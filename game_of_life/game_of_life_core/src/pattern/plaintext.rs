@@ -0,0 +1,84 @@
+//! The plaintext `.cells` format, see <https://conwaylife.com/wiki/Plaintext>.
+
+use super::{Pattern, PatternError};
+
+pub fn parse(contents: &str) -> Result<Pattern, PatternError> {
+    let mut cells = Vec::new();
+    let (mut width, mut height) = (0u32, 0u32);
+
+    let rows = contents
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.starts_with('!'));
+    for (y, (index, line)) in rows.enumerate() {
+        let line = line.trim_end();
+        for (x, character) in line.chars().enumerate() {
+            match character {
+                'O' | '*' => cells.push((x as u32, y as u32)),
+                '.' => {}
+                character => {
+                    return Err(PatternError::UnexpectedCharacter {
+                        line: index + 1,
+                        character,
+                    })
+                }
+            }
+        }
+        width = width.max(line.chars().count() as u32);
+        height = y as u32 + 1;
+    }
+
+    Ok(Pattern {
+        width,
+        height,
+        cells,
+        rule: None,
+        topology: Default::default(),
+    })
+}
+
+/// Writes every row in full, so the pattern's width survives a round trip.
+pub fn write(pattern: &Pattern) -> String {
+    let mut rows = vec![vec!['.'; pattern.width as usize]; pattern.height as usize];
+    for (x, y) in pattern.cells.iter() {
+        rows[*y as usize][*x as usize] = 'O';
+    }
+
+    let mut output = String::new();
+    for row in rows {
+        output.extend(row);
+        output.push('\n');
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_glider() {
+        let pattern = parse("!Name: Glider\n!\n.O\n..O\nOOO\n").unwrap();
+        assert_eq!(pattern.width, 3);
+        assert_eq!(pattern.height, 3);
+        assert_eq!(pattern.cells, vec![(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)]);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let pattern = Pattern::from_grid(4, 3, [(1, 0), (3, 2)], None);
+        assert_eq!(write(&pattern), ".O..\n....\n...O\n");
+        assert_eq!(parse(&write(&pattern)).unwrap(), pattern);
+    }
+
+    #[test]
+    fn test_parse_error() {
+        assert!(matches!(
+            parse("!comment\n.O\n.X\n"),
+            Err(PatternError::UnexpectedCharacter {
+                line: 3,
+                character: 'X'
+            })
+        ));
+    }
+}
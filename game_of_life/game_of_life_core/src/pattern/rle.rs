@@ -0,0 +1,229 @@
+//! The run-length encoded `.rle` format, see <https://conwaylife.com/wiki/Run_Length_Encoded>.
+
+use super::{Pattern, PatternError};
+use crate::{Rule, Topology};
+
+const MAX_LINE_LENGTH: usize = 70;
+
+pub fn parse(contents: &str) -> Result<Pattern, PatternError> {
+    let mut header: Option<(u32, u32, Option<Rule>, Topology)> = None;
+    let mut cells = Vec::new();
+    let (mut x, mut y) = (0u32, 0u32);
+    let (mut max_x, mut max_y) = (0u32, 0u32);
+    let mut run: Option<u32> = None;
+
+    'lines: for (index, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        if header.is_none() && cells.is_empty() && line.starts_with('x') {
+            header = Some(parse_header(line)?);
+            continue;
+        }
+
+        for character in line.chars() {
+            let count = run.unwrap_or(1);
+            match character {
+                '0'..='9' => {
+                    let digit = character.to_digit(10).unwrap();
+                    run = Some(run.unwrap_or(0) * 10 + digit);
+                    continue;
+                }
+                'b' | '.' => x += count,
+                '$' => {
+                    x = 0;
+                    y += count;
+                }
+                '!' => break 'lines,
+                character if character.is_ascii_alphabetic() => {
+                    for _ in 0..count {
+                        cells.push((x, y));
+                        max_x = max_x.max(x + 1);
+                        max_y = max_y.max(y + 1);
+                        x += 1;
+                    }
+                }
+                character if character.is_whitespace() => {}
+                character => {
+                    return Err(PatternError::UnexpectedCharacter {
+                        line: index + 1,
+                        character,
+                    })
+                }
+            }
+            run = None;
+        }
+    }
+
+    let (width, height, rule, topology) = header.unwrap_or((max_x, max_y, None, Topology::Square));
+    Ok(Pattern {
+        width: width.max(max_x),
+        height: height.max(max_y),
+        cells,
+        rule,
+        topology,
+    })
+}
+
+/// Parses a `x = 3, y = 3, rule = B3/S23` header line, where a `B2/S34H` rule marks
+/// hexagonal cells and `B4/S345L` triangular ones.
+fn parse_header(line: &str) -> Result<(u32, u32, Option<Rule>, Topology), PatternError> {
+    let invalid = || PatternError::InvalidHeader(line.to_string());
+    let (mut width, mut height, mut rule) = (None, None, None);
+    let mut topology = Topology::Square;
+
+    for entry in line.split(',') {
+        // The comma inside a `:T100,100` suffix leaves a trailing entry without a key.
+        let Some((key, value)) = entry.split_once('=') else {
+            continue;
+        };
+        let value = value.trim();
+        match key.trim() {
+            "x" => width = Some(value.parse().map_err(|_| invalid())?),
+            "y" => height = Some(value.parse().map_err(|_| invalid())?),
+            // Drop Golly's bounded grid suffix, e.g. `B3/S23:T100,100`.
+            "rule" => {
+                let (value, suffix) =
+                    Topology::split_rule(value.split(':').next().unwrap_or(value));
+                rule = Some(value.parse()?);
+                topology = suffix;
+            }
+            _ => {}
+        }
+    }
+
+    match (width, height) {
+        (Some(width), Some(height)) => Ok((width, height, rule, topology)),
+        _ => Err(invalid()),
+    }
+}
+
+pub fn write(pattern: &Pattern) -> String {
+    let mut tokens: Vec<(u32, char)> = Vec::new();
+    let mut push = |count: u32, tag: char| match tokens.last_mut() {
+        Some((last_count, last_tag)) if *last_tag == tag => *last_count += count,
+        _ => tokens.push((count, tag)),
+    };
+
+    let mut cells = pattern.cells.clone();
+    cells.sort_by_key(|(x, y)| (*y, *x));
+    let mut cells = cells.into_iter().peekable();
+    for y in 0..pattern.height {
+        let mut x = 0;
+        while let Some((cell_x, _)) = cells.next_if(|(_, cell_y)| *cell_y == y) {
+            if cell_x > x {
+                push(cell_x - x, 'b');
+            }
+            push(1, 'o');
+            x = cell_x + 1;
+        }
+        push(1, '$');
+    }
+    while tokens.last().is_some_and(|(_, tag)| *tag == '$') {
+        tokens.pop();
+    }
+    tokens.push((1, '!'));
+
+    let mut output = format!(
+        "x = {}, y = {}, rule = {}{}\n",
+        pattern.width,
+        pattern.height,
+        pattern.rule.unwrap_or_default(),
+        pattern.topology.rule_suffix()
+    );
+    let mut line_length = 0;
+    for (count, tag) in tokens {
+        let token = if count > 1 {
+            format!("{}{}", count, tag)
+        } else {
+            tag.to_string()
+        };
+        if line_length + token.len() > MAX_LINE_LENGTH {
+            output.push('\n');
+            line_length = 0;
+        }
+        line_length += token.len();
+        output.push_str(&token);
+    }
+    output.push('\n');
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GLIDER: &str = "#N Glider
+#C A comment
+x = 3, y = 3, rule = B3/S23
+bob$2bo$3o!
+";
+
+    #[test]
+    fn test_parse_glider() {
+        let pattern = parse(GLIDER).unwrap();
+        assert_eq!(pattern.width, 3);
+        assert_eq!(pattern.height, 3);
+        assert_eq!(pattern.rule, Some(Rule::conway()));
+        assert_eq!(pattern.cells, vec![(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)]);
+    }
+
+    #[test]
+    fn test_write_glider() {
+        let pattern = parse(GLIDER).unwrap();
+        assert_eq!(write(&pattern), "x = 3, y = 3, rule = B3/S23\nbo$2bo$3o!\n");
+    }
+
+    #[test]
+    fn test_round_trip_with_blank_rows() {
+        let pattern = Pattern::from_grid(
+            80,
+            6,
+            [(0, 0), (79, 0), (3, 4), (4, 4)],
+            Some("B36/S23".parse().unwrap()),
+        );
+        let encoded = write(&pattern);
+        assert!(encoded.contains("o78bo4$3b2o!"));
+        assert_eq!(parse(&encoded).unwrap(), pattern);
+    }
+
+    #[test]
+    fn test_topology_suffix() {
+        let pattern = parse("x = 2, y = 1, rule = B2/S34H\n2o!").unwrap();
+        assert_eq!(pattern.topology, Topology::Hexagonal);
+        assert_eq!(pattern.rule, Some("B2/S34".parse().unwrap()));
+        assert_eq!(write(&pattern), "x = 2, y = 1, rule = B2/S34H\n2o!\n");
+
+        let triangles = Pattern::from_grid(2, 2, [(0, 0), (1, 1)], Some(Rule::conway()))
+            .with_topology(Topology::Triangular);
+        assert!(write(&triangles).starts_with("x = 2, y = 2, rule = B3/S23L\n"));
+        assert_eq!(parse(&write(&triangles)).unwrap(), triangles);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(matches!(
+            parse("x = 3\nbo!"),
+            Err(PatternError::InvalidHeader(_))
+        ));
+        assert_eq!(
+            parse("x = 2, y = 1, rule = B3/S23:T10,10\n2o!")
+                .unwrap()
+                .width,
+            2
+        );
+        assert!(matches!(
+            parse("x = 1, y = 1, rule = B9/S\no!"),
+            Err(PatternError::InvalidRule(_))
+        ));
+        assert!(matches!(
+            parse("x = 1, y = 1\no?!"),
+            Err(PatternError::UnexpectedCharacter {
+                line: 2,
+                character: '?'
+            })
+        ));
+    }
+}
@@ -0,0 +1,450 @@
+use std::{fmt, str::FromStr};
+
+/// The state of a dead cell.
+pub const DEAD: u8 = 0;
+/// The state of an alive cell, the only one counted as a neighbour.
+pub const ALIVE: u8 = 1;
+
+/// How many disjoint intervals a set of neighbour counts can be made of, enough for any
+/// list of single digits.
+pub const MAX_INTERVALS: usize = 8;
+
+/// A set of neighbour counts kept as sorted, disjoint inclusive intervals, so that Larger
+/// than Life rules with counts in the hundreds stay `Copy`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Counts {
+    intervals: [(u16, u16); MAX_INTERVALS],
+    len: u8,
+}
+
+impl Counts {
+    /// Merges overlapping and adjacent intervals, returning `None` when more than
+    /// `MAX_INTERVALS` remain.
+    pub fn from_intervals(intervals: impl IntoIterator<Item = (u16, u16)>) -> Option<Self> {
+        let mut sorted: Vec<(u16, u16)> = intervals
+            .into_iter()
+            .filter(|(start, end)| start <= end)
+            .collect();
+        sorted.sort_unstable();
+
+        let mut counts = Counts::default();
+        for (start, end) in sorted {
+            match counts.intervals[..counts.len as usize].last_mut() {
+                Some(last) if start <= last.1.saturating_add(1) => last.1 = last.1.max(end),
+                _ => {
+                    *counts.intervals.get_mut(counts.len as usize)? = (start, end);
+                    counts.len += 1;
+                }
+            }
+        }
+        Some(counts)
+    }
+
+    pub fn contains(&self, count: u16) -> bool {
+        self.intervals()
+            .iter()
+            .any(|(start, end)| *start <= count && count <= *end)
+    }
+
+    pub fn intervals(&self) -> &[(u16, u16)] {
+        &self.intervals[..self.len as usize]
+    }
+
+    fn is_digits(&self) -> bool {
+        self.intervals().iter().all(|(_, end)| *end <= 8)
+    }
+}
+
+impl fmt::Display for Counts {
+    /// Single digits for counts up to 8, as in `B36`, and `start..end` intervals separated
+    /// by commas otherwise, as in `B34..45`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_digits() {
+            for (start, end) in self.intervals() {
+                for count in *start..=*end {
+                    write!(f, "{}", count)?;
+                }
+            }
+            return Ok(());
+        }
+        for (index, (start, end)) in self.intervals().iter().enumerate() {
+            if index > 0 {
+                write!(f, ",")?;
+            }
+            write!(f, "{}..{}", start, end)?;
+        }
+        Ok(())
+    }
+}
+
+/// A Life-like rule in birth/survival notation, e.g. `B3/S23` for Conway's Game of Life,
+/// or a Generations rule such as Brian's Brain, `B2/S/3`, whose third part is the number of
+/// cell states.
+///
+/// Counts are not limited to the eight Moore neighbours: Larger than Life rules give
+/// birth and survival as intervals, e.g. `B34..45/S34..58` over a range 5 neighbourhood.
+/// Cells in Generations rules that fail to survive do not die at once but count up
+/// through the dying states `2..states` before turning dead.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Rule {
+    birth: Counts,
+    survival: Counts,
+    states: u8,
+}
+
+impl Rule {
+    /// A rule over single counts. Counts past 8 are kept for the larger neighbourhoods,
+    /// but they cannot make up more than `MAX_INTERVALS` separate intervals.
+    pub fn new(birth: &[u8], survival: &[u8]) -> Result<Self, ParseRuleError> {
+        let counts = |counts: &[u8]| {
+            Counts::from_intervals(counts.iter().map(|count| (*count as u16, *count as u16)))
+                .ok_or_else(|| {
+                    let counts: Vec<String> = counts.iter().map(u8::to_string).collect();
+                    ParseRuleError::TooManyIntervals(counts.join(","))
+                })
+        };
+
+        Ok(Rule::with_counts(counts(birth)?, counts(survival)?, 2))
+    }
+
+    /// A Generations rule with `states` states in total, dead and alive included.
+    pub fn generations(birth: &[u8], survival: &[u8], states: u8) -> Result<Self, ParseRuleError> {
+        Ok(Rule {
+            states: states.max(2),
+            ..Rule::new(birth, survival)?
+        })
+    }
+
+    /// A rule over arbitrary count sets, as used by Larger than Life.
+    pub fn with_counts(birth: Counts, survival: Counts, states: u8) -> Self {
+        Rule {
+            birth,
+            survival,
+            states: states.max(2),
+        }
+    }
+
+    /// Conway's Game of Life, `B3/S23`.
+    pub fn conway() -> Self {
+        Rule::new(&[3], &[2, 3]).expect("three counts fit in two intervals")
+    }
+
+    pub fn birth(&self) -> &Counts {
+        &self.birth
+    }
+
+    pub fn survival(&self) -> &Counts {
+        &self.survival
+    }
+
+    pub fn is_birth(&self, neighbors: u16) -> bool {
+        self.birth.contains(neighbors)
+    }
+
+    pub fn is_survival(&self, neighbors: u16) -> bool {
+        self.survival.contains(neighbors)
+    }
+
+    /// Number of cell states, 2 for Life-like rules.
+    pub fn states(&self) -> u8 {
+        self.states
+    }
+
+    /// The state a cell moves to given the weighted count of its `ALIVE` neighbours.
+    pub fn next(&self, state: u8, neighbors: u16) -> u8 {
+        match state {
+            DEAD => {
+                if self.is_birth(neighbors) {
+                    ALIVE
+                } else {
+                    DEAD
+                }
+            }
+            ALIVE if self.is_survival(neighbors) => ALIVE,
+            dying if dying + 1 < self.states => dying + 1,
+            _ => DEAD,
+        }
+    }
+
+    /// Returns whether a cell is alive in the next generation, for two-state rules.
+    pub fn next_state(&self, alive: bool, neighbors: u16) -> bool {
+        if alive {
+            self.is_survival(neighbors)
+        } else {
+            self.is_birth(neighbors)
+        }
+    }
+}
+
+impl Default for Rule {
+    fn default() -> Self {
+        Rule::conway()
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "B{}/S{}", self.birth, self.survival)?;
+        if self.states > 2 {
+            write!(f, "/{}", self.states)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseRuleError {
+    MissingSeparator,
+    InvalidPrefix(String),
+    InvalidCount(char),
+    InvalidStates(String),
+    InvalidInterval(String),
+    TooManyIntervals(String),
+    /// A field of a `R5,C0,M1,S34..58,B34..45,NM` Larger than Life rulestring.
+    InvalidField(String),
+}
+
+impl fmt::Display for ParseRuleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseRuleError::MissingSeparator => write!(f, "rule must contain a '/' separator"),
+            ParseRuleError::InvalidPrefix(part) => {
+                write!(f, "rule part {:?} must start with 'B' or 'S'", part)
+            }
+            ParseRuleError::InvalidCount(c) => {
+                write!(f, "{:?} is not a neighbour count between 0 and 8", c)
+            }
+            ParseRuleError::InvalidStates(states) => {
+                write!(
+                    f,
+                    "{:?} is not a number of states between 2 and 255",
+                    states
+                )
+            }
+            ParseRuleError::InvalidInterval(interval) => {
+                write!(f, "{:?} is not a count or a start..end interval", interval)
+            }
+            ParseRuleError::TooManyIntervals(counts) => {
+                write!(
+                    f,
+                    "{:?} has more than {} separate intervals",
+                    counts, MAX_INTERVALS
+                )
+            }
+            ParseRuleError::InvalidField(field) => {
+                write!(f, "{:?} is not a valid Larger than Life field", field)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ParseRuleError {}
+
+/// Parses a single `start..end` interval, or a lone count.
+pub(crate) fn parse_interval(interval: &str) -> Result<(u16, u16), ParseRuleError> {
+    let invalid = || ParseRuleError::InvalidInterval(interval.to_string());
+    let (start, end) = interval.split_once("..").unwrap_or((interval, interval));
+    let start = start.trim().parse().map_err(|_| invalid())?;
+    let end = end.trim().parse().map_err(|_| invalid())?;
+    if start > end {
+        return Err(invalid());
+    }
+    Ok((start, end))
+}
+
+/// Parses the digits of a `B`/`S` part, or comma-separated intervals such as `34..45`.
+fn parse_counts(counts: &str) -> Result<Counts, ParseRuleError> {
+    let intervals: Vec<(u16, u16)> = if counts.contains(['.', ',']) {
+        counts
+            .split(',')
+            .map(parse_interval)
+            .collect::<Result<_, _>>()?
+    } else {
+        counts
+            .chars()
+            .map(|c| match c.to_digit(10) {
+                Some(count) if count <= 8 => Ok((count as u16, count as u16)),
+                _ => Err(ParseRuleError::InvalidCount(c)),
+            })
+            .collect::<Result<_, _>>()?
+    };
+    Counts::from_intervals(intervals)
+        .ok_or_else(|| ParseRuleError::TooManyIntervals(counts.to_string()))
+}
+
+fn parse_states(part: &str) -> Result<u8, ParseRuleError> {
+    let digits = part.trim_start_matches(['C', 'c', 'G', 'g']);
+    match digits.parse() {
+        Ok(states) if states >= 2 => Ok(states),
+        _ => Err(ParseRuleError::InvalidStates(part.to_string())),
+    }
+}
+
+impl FromStr for Rule {
+    type Err = ParseRuleError;
+
+    /// Parses `B3/S23` style rulestrings (in either order, case-insensitive) as well
+    /// as the older `23/3` survival/birth notation, both optionally followed by a
+    /// Generations state count: `B2/S/3`, `B2/S/C3` or `345/2/4`. Counts past 8 are
+    /// written as intervals, `B34..45/S34..58`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (first, rest) = s
+            .trim()
+            .split_once('/')
+            .ok_or(ParseRuleError::MissingSeparator)?;
+        let (second, states) = match rest.split_once('/') {
+            Some((second, states)) => (second, Some(parse_states(states)?)),
+            None => (rest, None),
+        };
+
+        let mut birth = None;
+        let mut survival = None;
+        for part in [first, second] {
+            let mut chars = part.chars();
+            match chars.next().map(|c| c.to_ascii_uppercase()) {
+                Some('B') => birth = Some(parse_counts(chars.as_str())?),
+                Some('S') => survival = Some(parse_counts(chars.as_str())?),
+                _ => {}
+            }
+        }
+
+        let states = states.unwrap_or(2);
+        match (birth, survival) {
+            (Some(birth), Some(survival)) => Ok(Rule::with_counts(birth, survival, states)),
+            (None, None) => {
+                let survival = parse_counts(first)?;
+                let birth = parse_counts(second)?;
+                Ok(Rule::with_counts(birth, survival, states))
+            }
+            (Some(_), None) => Err(ParseRuleError::InvalidPrefix(second.to_string())),
+            (None, Some(_)) => Err(ParseRuleError::InvalidPrefix(first.to_string())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_conway() {
+        let rule: Rule = "B3/S23".parse().unwrap();
+        assert_eq!(rule, Rule::conway());
+        assert!(rule.next_state(false, 3));
+        assert!(rule.next_state(true, 2));
+        assert!(!rule.next_state(true, 4));
+        assert!(!rule.next_state(false, 2));
+    }
+
+    #[test]
+    fn test_parse_variants() {
+        let highlife: Rule = "B36/S23".parse().unwrap();
+        assert!(highlife.next_state(false, 6));
+        assert_eq!(highlife.to_string(), "B36/S23");
+
+        let seeds: Rule = "b2/s".parse().unwrap();
+        assert!(seeds.next_state(false, 2));
+        assert!(!seeds.next_state(true, 2));
+        assert_eq!(seeds.to_string(), "B2/S");
+
+        assert_eq!("S23/B3".parse::<Rule>().unwrap(), Rule::conway());
+        assert_eq!("23/3".parse::<Rule>().unwrap(), Rule::conway());
+    }
+
+    #[test]
+    fn test_generations() {
+        let brians_brain: Rule = "B2/S/3".parse().unwrap();
+        assert_eq!(brians_brain.states(), 3);
+        assert_eq!(brians_brain.to_string(), "B2/S/3");
+        assert_eq!("/2/3".parse::<Rule>().unwrap(), brians_brain);
+        assert_eq!("B2/S/C3".parse::<Rule>().unwrap(), brians_brain);
+        assert_eq!(brians_brain.next(DEAD, 2), ALIVE);
+        assert_eq!(brians_brain.next(ALIVE, 2), 2);
+        assert_eq!(brians_brain.next(2, 2), DEAD);
+
+        let star_wars: Rule = "345/2/4".parse().unwrap();
+        assert_eq!(star_wars.to_string(), "B2/S345/4");
+        assert_eq!(star_wars.next(ALIVE, 4), ALIVE);
+        assert_eq!(star_wars.next(ALIVE, 1), 2);
+        assert_eq!(star_wars.next(2, 2), 3);
+        assert_eq!(star_wars.next(3, 2), DEAD);
+
+        // Life-like rules never pass through a dying state.
+        assert_eq!(Rule::conway().states(), 2);
+        assert_eq!(Rule::conway().next(ALIVE, 1), DEAD);
+        assert_eq!("B3/S23/2".parse::<Rule>().unwrap(), Rule::conway());
+    }
+
+    #[test]
+    fn test_intervals() {
+        let bosco: Rule = "B34..45/S33..57".parse().unwrap();
+        assert_eq!(bosco.to_string(), "B34..45/S33..57");
+        assert!(bosco.next_state(false, 34));
+        assert!(bosco.next_state(false, 45));
+        assert!(!bosco.next_state(false, 46));
+        assert!(bosco.next_state(true, 33));
+        assert!(!bosco.next_state(true, 32));
+
+        // Adjacent and overlapping intervals merge, and digit-sized sets print as digits.
+        assert_eq!("B3..3/S2,3".parse::<Rule>().unwrap(), Rule::conway());
+        assert_eq!(
+            "B1..4,3..9,10/S"
+                .parse::<Rule>()
+                .unwrap()
+                .birth()
+                .intervals(),
+            &[(1, 10)]
+        );
+        assert_eq!(Rule::conway().to_string(), "B3/S23");
+        assert_eq!(
+            "B2..3,9..9/S".parse::<Rule>().unwrap().to_string(),
+            "B2..3,9..9/S"
+        );
+
+        // `Rule::new` keeps counts past 8 rather than dropping them.
+        assert_eq!(
+            Rule::new(&[2, 3, 9], &[]),
+            Ok("B2..3,9..9/S".parse().unwrap())
+        );
+        assert_eq!(
+            Rule::generations(&[2], &[], 3),
+            Ok("B2/S/3".parse().unwrap())
+        );
+        assert_eq!(
+            Rule::new(&[1, 3, 5, 7, 9, 11, 13, 15, 17], &[]),
+            Err(ParseRuleError::TooManyIntervals(
+                "1,3,5,7,9,11,13,15,17".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(
+            "B3S23".parse::<Rule>(),
+            Err(ParseRuleError::MissingSeparator)
+        );
+        assert_eq!(
+            "B39/S23".parse::<Rule>(),
+            Err(ParseRuleError::InvalidCount('9'))
+        );
+        assert_eq!(
+            "B3/23".parse::<Rule>(),
+            Err(ParseRuleError::InvalidPrefix("23".to_string()))
+        );
+        assert_eq!(
+            "B2/S/1".parse::<Rule>(),
+            Err(ParseRuleError::InvalidStates("1".to_string()))
+        );
+        assert_eq!(
+            "B5..4/S".parse::<Rule>(),
+            Err(ParseRuleError::InvalidInterval("5..4".to_string()))
+        );
+        assert_eq!(
+            "B1,3,5,7,9,11,13,15,17/S".parse::<Rule>(),
+            Err(ParseRuleError::TooManyIntervals(
+                "1,3,5,7,9,11,13,15,17".to_string()
+            ))
+        );
+    }
+}
@@ -0,0 +1,269 @@
+//! Storage and stepping for the unbounded universe: the plane is cut into `CHUNK_SIZE`
+//! square chunks and only chunks with alive cells are kept, so patterns can travel
+//! without ever meeting an edge.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::{Pattern, Rule};
+
+pub const CHUNK_SIZE: i32 = 16;
+
+/// One bitmask per row, bit `x` of `rows[y]` being the cell at `(x, y)` inside the chunk.
+pub type Chunk = [u16; CHUNK_SIZE as usize];
+
+pub const EMPTY_CHUNK: Chunk = [0; CHUNK_SIZE as usize];
+
+/// The chunk holding a cell and the cell's position inside it.
+pub fn split(x: i32, y: i32) -> ((i32, i32), (usize, usize)) {
+    (
+        (x.div_euclid(CHUNK_SIZE), y.div_euclid(CHUNK_SIZE)),
+        (
+            x.rem_euclid(CHUNK_SIZE) as usize,
+            y.rem_euclid(CHUNK_SIZE) as usize,
+        ),
+    )
+}
+
+pub fn is_empty(chunk: &Chunk) -> bool {
+    chunk.iter().all(|row| *row == 0)
+}
+
+pub fn population(chunk: &Chunk) -> usize {
+    chunk.iter().map(|row| row.count_ones() as usize).sum()
+}
+
+/// Whether the chunk has alive cells on the edge or corner facing `(dx, dy)`, i.e.
+/// whether births can happen in the chunk on that side next generation.
+pub fn spills_into(chunk: &Chunk, dx: i32, dy: i32) -> bool {
+    let last = CHUNK_SIZE as usize - 1;
+    let columns = match dx {
+        -1 => 1,
+        1 => 1 << last,
+        _ => u16::MAX,
+    };
+    let rows = match dy {
+        -1 => &chunk[..1],
+        1 => &chunk[last..],
+        _ => &chunk[..],
+    };
+    rows.iter().any(|row| row & columns != 0)
+}
+
+/// The eight chunk positions around `key`.
+pub fn neighbor_keys((x, y): (i32, i32)) -> impl Iterator<Item = ((i32, i32), (i32, i32))> {
+    (-1..=1)
+        .flat_map(|dy| (-1..=1).map(move |dx| (dx, dy)))
+        .filter(|offset| *offset != (0, 0))
+        .map(move |(dx, dy)| ((x + dx, y + dy), (dx, dy)))
+}
+
+/// The next generation of the chunk at `key`, reading it and its eight neighbours through
+/// `chunk_at`, which should return `EMPTY_CHUNK` for chunks that are not stored.
+pub fn step_chunk(rule: &Rule, key: (i32, i32), chunk_at: impl Fn((i32, i32)) -> Chunk) -> Chunk {
+    let size = CHUNK_SIZE as usize;
+    let (x, y) = key;
+    let [nw, n, ne] = [-1, 0, 1].map(|dx| chunk_at((x + dx, y - 1)));
+    let [w, centre, e] = [-1, 0, 1].map(|dx| chunk_at((x + dx, y)));
+    let [sw, s, se] = [-1, 0, 1].map(|dx| chunk_at((x + dx, y + 1)));
+
+    // Rows of the chunk plus a one cell border, bit 0 being the column left of the chunk.
+    let row = |left: &Chunk, middle: &Chunk, right: &Chunk, index: usize| -> u32 {
+        (left[index] as u32 >> (size - 1))
+            | (middle[index] as u32) << 1
+            | ((right[index] as u32) & 1) << (size + 1)
+    };
+    let mut window = [0u32; CHUNK_SIZE as usize + 2];
+    window[0] = row(&nw, &n, &ne, size - 1);
+    for (index, rows) in window[1..=size].iter_mut().enumerate() {
+        *rows = row(&w, &centre, &e, index);
+    }
+    window[size + 1] = row(&sw, &s, &se, 0);
+
+    let mut next = EMPTY_CHUNK;
+    for (y, next_row) in next.iter_mut().enumerate() {
+        for x in 0..size {
+            let block = |row: u32| (row >> x & 0b111).count_ones();
+            let alive = window[y + 1] >> (x + 1) & 1 != 0;
+            let neighbors = block(window[y]) + block(window[y + 1]) + block(window[y + 2]);
+            if rule.next_state(alive, (neighbors - alive as u32) as u16) {
+                *next_row |= 1 << x;
+            }
+        }
+    }
+    next
+}
+
+/// Coordinates of the alive cells of the chunk at `key`.
+pub fn alive_cells(key: (i32, i32), chunk: &Chunk) -> impl Iterator<Item = (i32, i32)> + '_ {
+    chunk.iter().enumerate().flat_map(move |(y, row)| {
+        (0..CHUNK_SIZE)
+            .filter(move |x| row >> x & 1 != 0)
+            .map(move |x| (key.0 * CHUNK_SIZE + x, key.1 * CHUNK_SIZE + y as i32))
+    })
+}
+
+/// Every chunk position that needs stepping: the stored chunks plus the neighbours they
+/// spill into.
+pub fn active_keys<'a>(
+    chunks: impl Iterator<Item = (&'a (i32, i32), &'a Chunk)>,
+) -> HashSet<(i32, i32)> {
+    let mut keys = HashSet::new();
+    for (key, chunk) in chunks {
+        keys.insert(*key);
+        keys.extend(
+            neighbor_keys(*key)
+                .filter(|(_, (dx, dy))| spills_into(chunk, *dx, *dy))
+                .map(|(neighbor, _)| neighbor),
+        );
+    }
+    keys
+}
+
+/// A whole unbounded universe kept as a map of non-empty chunks.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SparseLife {
+    pub rule: Rule,
+    pub chunks: HashMap<(i32, i32), Chunk>,
+}
+
+impl SparseLife {
+    pub fn new(rule: Rule, cells: impl IntoIterator<Item = (i32, i32)>) -> Self {
+        let mut life = SparseLife {
+            rule,
+            chunks: HashMap::new(),
+        };
+        for (x, y) in cells {
+            life.set_alive(x, y);
+        }
+        life
+    }
+
+    pub fn set_alive(&mut self, x: i32, y: i32) {
+        let (key, (x, y)) = split(x, y);
+        self.chunks.entry(key).or_insert(EMPTY_CHUNK)[y] |= 1 << x;
+    }
+
+    pub fn is_alive(&self, x: i32, y: i32) -> bool {
+        let (key, (x, y)) = split(x, y);
+        self.chunks
+            .get(&key)
+            .is_some_and(|chunk| chunk[y] >> x & 1 != 0)
+    }
+
+    pub fn population(&self) -> usize {
+        self.chunks.values().map(population).sum()
+    }
+
+    /// Advances one generation, dropping chunks that died out.
+    pub fn step(&mut self) {
+        let chunk_at = |key| self.chunks.get(&key).copied().unwrap_or(EMPTY_CHUNK);
+        self.chunks = active_keys(self.chunks.iter())
+            .into_iter()
+            .map(|key| (key, step_chunk(&self.rule, key, chunk_at)))
+            .filter(|(_, chunk)| !is_empty(chunk))
+            .collect();
+    }
+
+    /// Coordinates of every alive cell, row by row.
+    pub fn alive_cells(&self) -> Vec<(i32, i32)> {
+        let mut cells: Vec<(i32, i32)> = self
+            .chunks
+            .iter()
+            .flat_map(|(key, chunk)| alive_cells(*key, chunk))
+            .collect();
+        cells.sort_by_key(|(x, y)| (*y, *x));
+        cells
+    }
+
+    pub fn to_pattern(&self) -> Pattern {
+        Pattern::from_cells(self.alive_cells(), Some(self.rule))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Boundary, Soup};
+
+    #[test]
+    fn test_split() {
+        assert_eq!(split(0, 0), ((0, 0), (0, 0)));
+        assert_eq!(split(17, 15), ((1, 0), (1, 15)));
+        assert_eq!(split(-1, -16), ((-1, -1), (15, 0)));
+        assert_eq!(split(-17, 3), ((-2, 0), (15, 3)));
+    }
+
+    #[test]
+    fn test_spills_into() {
+        let mut chunk = EMPTY_CHUNK;
+        chunk[0] = 1;
+        assert!(spills_into(&chunk, -1, -1));
+        assert!(spills_into(&chunk, -1, 0));
+        assert!(spills_into(&chunk, 0, -1));
+        assert!(!spills_into(&chunk, 1, 0));
+        assert!(!spills_into(&chunk, 0, 1));
+        assert!(!spills_into(&chunk, -1, 1));
+    }
+
+    #[test]
+    fn test_matches_dead_grid_inside_its_bounds() {
+        // A soup in the middle of a large dead grid behaves exactly like the unbounded
+        // plane as long as nothing reaches the edge.
+        let (width, height) = (96, 96);
+        let soup = Soup::new(99, 0.4);
+        let initial: HashSet<(i32, i32)> = soup
+            .alive_cells(24, 24)
+            .map(|(x, y)| (x as i32 + 36, y as i32 + 36))
+            .collect();
+
+        let rule = Rule::default();
+        let mut life = SparseLife::new(rule, initial.iter().copied());
+        let mut cells = initial;
+        for generation in 0..30 {
+            let mut next = HashSet::new();
+            for y in 0..height as i32 {
+                for x in 0..width as i32 {
+                    let neighbors = Boundary::Dead
+                        .neighbors(x, y, width, height)
+                        .filter(|neighbor| cells.contains(neighbor))
+                        .count();
+                    if rule.next_state(cells.contains(&(x, y)), neighbors as u16) {
+                        next.insert((x, y));
+                    }
+                }
+            }
+            cells = next;
+            life.step();
+
+            let mut expected: Vec<_> = cells.iter().copied().collect();
+            expected.sort_by_key(|(x, y)| (*y, *x));
+            assert_eq!(
+                life.alive_cells(),
+                expected,
+                "generation {}",
+                generation + 1
+            );
+        }
+    }
+
+    #[test]
+    fn test_glider_crosses_chunks() {
+        let glider = [(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)];
+        let mut life = SparseLife::new(Rule::default(), glider);
+        for _ in 0..400 {
+            life.step();
+        }
+        let expected: Vec<_> = glider.iter().map(|(x, y)| (x + 100, y + 100)).collect();
+        assert_eq!(life.alive_cells(), expected);
+        assert_eq!(life.chunks.len(), 1);
+
+        // And the same glider flipped to travel towards negative coordinates.
+        let flipped = glider.map(|(x, y)| (-x, -y));
+        let mut life = SparseLife::new(Rule::default(), flipped);
+        for _ in 0..40 {
+            life.step();
+        }
+        assert!(flipped.iter().all(|(x, y)| life.is_alive(x - 10, y - 10)));
+        assert_eq!(life.population(), 5);
+    }
+}
@@ -0,0 +1,317 @@
+//! A per-generation record of how busy the grid is, written by `--stats-output` so step
+//! times can be lined up with the activity that caused them.
+
+use std::{collections::HashMap, fmt::Write as _, path::Path, time::Duration};
+
+use crate::{
+    rule::{ALIVE, DEAD},
+    sparse::{self, Chunk, EMPTY_CHUNK},
+};
+
+/// The smallest rectangle holding every alive cell, edges included.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Bounds {
+    pub min_x: i32,
+    pub min_y: i32,
+    pub max_x: i32,
+    pub max_y: i32,
+}
+
+impl Bounds {
+    fn around(x: i32, y: i32) -> Self {
+        Bounds {
+            min_x: x,
+            min_y: y,
+            max_x: x,
+            max_y: y,
+        }
+    }
+
+    fn union(self, other: Bounds) -> Self {
+        Bounds {
+            min_x: self.min_x.min(other.min_x),
+            min_y: self.min_y.min(other.min_y),
+            max_x: self.max_x.max(other.max_x),
+            max_y: self.max_y.max(other.max_y),
+        }
+    }
+}
+
+/// What one generation looks like and how it differs from the one before.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct GenerationStats {
+    pub generation: u64,
+    /// Alive cells.
+    pub population: usize,
+    /// Cells in one of the refractory states of a Generations rule.
+    pub dying: usize,
+    /// Dead cells that came alive.
+    pub births: usize,
+    /// Alive cells that died or started dying.
+    pub deaths: usize,
+    /// Cells whose state changed at all, dying cells moving on included.
+    pub changed: usize,
+    pub bounds: Option<Bounds>,
+    /// How long the step that produced this generation took, `None` for generation 0.
+    pub duration: Option<Duration>,
+}
+
+impl GenerationStats {
+    pub fn new(generation: u64) -> Self {
+        GenerationStats {
+            generation,
+            ..Default::default()
+        }
+    }
+
+    /// Counts a cell as it is now, without looking at what it was.
+    pub fn count(&mut self, x: i32, y: i32, state: u8) {
+        match state {
+            DEAD => {}
+            ALIVE => {
+                self.population += 1;
+                let cell = Bounds::around(x, y);
+                self.bounds = Some(self.bounds.map_or(cell, |bounds| bounds.union(cell)));
+            }
+            _ => self.dying += 1,
+        }
+    }
+
+    /// Counts a cell that went from state `before` to `after`.
+    pub fn record(&mut self, x: i32, y: i32, before: u8, after: u8) {
+        self.count(x, y, after);
+        if before != after {
+            self.changed += 1;
+            if after == ALIVE {
+                self.births += 1;
+            } else if before == ALIVE {
+                self.deaths += 1;
+            }
+        }
+    }
+
+    /// Adds up the counts of two halves of the same generation.
+    pub fn merge(self, other: GenerationStats) -> Self {
+        GenerationStats {
+            generation: self.generation,
+            population: self.population + other.population,
+            dying: self.dying + other.dying,
+            births: self.births + other.births,
+            deaths: self.deaths + other.deaths,
+            changed: self.changed + other.changed,
+            bounds: match (self.bounds, other.bounds) {
+                (Some(a), Some(b)) => Some(a.union(b)),
+                (a, b) => a.or(b),
+            },
+            duration: self.duration.or(other.duration),
+        }
+    }
+
+    /// Counts the alive cells of the chunk at `key` of the unbounded universe.
+    pub fn count_chunk(&mut self, key: (i32, i32), chunk: &Chunk) {
+        for (x, y) in sparse::alive_cells(key, chunk) {
+            self.count(x, y, ALIVE);
+        }
+    }
+
+    /// Counts a chunk that went from `before` to `after`. Chunks only have two states.
+    pub fn record_chunk(&mut self, key: (i32, i32), before: &Chunk, after: &Chunk) {
+        for (before, after) in before.iter().zip(after) {
+            let born = (after & !before).count_ones() as usize;
+            let died = (before & !after).count_ones() as usize;
+            self.births += born;
+            self.deaths += died;
+            self.changed += born + died;
+        }
+        self.count_chunk(key, after);
+    }
+}
+
+/// Compares two generations of the unbounded universe, chunks missing from either map
+/// being empty.
+pub fn chunk_stats(
+    generation: u64,
+    before: &HashMap<(i32, i32), Chunk>,
+    after: &HashMap<(i32, i32), Chunk>,
+) -> GenerationStats {
+    let mut stats = GenerationStats::new(generation);
+    for (key, chunk) in after {
+        stats.record_chunk(*key, before.get(key).unwrap_or(&EMPTY_CHUNK), chunk);
+    }
+    for (key, chunk) in before {
+        if !after.contains_key(key) {
+            stats.record_chunk(*key, chunk, &EMPTY_CHUNK);
+        }
+    }
+    stats
+}
+
+const COLUMNS: [&str; 11] = [
+    "generation",
+    "population",
+    "dying",
+    "births",
+    "deaths",
+    "changed",
+    "min_x",
+    "min_y",
+    "max_x",
+    "max_y",
+    "nanoseconds",
+];
+
+/// The record's values in `COLUMNS` order, `None` where there is nothing to report.
+fn values(stats: &GenerationStats) -> [Option<String>; 11] {
+    let bound = |value: fn(&Bounds) -> i32| stats.bounds.as_ref().map(|b| value(b).to_string());
+    [
+        Some(stats.generation.to_string()),
+        Some(stats.population.to_string()),
+        Some(stats.dying.to_string()),
+        Some(stats.births.to_string()),
+        Some(stats.deaths.to_string()),
+        Some(stats.changed.to_string()),
+        bound(|b| b.min_x),
+        bound(|b| b.min_y),
+        bound(|b| b.max_x),
+        bound(|b| b.max_y),
+        stats.duration.map(|d| d.as_nanos().to_string()),
+    ]
+}
+
+/// One row per generation, leaving the bounds of an empty grid and the duration of
+/// generation 0 blank.
+pub fn to_csv(stats: &[GenerationStats]) -> String {
+    let mut csv = COLUMNS.join(",");
+    csv.push('\n');
+    for record in stats {
+        let row: Vec<String> = values(record)
+            .into_iter()
+            .map(Option::unwrap_or_default)
+            .collect();
+        let _ = writeln!(csv, "{}", row.join(","));
+    }
+    csv
+}
+
+/// An array with one object per generation, using `null` where the CSV leaves blanks.
+pub fn to_json(stats: &[GenerationStats]) -> String {
+    let mut json = String::from("[\n");
+    for (index, record) in stats.iter().enumerate() {
+        let fields: Vec<String> = COLUMNS
+            .iter()
+            .zip(values(record))
+            .map(|(column, value)| {
+                format!("\"{}\": {}", column, value.as_deref().unwrap_or("null"))
+            })
+            .collect();
+        let _ = write!(json, "  {{{}}}", fields.join(", "));
+        json.push_str(if index + 1 < stats.len() { ",\n" } else { "\n" });
+    }
+    json.push_str("]\n");
+    json
+}
+
+/// Writes the records as JSON or CSV depending on the extension of `filename`.
+pub fn save(stats: &[GenerationStats], filename: &str) -> std::io::Result<()> {
+    let contents = match Path::new(filename).extension().and_then(|e| e.to_str()) {
+        Some("json") => to_json(stats),
+        _ => to_csv(stats),
+    };
+    std::fs::write(filename, contents)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record() {
+        let mut stats = GenerationStats::new(1);
+        stats.record(0, 0, DEAD, ALIVE);
+        stats.record(4, 2, ALIVE, ALIVE);
+        stats.record(1, 1, ALIVE, 2);
+        stats.record(2, 1, 2, 3);
+        stats.record(3, 1, DEAD, DEAD);
+
+        assert_eq!(stats.population, 2);
+        assert_eq!(stats.dying, 2);
+        assert_eq!((stats.births, stats.deaths, stats.changed), (1, 1, 3));
+        assert_eq!(
+            stats.bounds,
+            Some(Bounds {
+                min_x: 0,
+                min_y: 0,
+                max_x: 4,
+                max_y: 2
+            })
+        );
+
+        let mut half = GenerationStats::new(1);
+        half.record(-3, 5, DEAD, ALIVE);
+        let merged = stats.merge(half);
+        assert_eq!(
+            (merged.population, merged.births, merged.changed),
+            (3, 2, 4)
+        );
+        assert_eq!(merged.bounds.map(|b| (b.min_x, b.max_y)), Some((-3, 5)));
+    }
+
+    #[test]
+    fn test_chunk_stats_match_cell_by_cell() {
+        let mut before = HashMap::new();
+        let mut after = HashMap::new();
+        // A blinker turning on its side across a chunk edge, plus a chunk dying out.
+        let mut edge = EMPTY_CHUNK;
+        edge[15] = 0b111;
+        before.insert((0, 0), edge);
+        before.insert((3, 3), [1; 16]);
+        let mut top = EMPTY_CHUNK;
+        top[14] = 0b10;
+        top[15] = 0b10;
+        after.insert((0, 0), top);
+        let mut bottom = EMPTY_CHUNK;
+        bottom[0] = 0b10;
+        after.insert((0, 1), bottom);
+
+        let stats = chunk_stats(7, &before, &after);
+        assert_eq!(stats.generation, 7);
+        assert_eq!(stats.population, 3);
+        assert_eq!(stats.births, 2);
+        assert_eq!(stats.deaths, 2 + 16);
+        assert_eq!(stats.changed, 20);
+        assert_eq!(
+            stats.bounds,
+            Some(Bounds {
+                min_x: 1,
+                min_y: 14,
+                max_x: 1,
+                max_y: 16
+            })
+        );
+    }
+
+    #[test]
+    fn test_export() {
+        let mut first = GenerationStats::new(0);
+        first.count(2, 3, ALIVE);
+        let second = GenerationStats {
+            generation: 1,
+            deaths: 1,
+            changed: 1,
+            duration: Some(Duration::from_micros(5)),
+            ..Default::default()
+        };
+        let stats = [first, second];
+
+        assert_eq!(
+            to_csv(&stats),
+            "generation,population,dying,births,deaths,changed,min_x,min_y,max_x,max_y,nanoseconds\n\
+             0,1,0,0,0,0,2,3,2,3,\n\
+             1,0,0,0,1,1,,,,,5000\n"
+        );
+        let json = to_json(&stats);
+        assert!(json.starts_with("[\n  {\"generation\": 0, \"population\": 1,"));
+        assert!(json.contains("\"min_x\": null, \"min_y\": null"));
+        assert!(json.ends_with("\"nanoseconds\": 5000}\n]\n"));
+    }
+}
@@ -0,0 +1,228 @@
+//! Helpers for the duration files the backends write and the statistics built from them.
+
+use std::{fmt::Write as _, fs, io, path::Path, time::Duration};
+
+/// Summary statistics over a set of timings.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Summary {
+    pub count: usize,
+    pub min: Duration,
+    pub max: Duration,
+    pub mean: Duration,
+    pub p50: Duration,
+    pub p99: Duration,
+    pub stddev: Duration,
+}
+
+impl Summary {
+    pub fn new(durations: &[Duration]) -> Self {
+        if durations.is_empty() {
+            return Summary::default();
+        }
+
+        let mut sorted = durations.to_vec();
+        sorted.sort();
+        let nanos: Vec<f64> = sorted.iter().map(|d| d.as_nanos() as f64).collect();
+        let mean = nanos.iter().sum::<f64>() / nanos.len() as f64;
+        let variance = nanos.iter().map(|n| (n - mean).powi(2)).sum::<f64>() / nanos.len() as f64;
+
+        Summary {
+            count: sorted.len(),
+            min: sorted[0],
+            max: sorted[sorted.len() - 1],
+            mean: Duration::from_nanos(mean.round() as u64),
+            p50: percentile(&sorted, 50.0),
+            p99: percentile(&sorted, 99.0),
+            stddev: Duration::from_nanos(variance.sqrt().round() as u64),
+        }
+    }
+}
+
+/// Nearest-rank percentile of an already sorted, non-empty slice.
+pub fn percentile(sorted: &[Duration], percent: f64) -> Duration {
+    let rank = (percent / 100.0 * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+/// Parses the `{:?}` form of a `Duration`, e.g. `1.5ms`, `830ns` or `2.734853s`.
+pub fn parse_duration(s: &str) -> Option<Duration> {
+    let s = s.trim();
+    let split = s.find(|c: char| !c.is_ascii_digit() && c != '.')?;
+    let (value, unit) = s.split_at(split);
+    let value: f64 = value.parse().ok()?;
+    let seconds = match unit {
+        "ns" => value / 1e9,
+        "µs" | "us" => value / 1e6,
+        "ms" => value / 1e3,
+        "s" => value,
+        _ => return None,
+    };
+    Some(Duration::from_secs_f64(seconds))
+}
+
+/// Reads a file with one `{:?}` formatted duration per line, as written by
+/// `--durations-output`.
+pub fn load_durations(filename: &str) -> io::Result<Vec<Duration>> {
+    fs::read_to_string(filename)?
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            parse_duration(line).ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("invalid duration {:?} in {}", line, filename),
+                )
+            })
+        })
+        .collect()
+}
+
+/// Every run of each instrumented system, kept in the order the systems first reported.
+#[derive(Debug, Clone, Default)]
+pub struct SystemTimings {
+    systems: Vec<(&'static str, Vec<Duration>)>,
+}
+
+const SYSTEM_COLUMNS: [&str; 7] = [
+    "system", "count", "min_ns", "mean_ns", "p50_ns", "p99_ns", "max_ns",
+];
+
+impl SystemTimings {
+    pub fn record(&mut self, system: &'static str, duration: Duration) {
+        match self.systems.iter_mut().find(|(name, _)| *name == system) {
+            Some((_, durations)) => durations.push(duration),
+            None => self.systems.push((system, vec![duration])),
+        }
+    }
+
+    pub fn durations(&self, system: &str) -> Option<&[Duration]> {
+        self.systems
+            .iter()
+            .find(|(name, _)| *name == system)
+            .map(|(_, durations)| durations.as_slice())
+    }
+
+    pub fn summaries(&self) -> Vec<(&'static str, Summary)> {
+        self.systems
+            .iter()
+            .map(|(name, durations)| (*name, Summary::new(durations)))
+            .collect()
+    }
+
+    /// The summary's values in `SYSTEM_COLUMNS` order after the name.
+    fn values(summary: &Summary) -> [String; 6] {
+        [
+            summary.count.to_string(),
+            summary.min.as_nanos().to_string(),
+            summary.mean.as_nanos().to_string(),
+            summary.p50.as_nanos().to_string(),
+            summary.p99.as_nanos().to_string(),
+            summary.max.as_nanos().to_string(),
+        ]
+    }
+
+    /// One row per system.
+    pub fn to_csv(&self) -> String {
+        let mut csv = SYSTEM_COLUMNS.join(",");
+        csv.push('\n');
+        for (name, summary) in self.summaries() {
+            let _ = writeln!(csv, "{},{}", name, Self::values(&summary).join(","));
+        }
+        csv
+    }
+
+    /// An array with one object per system, with the same fields as the CSV.
+    pub fn to_json(&self) -> String {
+        let summaries = self.summaries();
+        let mut json = String::from("[\n");
+        for (index, (name, summary)) in summaries.iter().enumerate() {
+            let mut fields = vec![format!("\"system\": \"{}\"", name)];
+            fields.extend(
+                SYSTEM_COLUMNS[1..]
+                    .iter()
+                    .zip(Self::values(summary))
+                    .map(|(column, value)| format!("\"{}\": {}", column, value)),
+            );
+            let _ = write!(json, "  {{{}}}", fields.join(", "));
+            json.push_str(if index + 1 < summaries.len() {
+                ",\n"
+            } else {
+                "\n"
+            });
+        }
+        json.push_str("]\n");
+        json
+    }
+
+    /// Writes the summaries as JSON or CSV depending on the extension of `filename`, like
+    /// `stats::save`.
+    pub fn save(&self, filename: &str) -> io::Result<()> {
+        let contents = match Path::new(filename).extension().and_then(|e| e.to_str()) {
+            Some("json") => self.to_json(),
+            _ => self.to_csv(),
+        };
+        fs::write(filename, contents)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_duration() {
+        for duration in [
+            Duration::from_nanos(830),
+            Duration::from_nanos(12_345),
+            Duration::from_micros(5_891),
+            Duration::from_millis(2_734),
+        ] {
+            assert_eq!(parse_duration(&format!("{:?}", duration)), Some(duration));
+        }
+        assert_eq!(parse_duration("1.5ms"), Some(Duration::from_micros(1500)));
+        assert_eq!(parse_duration("12 parsecs"), None);
+        assert_eq!(parse_duration("ms"), None);
+    }
+
+    #[test]
+    fn test_summary() {
+        let durations: Vec<Duration> = (1..=100).rev().map(Duration::from_millis).collect();
+        let summary = Summary::new(&durations);
+        assert_eq!(summary.count, 100);
+        assert_eq!(summary.min, Duration::from_millis(1));
+        assert_eq!(summary.max, Duration::from_millis(100));
+        assert_eq!(summary.mean, Duration::from_micros(50_500));
+        assert_eq!(summary.p50, Duration::from_millis(50));
+        assert_eq!(summary.p99, Duration::from_millis(99));
+
+        assert_eq!(Summary::new(&[]), Summary::default());
+    }
+
+    #[test]
+    fn test_system_timings() {
+        let mut timings = SystemTimings::default();
+        for micros in [3, 1, 2] {
+            timings.record("update_cells_system", Duration::from_micros(micros));
+        }
+        timings.record("sync_cell_positions", Duration::from_nanos(500));
+
+        assert_eq!(
+            timings.durations("update_cells_system").map(<[_]>::len),
+            Some(3)
+        );
+        assert_eq!(timings.durations("release_system"), None);
+        assert_eq!(
+            timings.to_csv(),
+            "system,count,min_ns,mean_ns,p50_ns,p99_ns,max_ns\n\
+             update_cells_system,3,1000,2000,2000,3000,3000\n\
+             sync_cell_positions,1,500,500,500,500,500\n"
+        );
+        assert_eq!(
+            timings.to_json(),
+            "[\n  {\"system\": \"update_cells_system\", \"count\": 3, \"min_ns\": 1000, \
+             \"mean_ns\": 2000, \"p50_ns\": 2000, \"p99_ns\": 3000, \"max_ns\": 3000},\n  \
+             {\"system\": \"sync_cell_positions\", \"count\": 1, \"min_ns\": 500, \
+             \"mean_ns\": 500, \"p50_ns\": 500, \"p99_ns\": 500, \"max_ns\": 500}\n]\n"
+        );
+    }
+}
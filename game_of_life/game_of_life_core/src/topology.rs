@@ -0,0 +1,302 @@
+use std::{fmt, str::FromStr};
+
+use crate::neighborhood::{Neighborhood, Offset};
+
+/// Height of a row of hexagons or triangles, in units of the cell side.
+const ROW_HEIGHT: f32 = 0.866_025_4;
+
+/// The shape of the cells the `width x height` grid is made of.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Topology {
+    /// Square cells, whose neighbours are given by the `Neighborhood`.
+    #[default]
+    Square,
+    /// Hexagons in axial coordinates: every row is shifted half a cell left of the one
+    /// above, so the six neighbours are the Moore neighbours minus `(1, -1)` and `(-1, 1)`,
+    /// as in Golly.
+    Hexagonal,
+    /// Triangles pointing up where `x + y` is even and down where it is odd, half a cell
+    /// wide each.
+    Triangular,
+}
+
+impl Topology {
+    pub const ALL: [Topology; 3] = [Topology::Square, Topology::Hexagonal, Topology::Triangular];
+
+    /// Hexagons and triangles only come with range 1 neighbourhoods: `moore:1` is every
+    /// cell sharing a corner and `vonneumann:1` every cell sharing an edge, the same six
+    /// for hexagons but twelve against three for triangles.
+    pub fn supports(self, neighborhood: &Neighborhood) -> bool {
+        match self {
+            Topology::Square => true,
+            Topology::Hexagonal | Topology::Triangular => matches!(
+                neighborhood,
+                Neighborhood::Moore(1) | Neighborhood::VonNeumann(1)
+            ),
+        }
+    }
+
+    /// The neighbour offsets of `neighborhood` on this tiling.
+    ///
+    /// Panics when the topology does not `support` the neighbourhood.
+    pub fn stencil(self, neighborhood: &Neighborhood) -> Stencil {
+        assert!(
+            self.supports(neighborhood),
+            "the {} topology has no {} neighbourhood",
+            self,
+            neighborhood
+        );
+        let edges_only = matches!(neighborhood, Neighborhood::VonNeumann(_));
+        let unweighted = |offsets: &[(i32, i32)]| -> Vec<Offset> {
+            offsets.iter().map(|offset| (*offset, 1)).collect()
+        };
+        match self {
+            Topology::Square => {
+                let offsets = neighborhood.offsets();
+                Stencil {
+                    even: offsets.clone(),
+                    odd: offsets,
+                }
+            }
+            Topology::Hexagonal => {
+                let offsets = unweighted(&[(-1, -1), (0, -1), (-1, 0), (1, 0), (0, 1), (1, 1)]);
+                Stencil {
+                    even: offsets.clone(),
+                    odd: offsets,
+                }
+            }
+            Topology::Triangular if edges_only => Stencil {
+                even: unweighted(&[(-1, 0), (1, 0), (0, 1)]),
+                odd: unweighted(&[(0, -1), (-1, 0), (1, 0)]),
+            },
+            Topology::Triangular => {
+                // A triangle touches three cells on the side of its apex and five on the
+                // side of its base.
+                let rows = |apex: i32| -> Vec<Offset> {
+                    let offsets: Vec<(i32, i32)> = [-1, 0, 1]
+                        .into_iter()
+                        .flat_map(|dy| {
+                            let reach = if dy == apex { 1 } else { 2 };
+                            (-reach..=reach).map(move |dx| (dx, dy))
+                        })
+                        .filter(|offset| *offset != (0, 0))
+                        .collect();
+                    unweighted(&offsets)
+                };
+                Stencil {
+                    even: rows(-1),
+                    odd: rows(1),
+                }
+            }
+        }
+    }
+
+    /// The suffix marking the tiling in a rulestring, as in Golly's `B2/S34H`, with `L`
+    /// for triangles as LifeViewer does.
+    pub fn rule_suffix(self) -> &'static str {
+        match self {
+            Topology::Square => "",
+            Topology::Hexagonal => "H",
+            Topology::Triangular => "L",
+        }
+    }
+
+    /// Splits a rulestring into the rule and the tiling its suffix names.
+    pub fn split_rule(rule: &str) -> (&str, Topology) {
+        let rule = rule.trim();
+        for topology in [Topology::Hexagonal, Topology::Triangular] {
+            let suffix = topology.rule_suffix();
+            if let Some(rule) = rule
+                .strip_suffix(suffix)
+                .or_else(|| rule.strip_suffix(&suffix.to_ascii_lowercase()))
+            {
+                return (rule, topology);
+            }
+        }
+        (rule, Topology::Square)
+    }
+
+    /// Centre of the cell at `(x, y)` in units of the cell side, with `y` growing
+    /// downwards like the rows do.
+    pub fn center(self, x: i32, y: i32) -> (f32, f32) {
+        let (x, y) = (x as f32, y as f32);
+        match self {
+            Topology::Square => (x, y),
+            Topology::Hexagonal => (x - 0.5 * y, ROW_HEIGHT * y),
+            Topology::Triangular => {
+                // The centroid sits a third of the way up from the base.
+                let shift = if (x + y) % 2.0 == 0.0 { 1.0 } else { -1.0 } * ROW_HEIGHT / 6.0;
+                (0.5 * x, ROW_HEIGHT * y + shift)
+            }
+        }
+    }
+}
+
+/// The neighbour offsets of every cell of a tiling. Triangles pointing up and down see
+/// different neighbours, so the offsets are kept per parity of `x + y`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Stencil {
+    even: Vec<Offset>,
+    odd: Vec<Offset>,
+}
+
+impl Stencil {
+    pub fn at(&self, x: i32, y: i32) -> &[Offset] {
+        if (x + y).rem_euclid(2) == 0 {
+            &self.even
+        } else {
+            &self.odd
+        }
+    }
+
+    /// The offsets of cells with even and odd `x + y`, in that order.
+    pub fn by_parity(&self) -> [&[Offset]; 2] {
+        [&self.even, &self.odd]
+    }
+
+    /// How far the farthest neighbour is along either axis.
+    pub fn range(&self) -> i32 {
+        self.even
+            .iter()
+            .chain(&self.odd)
+            .map(|((dx, dy), _)| dx.abs().max(dy.abs()))
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+impl From<&Neighborhood> for Stencil {
+    fn from(neighborhood: &Neighborhood) -> Self {
+        Topology::Square.stencil(neighborhood)
+    }
+}
+
+impl fmt::Display for Topology {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Topology::Square => write!(f, "square"),
+            Topology::Hexagonal => write!(f, "hexagonal"),
+            Topology::Triangular => write!(f, "triangular"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseTopologyError(pub String);
+
+impl fmt::Display for ParseTopologyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "unknown topology {:?}, expected square, hexagonal or triangular",
+            self.0
+        )
+    }
+}
+
+impl std::error::Error for ParseTopologyError {}
+
+impl FromStr for Topology {
+    type Err = ParseTopologyError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "square" => Ok(Topology::Square),
+            "hexagonal" | "hex" => Ok(Topology::Hexagonal),
+            "triangular" | "triangle" | "tri" => Ok(Topology::Triangular),
+            _ => Err(ParseTopologyError(s.to_string())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Neighbours must be mutual: if `a` counts `b`, `b` counts `a`.
+    fn assert_symmetric(stencil: &Stencil) {
+        for y in 0..4 {
+            for x in 0..4 {
+                for ((dx, dy), _) in stencil.at(x, y) {
+                    let back = stencil.at(x + dx, y + dy);
+                    assert!(
+                        back.iter().any(|(offset, _)| *offset == (-dx, -dy)),
+                        "({}, {}) + ({}, {})",
+                        x,
+                        y,
+                        dx,
+                        dy
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_stencils() {
+        let moore = Neighborhood::default();
+        let von_neumann = Neighborhood::VonNeumann(1);
+
+        let hexagonal = Topology::Hexagonal.stencil(&moore);
+        assert_eq!(hexagonal.at(0, 0).len(), 6);
+        assert_eq!(hexagonal, Topology::Hexagonal.stencil(&von_neumann));
+        assert_symmetric(&hexagonal);
+
+        let triangular = Topology::Triangular.stencil(&moore);
+        assert_eq!(triangular.at(0, 0).len(), 12);
+        assert_eq!(triangular.at(1, 0).len(), 12);
+        assert_symmetric(&triangular);
+
+        let edges = Topology::Triangular.stencil(&von_neumann);
+        assert_eq!(edges.at(2, 2).len(), 3);
+        assert!(edges.at(2, 2).contains(&((0, 1), 1)));
+        assert!(edges.at(2, 1).contains(&((0, -1), 1)));
+        assert_symmetric(&edges);
+
+        assert_eq!(Stencil::from(&moore).at(5, 2).len(), 8);
+        assert!(!Topology::Hexagonal.supports(&Neighborhood::Moore(2)));
+    }
+
+    #[test]
+    fn test_centers_are_one_side_apart() {
+        for topology in [Topology::Hexagonal, Topology::Triangular] {
+            let stencil = topology.stencil(&Neighborhood::VonNeumann(1));
+            for (x, y) in [(2, 2), (3, 2)] {
+                let (cx, cy) = topology.center(x, y);
+                for ((dx, dy), _) in stencil.at(x, y) {
+                    let (nx, ny) = topology.center(x + dx, y + dy);
+                    let distance = ((nx - cx).powi(2) + (ny - cy).powi(2)).sqrt();
+                    // Hexagon centres are one side apart, triangles sharing an edge a
+                    // third of a row height times two.
+                    let expected = match topology {
+                        Topology::Hexagonal => 1.0,
+                        _ => ROW_HEIGHT / 1.5,
+                    };
+                    assert!(
+                        (distance - expected).abs() < 1e-4,
+                        "{} {:?}",
+                        topology,
+                        (dx, dy)
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_parse() {
+        for topology in Topology::ALL {
+            assert_eq!(topology.to_string().parse::<Topology>(), Ok(topology));
+        }
+        assert!("pentagonal".parse::<Topology>().is_err());
+        assert_eq!(
+            Topology::split_rule("B2/S34H"),
+            ("B2/S34", Topology::Hexagonal)
+        );
+        assert_eq!(
+            Topology::split_rule("B4/S345L"),
+            ("B4/S345", Topology::Triangular)
+        );
+        assert_eq!(Topology::split_rule("B3/S23"), ("B3/S23", Topology::Square));
+    }
+}
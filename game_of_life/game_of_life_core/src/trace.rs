@@ -0,0 +1,295 @@
+//! Records which thread ran what and when, and writes it as Chrome trace-event JSON for
+//! `chrome://tracing` or Perfetto.
+
+use std::{
+    fmt::Write as _,
+    io,
+    sync::{
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        Mutex, OnceLock,
+    },
+    time::{Duration, Instant},
+};
+
+use crate::timing::SystemTimings;
+
+static NEXT_THREAD: AtomicU64 = AtomicU64::new(0);
+
+thread_local! {
+    /// A small id per thread, `ThreadId` has no stable numeric form.
+    static THREAD: u64 = NEXT_THREAD.fetch_add(1, Ordering::Relaxed);
+}
+
+/// The id and name of the calling thread.
+fn current_thread() -> (u64, String) {
+    let thread = std::thread::current();
+    let id = THREAD.with(|id| *id);
+    let name = thread
+        .name()
+        .map_or_else(|| format!("thread {}", id), str::to_string);
+    (id, name)
+}
+
+/// One stretch of work on one thread.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Span {
+    pub name: &'static str,
+    /// `system` for a whole system run, `batch` for one batch of a parallel query.
+    pub category: &'static str,
+    pub thread: u64,
+    /// Since the recorder was created.
+    pub start: Duration,
+    pub duration: Duration,
+    /// How many query items a batch went through.
+    pub items: Option<usize>,
+}
+
+/// Collects spans from any thread.
+#[derive(Debug)]
+pub struct TraceRecorder {
+    origin: Instant,
+    spans: Mutex<Vec<Span>>,
+    threads: Mutex<Vec<(u64, String)>>,
+}
+
+impl Default for TraceRecorder {
+    fn default() -> Self {
+        TraceRecorder::new()
+    }
+}
+
+impl TraceRecorder {
+    pub fn new() -> Self {
+        TraceRecorder {
+            origin: Instant::now(),
+            spans: Mutex::new(Vec::new()),
+            threads: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Records work the calling thread did between `start` and `end`.
+    pub fn record(
+        &self,
+        name: &'static str,
+        category: &'static str,
+        start: Instant,
+        end: Instant,
+        items: Option<usize>,
+    ) {
+        let (thread, thread_name) = current_thread();
+        {
+            let mut threads = self.threads.lock().unwrap();
+            if !threads.iter().any(|(id, _)| *id == thread) {
+                threads.push((thread, thread_name));
+            }
+        }
+        self.spans.lock().unwrap().push(Span {
+            name,
+            category,
+            thread,
+            start: start.saturating_duration_since(self.origin),
+            duration: end.saturating_duration_since(start),
+            items,
+        });
+    }
+
+    /// The spans recorded so far, in the order they ended.
+    pub fn spans(&self) -> Vec<Span> {
+        self.spans.lock().unwrap().clone()
+    }
+
+    /// A `traceEvents` object with a name for every thread seen and a complete event per
+    /// span, timestamps in microseconds.
+    pub fn to_json(&self) -> String {
+        let threads = self.threads.lock().unwrap();
+        let spans = self.spans.lock().unwrap();
+        let mut events: Vec<String> = threads
+            .iter()
+            .map(|(id, name)| {
+                format!(
+                    "{{\"name\": \"thread_name\", \"ph\": \"M\", \"pid\": 1, \"tid\": {}, \"args\": {{\"name\": \"{}\"}}}}",
+                    id,
+                    name.replace('\\', "\\\\").replace('"', "\\\"")
+                )
+            })
+            .collect();
+        for span in spans.iter() {
+            let mut event = format!(
+                "{{\"name\": \"{}\", \"cat\": \"{}\", \"ph\": \"X\", \"ts\": {:.3}, \"dur\": {:.3}, \"pid\": 1, \"tid\": {}",
+                span.name,
+                span.category,
+                span.start.as_nanos() as f64 / 1e3,
+                span.duration.as_nanos() as f64 / 1e3,
+                span.thread
+            );
+            if let Some(items) = span.items {
+                let _ = write!(event, ", \"args\": {{\"items\": {}}}", items);
+            }
+            event.push('}');
+            events.push(event);
+        }
+        format!("{{\"traceEvents\": [\n  {}\n]}}\n", events.join(",\n  "))
+    }
+
+    pub fn save(&self, filename: &str) -> io::Result<()> {
+        std::fs::write(filename, self.to_json())
+    }
+}
+
+/// Records a run of `system` from `start` until now, to whichever of the per-system timings
+/// and the trace are kept.
+pub fn record_system(
+    timings: Option<&mut SystemTimings>,
+    recorder: Option<&TraceRecorder>,
+    system: &'static str,
+    start: Instant,
+) {
+    let end = Instant::now();
+    if let Some(timings) = timings {
+        timings.record(system, end - start);
+    }
+    if let Some(recorder) = recorder {
+        recorder.record(system, "system", start, end, None);
+    }
+}
+
+/// Times one batch of a parallel query.
+///
+/// Bevy clones the `for_each` closure once per batch and drops the clone on the thread
+/// that ran it, so a `BatchSpan` moved into the closure is timed from its first item to
+/// the end of its batch. Clones start out empty, and a span that saw no items records
+/// nothing. Without a recorder it only costs a branch per item.
+#[derive(Debug)]
+pub struct BatchSpan<'a> {
+    recorder: Option<&'a TraceRecorder>,
+    name: &'static str,
+    start: OnceLock<Instant>,
+    items: AtomicUsize,
+}
+
+impl<'a> BatchSpan<'a> {
+    pub fn new(recorder: Option<&'a TraceRecorder>, name: &'static str) -> Self {
+        BatchSpan {
+            recorder,
+            name,
+            start: OnceLock::new(),
+            items: AtomicUsize::new(0),
+        }
+    }
+
+    /// Counts an item, starting the clock on the first one.
+    pub fn item(&self) {
+        if self.recorder.is_some() {
+            self.start.get_or_init(Instant::now);
+            self.items.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Counts every item before handing it to `func`, for `for_each` closures that borrow
+    /// what they use and so cannot take the span by `move`.
+    pub fn wrap<T>(self, func: impl Fn(T) + Clone + 'a) -> impl Fn(T) + Clone + 'a {
+        move |item| {
+            self.item();
+            func(item)
+        }
+    }
+}
+
+impl Clone for BatchSpan<'_> {
+    fn clone(&self) -> Self {
+        BatchSpan::new(self.recorder, self.name)
+    }
+}
+
+impl Drop for BatchSpan<'_> {
+    fn drop(&mut self) {
+        if let (Some(recorder), Some(start)) = (self.recorder, self.start.get()) {
+            let items = self.items.load(Ordering::Relaxed);
+            recorder.record(self.name, "batch", *start, Instant::now(), Some(items));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_batches_are_recorded_per_clone_and_thread() {
+        let recorder = TraceRecorder::new();
+        let start = Instant::now();
+        let batch = BatchSpan::new(Some(&recorder), "update_cells_system");
+        std::thread::scope(|scope| {
+            for size in [3, 5] {
+                let batch = batch.clone();
+                std::thread::Builder::new()
+                    .name(format!("worker {}", size))
+                    .spawn_scoped(scope, move || (0..size).for_each(|_| batch.item()))
+                    .unwrap();
+            }
+        });
+        drop(batch);
+        recorder.record("update_cells_system", "system", start, Instant::now(), None);
+
+        let spans = recorder.spans();
+        assert_eq!(spans.len(), 3);
+        let mut items: Vec<_> = spans.iter().filter_map(|span| span.items).collect();
+        items.sort();
+        assert_eq!(items, [3, 5]);
+        assert_ne!(spans[0].thread, spans[1].thread);
+        assert_eq!(spans[2].category, "system");
+        assert!(spans[2].duration >= spans[0].duration);
+
+        let json = recorder.to_json();
+        assert!(json.starts_with("{\"traceEvents\": [\n"));
+        assert!(json.contains("\"args\": {\"name\": \"worker 3\"}"));
+        assert!(json.contains("\"cat\": \"batch\", \"ph\": \"X\""));
+        assert!(json.contains("\"args\": {\"items\": 5}"));
+    }
+
+    #[test]
+    fn test_no_recorder_records_nothing() {
+        let batch = BatchSpan::new(None, "update_cells_system");
+        batch.item();
+        drop(batch);
+
+        let recorder = TraceRecorder::new();
+        drop(BatchSpan::new(Some(&recorder), "unused"));
+        assert!(recorder.spans().is_empty());
+        assert_eq!(recorder.to_json(), "{\"traceEvents\": [\n  \n]}\n");
+    }
+
+    #[test]
+    fn test_wrap_counts_items() {
+        let recorder = TraceRecorder::new();
+        let total = std::cell::Cell::new(0);
+        let func = BatchSpan::new(Some(&recorder), "reaction_system")
+            .wrap(|n: u32| total.set(total.get() + n));
+        (1..=4).for_each(&func);
+        drop(func);
+        assert_eq!(total.get(), 10);
+        assert_eq!(recorder.spans()[0].items, Some(4));
+    }
+
+    #[test]
+    fn test_record_system_reports_to_both() {
+        let mut timings = SystemTimings::default();
+        let recorder = TraceRecorder::new();
+        let start = Instant::now();
+        record_system(
+            Some(&mut timings),
+            Some(&recorder),
+            "reaction_system",
+            start,
+        );
+        record_system(None, None, "reaction_system", start);
+
+        let spans = recorder.spans();
+        assert_eq!(spans.len(), 1);
+        assert_eq!(spans[0].category, "system");
+        assert_eq!(
+            timings.durations("reaction_system"),
+            Some(&[spans[0].duration][..])
+        );
+    }
+}
@@ -0,0 +1,429 @@
+//! Gosper's HashLife: the plane is a quadtree of hash-consed nodes, and the result of
+//! advancing every node is memoized, so repetitive patterns can be run for astronomically
+//! many generations.
+
+use std::collections::HashMap;
+
+use game_of_life_core::Rule;
+
+type NodeId = u32;
+
+const DEAD: NodeId = 0;
+const ALIVE: NodeId = 1;
+
+/// A `2^level` square split into four quadrants, or a single cell at level 0.
+#[derive(Debug, Clone, Copy)]
+struct Node {
+    /// `[nw, ne, sw, se]`, unused for cells.
+    children: [NodeId; 4],
+    level: u8,
+    population: u128,
+}
+
+/// An unbounded plane whose root node is always centred on the origin: a root of level
+/// `k` covers `-2^(k-1)..2^(k-1)` on both axes.
+pub struct Universe {
+    rule: Rule,
+    nodes: Vec<Node>,
+    lookup: HashMap<[NodeId; 4], NodeId>,
+    /// The centre of a node advanced by `2^j` generations, keyed by `(node, j)`.
+    results: HashMap<(NodeId, u8), NodeId>,
+    /// The empty node of every level built so far.
+    empty: Vec<NodeId>,
+    root: NodeId,
+    generation: u64,
+}
+
+impl Universe {
+    /// # Panics
+    ///
+    /// If the rule has `B0`, since an empty plane would then fill up at once, or more than
+    /// two states, since a node only stores whether its cells are alive.
+    pub fn new(rule: Rule) -> Self {
+        assert!(
+            !rule.is_birth(0),
+            "HashLife cannot run {}: B0 rules do not keep empty space empty",
+            rule
+        );
+        assert!(
+            rule.states() == 2,
+            "HashLife cannot run {}: Generations rules need more than two states",
+            rule
+        );
+
+        let cell = |population| Node {
+            children: [DEAD; 4],
+            level: 0,
+            population,
+        };
+        let mut universe = Universe {
+            rule,
+            nodes: vec![cell(0), cell(1)],
+            lookup: HashMap::new(),
+            results: HashMap::new(),
+            empty: vec![DEAD],
+            root: DEAD,
+            generation: 0,
+        };
+        universe.root = universe.empty(3);
+        universe
+    }
+
+    pub fn with_cells(rule: Rule, cells: impl IntoIterator<Item = (i64, i64)>) -> Self {
+        let mut universe = Universe::new(rule);
+        for (x, y) in cells {
+            universe.set_alive(x, y);
+        }
+        universe
+    }
+
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    pub fn population(&self) -> u128 {
+        self.nodes[self.root as usize].population
+    }
+
+    /// Number of distinct nodes built so far, a rough measure of memory use.
+    pub fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    fn level(&self, node: NodeId) -> u8 {
+        self.nodes[node as usize].level
+    }
+
+    fn children(&self, node: NodeId) -> [NodeId; 4] {
+        self.nodes[node as usize].children
+    }
+
+    fn join(&mut self, children: [NodeId; 4]) -> NodeId {
+        if let Some(node) = self.lookup.get(&children) {
+            return *node;
+        }
+        let node = Node {
+            children,
+            level: self.level(children[0]) + 1,
+            population: children
+                .iter()
+                .map(|child| self.nodes[*child as usize].population)
+                .sum(),
+        };
+        let id = self.nodes.len() as NodeId;
+        self.nodes.push(node);
+        self.lookup.insert(children, id);
+        id
+    }
+
+    fn empty(&mut self, level: u8) -> NodeId {
+        while self.empty.len() <= level as usize {
+            let below = *self.empty.last().unwrap();
+            let node = self.join([below; 4]);
+            self.empty.push(node);
+        }
+        self.empty[level as usize]
+    }
+
+    /// The same contents one level up, padded with empty space on every side.
+    fn expand(&mut self, node: NodeId) -> NodeId {
+        let [nw, ne, sw, se] = self.children(node);
+        let e = self.empty(self.level(node) - 1);
+        let nw = self.join([e, e, e, nw]);
+        let ne = self.join([e, e, ne, e]);
+        let sw = self.join([e, sw, e, e]);
+        let se = self.join([se, e, e, e]);
+        self.join([nw, ne, sw, se])
+    }
+
+    /// The middle half of a node of level 2 or more.
+    fn centre(&mut self, node: NodeId) -> NodeId {
+        let [nw, ne, sw, se] = self.children(node);
+        self.join([
+            self.children(nw)[3],
+            self.children(ne)[2],
+            self.children(sw)[1],
+            self.children(se)[0],
+        ])
+    }
+
+    /// Whether every alive cell of the root lies in its middle half, which is what keeps a
+    /// step from running off the edge.
+    fn is_padded(&self, node: NodeId) -> bool {
+        let [nw, ne, sw, se] = self.children(node);
+        let inner = self.nodes[self.children(nw)[3] as usize].population
+            + self.nodes[self.children(ne)[2] as usize].population
+            + self.nodes[self.children(sw)[1] as usize].population
+            + self.nodes[self.children(se)[0] as usize].population;
+        inner == self.nodes[node as usize].population
+    }
+
+    /// One generation of the middle 2x2 of a 4x4 node, counted the plain way.
+    fn step_leaf(&mut self, node: NodeId) -> NodeId {
+        let mut cells = [[false; 4]; 4];
+        for (quadrant, child) in self.children(node).into_iter().enumerate() {
+            for (index, cell) in self.children(child).into_iter().enumerate() {
+                let x = (quadrant % 2) * 2 + index % 2;
+                let y = (quadrant / 2) * 2 + index / 2;
+                cells[y][x] = cell == ALIVE;
+            }
+        }
+
+        let mut next = [DEAD; 4];
+        for (index, cell) in next.iter_mut().enumerate() {
+            let (x, y) = (1 + index % 2, 1 + index / 2);
+            let neighbors = (y - 1..=y + 1)
+                .flat_map(|ny| (x - 1..=x + 1).map(move |nx| (nx, ny)))
+                .filter(|&(nx, ny)| (nx, ny) != (x, y) && cells[ny][nx])
+                .count();
+            if self.rule.next_state(cells[y][x], neighbors as u16) {
+                *cell = ALIVE;
+            }
+        }
+        self.join(next)
+    }
+
+    /// The middle half of a level `k` node advanced by `2^j` generations, `j <= k - 2`.
+    fn step(&mut self, node: NodeId, j: u8) -> NodeId {
+        let level = self.level(node);
+        debug_assert!(level >= 2 && j <= level - 2);
+        if self.nodes[node as usize].population == 0 {
+            return self.empty(level - 1);
+        }
+        if level == 2 {
+            return self.step_leaf(node);
+        }
+        if let Some(result) = self.results.get(&(node, j)) {
+            return *result;
+        }
+
+        // Nine overlapping level k-1 squares tiling the node in a 3x3 grid.
+        let [nw, ne, sw, se] = self.children(node);
+        let [_, nw_ne, nw_sw, nw_se] = self.children(nw);
+        let [ne_nw, _, ne_sw, ne_se] = self.children(ne);
+        let [sw_nw, sw_ne, _, sw_se] = self.children(sw);
+        let [se_nw, se_ne, se_sw, _] = self.children(se);
+        let squares = [
+            nw,
+            self.join([nw_ne, ne_nw, nw_se, ne_sw]),
+            ne,
+            self.join([nw_sw, nw_se, sw_nw, sw_ne]),
+            self.join([nw_se, ne_sw, sw_ne, se_nw]),
+            self.join([ne_sw, ne_se, se_nw, se_ne]),
+            sw,
+            self.join([sw_ne, se_nw, sw_se, se_sw]),
+            se,
+        ];
+
+        // At full speed both halves of the jump advance by 2^(k-3); otherwise the first
+        // half only crops and the second one does all the advancing.
+        let full_speed = j == level - 2;
+        let mut inner = [DEAD; 9];
+        for (inner, square) in inner.iter_mut().zip(squares) {
+            *inner = if full_speed {
+                self.step(square, level - 3)
+            } else {
+                self.centre(square)
+            };
+        }
+        let next_j = if full_speed { level - 3 } else { j };
+
+        let mut quadrants = [DEAD; 4];
+        for (index, quadrant) in quadrants.iter_mut().enumerate() {
+            let corner = (index / 2) * 3 + index % 2;
+            let square = self.join([
+                inner[corner],
+                inner[corner + 1],
+                inner[corner + 3],
+                inner[corner + 4],
+            ]);
+            *quadrant = self.step(square, next_j);
+        }
+        let result = self.join(quadrants);
+        self.results.insert((node, j), result);
+        result
+    }
+
+    /// Advances the whole plane by `generations`, one power-of-two jump per set bit.
+    pub fn advance(&mut self, generations: u64) {
+        let mut remaining = generations;
+        while remaining > 0 {
+            let j = remaining.trailing_zeros() as u8;
+            while self.level(self.root) < j + 2 || !self.is_padded(self.root) {
+                self.root = self.expand(self.root);
+            }
+            let padded = self.expand(self.root);
+            self.root = self.step(padded, j);
+            remaining -= 1 << j;
+            self.generation += 1 << j;
+        }
+    }
+
+    pub fn set_alive(&mut self, x: i64, y: i64) {
+        loop {
+            let half = 1i64 << (self.level(self.root) - 1);
+            if (-half..half).contains(&x) && (-half..half).contains(&y) {
+                self.root = self.set_alive_in(self.root, x + half, y + half);
+                return;
+            }
+            self.root = self.expand(self.root);
+        }
+    }
+
+    /// Sets a cell given relative to the top-left corner of `node`.
+    fn set_alive_in(&mut self, node: NodeId, x: i64, y: i64) -> NodeId {
+        let level = self.level(node);
+        if level == 0 {
+            return ALIVE;
+        }
+        let half = 1i64 << (level - 1);
+        let quadrant = (y >= half) as usize * 2 + (x >= half) as usize;
+        let mut children = self.children(node);
+        children[quadrant] = self.set_alive_in(children[quadrant], x % half, y % half);
+        self.join(children)
+    }
+
+    /// Coordinates of every alive cell, row by row.
+    ///
+    /// # Panics
+    ///
+    /// If the plane has grown past what an `i64` coordinate can address.
+    pub fn alive_cells(&self) -> Vec<(i64, i64)> {
+        let level = self.level(self.root);
+        assert!(level < 63, "the universe is too large to list its cells");
+        let half = 1i64 << (level - 1);
+        let mut cells = Vec::new();
+        self.collect_cells(self.root, -half, -half, &mut cells);
+        cells.sort_by_key(|(x, y)| (*y, *x));
+        cells
+    }
+
+    fn collect_cells(&self, node: NodeId, x: i64, y: i64, cells: &mut Vec<(i64, i64)>) {
+        let Node {
+            children,
+            level,
+            population,
+        } = self.nodes[node as usize];
+        if population == 0 {
+            return;
+        }
+        if level == 0 {
+            cells.push((x, y));
+            return;
+        }
+        let half = 1i64 << (level - 1);
+        for (quadrant, child) in children.into_iter().enumerate() {
+            let offset_x = (quadrant % 2) as i64 * half;
+            let offset_y = (quadrant / 2) as i64 * half;
+            self.collect_cells(child, x + offset_x, y + offset_y, cells);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{HashMap, HashSet};
+
+    use game_of_life_core::Soup;
+
+    use super::*;
+
+    const GLIDER: [(i64, i64); 5] = [(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)];
+    const R_PENTOMINO: [(i64, i64); 5] = [(1, 0), (2, 0), (0, 1), (1, 1), (1, 2)];
+
+    /// A plain set-based stepper on the unbounded plane.
+    fn step(cells: &HashSet<(i64, i64)>, rule: &Rule) -> HashSet<(i64, i64)> {
+        let mut counts: HashMap<(i64, i64), u16> = HashMap::new();
+        for (x, y) in cells {
+            for dy in -1..=1 {
+                for dx in -1..=1 {
+                    if (dx, dy) != (0, 0) {
+                        *counts.entry((x + dx, y + dy)).or_default() += 1;
+                    }
+                }
+            }
+        }
+        counts
+            .into_iter()
+            .filter(|(cell, neighbors)| rule.next_state(cells.contains(cell), *neighbors))
+            .map(|(cell, _)| cell)
+            .collect()
+    }
+
+    fn sorted(cells: impl IntoIterator<Item = (i64, i64)>) -> Vec<(i64, i64)> {
+        let mut cells: Vec<_> = cells.into_iter().collect();
+        cells.sort_by_key(|(x, y)| (*y, *x));
+        cells
+    }
+
+    #[test]
+    fn test_still_life_and_oscillator() {
+        let block = [(0, 0), (1, 0), (0, 1), (1, 1)];
+        let mut universe = Universe::with_cells(Rule::default(), block);
+        universe.advance(1000);
+        assert_eq!(universe.alive_cells(), sorted(block));
+
+        let blinker = [(-1, 0), (0, 0), (1, 0)];
+        let mut universe = Universe::with_cells(Rule::default(), blinker);
+        universe.advance(7);
+        assert_eq!(universe.alive_cells(), vec![(0, -1), (0, 0), (0, 1)]);
+        universe.advance(1);
+        assert_eq!(universe.alive_cells(), sorted(blinker));
+        assert_eq!(universe.generation(), 8);
+    }
+
+    #[test]
+    fn test_matches_plain_stepper() {
+        let rule = Rule::default();
+        let soup = Soup::new(1234567, 0.4);
+        let initial: HashSet<(i64, i64)> = soup
+            .alive_cells(16, 16)
+            .map(|(x, y)| (x as i64 - 8, y as i64 - 8))
+            .collect();
+
+        let mut universe = Universe::with_cells(rule, initial.iter().copied());
+        let mut cells = initial;
+        let mut elapsed = 0;
+        // Uneven jumps, so every power of two up to 32 gets used.
+        for generations in [1, 2, 3, 5, 8, 13, 21, 34, 55] {
+            for _ in 0..generations {
+                cells = step(&cells, &rule);
+            }
+            universe.advance(generations);
+            elapsed += generations;
+            assert_eq!(universe.alive_cells(), sorted(cells.clone()), "{}", elapsed);
+        }
+    }
+
+    #[test]
+    fn test_r_pentomino_stabilises() {
+        let mut universe = Universe::with_cells(Rule::default(), R_PENTOMINO);
+        universe.advance(1103);
+        assert_eq!(universe.population(), 116);
+    }
+
+    #[test]
+    fn test_glider_superspeed() {
+        let mut universe = Universe::with_cells(Rule::default(), GLIDER);
+        universe.advance(1 << 40);
+        assert_eq!(universe.generation(), 1 << 40);
+        assert_eq!(universe.population(), 5);
+
+        // A glider moves one cell diagonally every four generations.
+        let shift = 1i64 << 38;
+        let expected: Vec<_> = GLIDER.iter().map(|(x, y)| (x + shift, y + shift)).collect();
+        assert_eq!(universe.alive_cells(), sorted(expected));
+    }
+
+    #[test]
+    #[should_panic(expected = "Generations")]
+    fn test_rejects_generations_rules() {
+        Universe::new("B2/S/3".parse().unwrap());
+    }
+
+    #[test]
+    #[should_panic(expected = "B0")]
+    fn test_rejects_b0_rules() {
+        Universe::new("B012/S3".parse().unwrap());
+    }
+}
@@ -0,0 +1,112 @@
+use std::time::Instant;
+
+use game_of_life_core::{
+    config::CycleDetection, Boundary, Config, Neighborhood, Pattern, Topology,
+};
+
+mod hashlife;
+
+use hashlife::Universe;
+
+fn initial_universe(config: &Config) -> Universe {
+    match &config.pattern {
+        Some(path) => {
+            let pattern = Pattern::load(path).expect("Invalid pattern");
+            let (offset_x, offset_y) = config.pattern_offset;
+            let cells = pattern
+                .cells
+                .iter()
+                .map(|(x, y)| (*x as i64 + offset_x as i64, *y as i64 + offset_y as i64));
+            Universe::with_cells(config.rule, cells)
+        }
+        None => {
+            let soup = config.soup();
+            println!("Soup seed: {}", soup.seed);
+            let cells = soup
+                .alive_cells(config.width, config.height)
+                .map(|(x, y)| (x as i64, y as i64));
+            Universe::with_cells(config.rule, cells)
+        }
+    }
+}
+
+/// Saves the alive cells with the top-left corner of their bounding box at `(0, 0)`.
+fn save_cells_to_pattern_file(universe: &Universe, config: &Config, filename: &str) {
+    let cells = universe.alive_cells();
+    let min_x = cells.iter().map(|(x, _)| *x).min().unwrap_or(0);
+    let min_y = cells.iter().map(|(_, y)| *y).min().unwrap_or(0);
+    let max_x = cells.iter().map(|(x, _)| *x).max().unwrap_or(-1);
+    let max_y = cells.iter().map(|(_, y)| *y).max().unwrap_or(-1);
+    let width = u32::try_from(max_x - min_x + 1).expect("The pattern is too wide to save");
+    let height = u32::try_from(max_y - min_y + 1).expect("The pattern is too tall to save");
+
+    let pattern = Pattern::from_grid(
+        width,
+        height,
+        cells
+            .into_iter()
+            .map(|(x, y)| ((x - min_x) as i32, (y - min_y) as i32)),
+        Some(config.rule),
+    );
+    pattern.save(filename).expect("Unable to save pattern");
+}
+
+fn main() {
+    let config = Config::default().parse_env_or_exit();
+    // Quadtree leaves only see one cell past their edge, on a square grid.
+    if config.neighborhood != Neighborhood::default() || config.topology != Topology::Square {
+        eprintln!(
+            "HashLife only runs the moore:1 neighbourhood on square cells, not {} on {} cells",
+            config.neighborhood, config.topology
+        );
+        std::process::exit(1);
+    }
+    // Nodes only store whether their cells are alive, and an empty node has to stay empty.
+    if config.rule.states() > 2 || config.rule.is_birth(0) {
+        eprintln!(
+            "HashLife only runs two-state rules without B0, not {}",
+            config.rule
+        );
+        std::process::exit(1);
+    }
+    if config.boundary != Boundary::Dead {
+        eprintln!(
+            "HashLife runs on an unbounded plane, ignoring --boundary {}",
+            config.boundary
+        );
+    }
+    if config.cycles != CycleDetection::Off {
+        eprintln!(
+            "HashLife jumps over the generations it skips, ignoring --cycles {}",
+            config.cycles
+        );
+    }
+    if config.stats_output.is_some() {
+        eprintln!("HashLife jumps over the generations it skips, ignoring --stats-output");
+    }
+
+    let mut universe = initial_universe(&config);
+    println!("Generation 0: population {}", universe.population());
+
+    // One jump per set bit of the generation count, so the whole run is a single step.
+    let start_sim = Instant::now();
+    universe.advance(config.generations);
+    let total_time = start_sim.elapsed();
+    println!(
+        "Generation {}: population {} ({} nodes) in {:?}",
+        universe.generation(),
+        universe.population(),
+        universe.node_count(),
+        total_time
+    );
+
+    if let Some(filename) = &config.durations_output {
+        std::fs::write(filename, format!("{:?}\n", total_time)).expect("Unable to write data");
+    }
+    if let Some(filename) = &config.total_time_output {
+        std::fs::write(filename, format!("{:?}", total_time)).expect("Unable to write data");
+    }
+    if let Some(filename) = &config.output {
+        save_cells_to_pattern_file(&universe, &config, filename);
+    }
+}
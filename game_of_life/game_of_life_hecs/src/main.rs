@@ -1,9 +1,53 @@
+use game_of_life_core::Config;
+
 mod plugin;
 
 fn main() {
     // plugin::run_simulation();
     let args: Vec<String> = std::env::args().collect();
-    let iterations = args[1].parse::<usize>().unwrap();
-    let size = args[2].parse::<usize>().unwrap();
-    plugin::run_simulation_n_times(iterations, size);
+    let config = match args.get(1) {
+        Some(arg) if !arg.starts_with('-') => parse_positional_args(&args),
+        _ => Config::default().parse_env_or_exit(),
+    };
+    plugin::run_simulation(&config);
+}
+
+/// The original `<iterations> <size> [rule] [boundary] [pattern] [x] [y] [output]` arguments.
+fn parse_positional_args(args: &[String]) -> Config {
+    if args.len() < 3 {
+        println!(
+            "Usage: {} <iterations> <size> [rule] [boundary] [pattern.rle] [x] [y] [output.rle]",
+            args[0]
+        );
+        println!(
+            "   or: {} [options]\n\n{}",
+            args[0],
+            game_of_life_core::config::USAGE
+        );
+        std::process::exit(1);
+    }
+
+    let size = args[2].parse().expect("Invalid size");
+    let mut config = Config {
+        generations: args[1].parse().expect("Invalid iterations"),
+        width: size,
+        height: size,
+        pattern: args.get(5).cloned(),
+        output: args.get(8).cloned(),
+        ..Default::default()
+    };
+    if let Some(rule) = args.get(3) {
+        config.rule = rule.parse().expect("Invalid rule");
+    }
+    if let Some(boundary) = args.get(4) {
+        config.boundary = boundary.parse().expect("Invalid boundary");
+    }
+    if let Some(x) = args.get(6) {
+        config.pattern_offset.0 = x.parse().expect("Invalid x offset");
+    }
+    if let Some(y) = args.get(7) {
+        config.pattern_offset.1 = y.parse().expect("Invalid y offset");
+    }
+
+    config
 }
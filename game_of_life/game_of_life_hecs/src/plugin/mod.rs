@@ -1,5 +1,17 @@
+use std::io::Write;
+
+use std::collections::HashMap;
+
+use game_of_life_core::{
+    config::{CycleDetection, Extent},
+    cycle::{self, CycleDetector},
+    rule::{ALIVE, DEAD},
+    sparse::{self, Chunk, EMPTY_CHUNK},
+    stats::{self, GenerationStats},
+    topology::Stencil,
+    Boundary, Config, Pattern, Rule, Soup, Topology,
+};
 use hecs::*;
-use rand::{thread_rng, Rng};
 
 #[derive(Debug, PartialEq, Eq, Clone)]
 struct Position {
@@ -7,34 +19,58 @@ struct Position {
     y: i32,
 }
 
-// is alive or dead
+// dead, alive, or one of the dying states of a Generations rule
 #[derive(Clone, PartialEq, Eq)]
-struct State(bool);
+struct State(u8);
+
+impl State {
+    fn is_alive(&self) -> bool {
+        self.0 == ALIVE
+    }
+}
 
 impl std::fmt::Debug for State {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        if self.0 {
-            write!(f, "Alive")
-        } else {
-            write!(f, "Dead")
+        match self.0 {
+            DEAD => write!(f, "Dead"),
+            ALIVE => write!(f, "Alive"),
+            dying => write!(f, "Dying({})", dying),
         }
     }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
-struct Neighbors(u8);
+struct Neighbors(u16);
+
+/// A `sparse::CHUNK_SIZE` square of the unbounded plane, in chunk coordinates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct ChunkPosition {
+    x: i32,
+    y: i32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ChunkCells(Chunk);
+
+#[derive(Debug, Clone, Copy)]
+struct Grid {
+    width: u32,
+    height: u32,
+    boundary: Boundary,
+    topology: Topology,
+}
 
-fn batch_spawn_cells(world: &mut World, n: usize) {
-    let mut rng = thread_rng();
-    let cells_to_spawn_count = n * n; // square grid
+fn batch_spawn_cells(world: &mut World, grid: &Grid, soup: &Soup) {
+    let width = grid.width as usize;
+    let cells_to_spawn_count = width * grid.height as usize;
     let to_spawn = (0..cells_to_spawn_count).map(|i| {
-        let x = i % n;
-        let y = i / n;
+        let x = i % width;
+        let y = i / width;
         let position = Position {
             x: x as i32,
             y: y as i32,
         };
-        let state = State(rng.gen_bool(0.5)); // 50% chance of being alive
+        let state = State(soup.is_alive(x as u32, y as u32) as u8);
         let neighbors = Neighbors(0);
 
         (position, state, neighbors)
@@ -43,37 +79,65 @@ fn batch_spawn_cells(world: &mut World, n: usize) {
     world.spawn_batch(to_spawn);
 }
 
-fn update_neighbors_system(world: &mut World) {
-    let neighbors_count: Vec<(Entity, usize)> = world
+fn spawn_pattern(world: &mut World, grid: &Grid, pattern: &Pattern, offset_x: i32, offset_y: i32) {
+    let alive_cells = pattern.cells_at_offset(offset_x, offset_y, grid.width, grid.height);
+    let width = grid.width as usize;
+    let cells_to_spawn_count = width * grid.height as usize;
+    let to_spawn = (0..cells_to_spawn_count).map(|i| {
+        let position = Position {
+            x: (i % width) as i32,
+            y: (i / width) as i32,
+        };
+        let state = State(alive_cells.contains(&(position.x, position.y)) as u8);
+
+        (position, state, Neighbors(0))
+    });
+
+    world.spawn_batch(to_spawn);
+}
+
+fn save_durations_to_file(durations: &[std::time::Duration], filename: &str) {
+    let mut file = std::fs::File::create(filename).expect("Unable to create file");
+    for duration in durations {
+        writeln!(file, "{:?}", duration).expect("Unable to write data");
+    }
+}
+
+fn save_cells_to_pattern_file(world: &World, grid: &Grid, rule: &Rule, filename: &str) {
+    let alive_cells: Vec<(i32, i32)> = world
+        .query::<(&Position, &State)>()
+        .iter()
+        .filter(|(_, (_, state))| state.is_alive())
+        .map(|(_, (position, _))| (position.x, position.y))
+        .collect();
+    let pattern = Pattern::from_grid(grid.width, grid.height, alive_cells, Some(*rule))
+        .with_topology(grid.topology);
+    pattern.save(filename).expect("Unable to write data");
+}
+
+fn update_neighbors_system(world: &mut World, grid: &Grid, stencil: &Stencil) {
+    let neighbors_count: Vec<(Entity, u16)> = world
         .query::<&Position>()
         .iter()
         .map(|(entity, position)| {
             let mut count = 0;
-            for x in -1..=1 {
-                for y in -1..=1 {
-                    if x == 0 && y == 0 {
-                        continue; // skip the cell itself
-                    }
-
-                    let neighbor_position = Position {
-                        x: position.x + x,
-                        y: position.y + y,
-                    };
-
-                    let mut entities_checked: Vec<Entity> = vec![];
-                    // check if neighbor is alive, if so, increment count
-                    if let Some((e, (_, _))) = world
-                        .query::<(&State, &Position)>()
-                        .iter()
-                        .find(|(_, (state, pos))| *pos == &neighbor_position && state.0)
-                    {
-                        if entities_checked.contains(&e) {
-                            continue;
-                        }
-
-                        count += 1;
-                        entities_checked.push(e);
-                    }
+            // the cell itself is only among the offsets of a weighted neighbourhood
+            for ((x, y), weight) in stencil.at(position.x, position.y) {
+                let Some((x, y)) =
+                    grid.boundary
+                        .resolve(position.x + x, position.y + y, grid.width, grid.height)
+                else {
+                    continue; // the neighbor is outside of a dead boundary
+                };
+                let neighbor_position = Position { x, y };
+
+                // check if neighbor is alive, if so, add its weight to the count
+                if world
+                    .query::<(&State, &Position)>()
+                    .iter()
+                    .any(|(_, (state, pos))| *pos == neighbor_position && state.is_alive())
+                {
+                    count += weight;
                 }
             }
             (entity, count)
@@ -82,23 +146,19 @@ fn update_neighbors_system(world: &mut World) {
 
     for (entity, count) in neighbors_count {
         if let Ok(mut neighbors) = world.get::<&mut Neighbors>(entity) {
-            neighbors.0 = count as u8;
+            neighbors.0 = count;
         }
     }
 }
 
-fn update_cells_system(world: &mut World) {
-    let entites_to_update: Vec<(Entity, bool)> = world
+fn update_cells_system(world: &mut World, rule: &Rule) {
+    let entites_to_update: Vec<(Entity, u8)> = world
         .query::<(&Neighbors, &State)>()
         .iter()
         .map(|(entity, (neighbors, state))| {
-            // apply the rules of the game of life
-            // if cell is alive and has 2 or 3 neighbors, it stays alive
-            // if cell is dead and has 3 neighbors, it becomes alive
-            let new_state = match (state.0, neighbors.0) {
-                (true, 2) | (_, 3) => true,
-                _ => false,
-            };
+            // apply the birth/survival rule, B3/S23 for the classic game of life, with
+            // dying cells counting down for Generations rules
+            let new_state = rule.next(state.0, neighbors.0);
 
             (entity, new_state)
         })
@@ -111,6 +171,94 @@ fn update_cells_system(world: &mut World) {
     }
 }
 
+fn spawn_chunks(world: &mut World, cells: impl IntoIterator<Item = (i32, i32)>) {
+    let mut chunks: HashMap<(i32, i32), Chunk> = HashMap::new();
+    for (x, y) in cells {
+        let (key, (x, y)) = sparse::split(x, y);
+        chunks.entry(key).or_insert(EMPTY_CHUNK)[y] |= 1 << x;
+    }
+    world.spawn_batch(
+        chunks
+            .into_iter()
+            .map(|((x, y), cells)| (ChunkPosition { x, y }, ChunkCells(cells))),
+    );
+}
+
+/// Steps every chunk entity, spawning empty chunks where activity spills over an edge
+/// and despawning the chunks that died out.
+fn update_chunks_system(world: &mut World, rule: &Rule) {
+    let front: HashMap<(i32, i32), Chunk> = world
+        .query::<(&ChunkPosition, &ChunkCells)>()
+        .iter()
+        .map(|(_, (position, cells))| ((position.x, position.y), cells.0))
+        .collect();
+
+    let to_spawn: Vec<(ChunkPosition, ChunkCells)> = sparse::active_keys(front.iter())
+        .into_iter()
+        .filter(|key| !front.contains_key(key))
+        .map(|(x, y)| (ChunkPosition { x, y }, ChunkCells(EMPTY_CHUNK)))
+        .collect();
+    world.spawn_batch(to_spawn);
+
+    let chunk_at = |key| front.get(&key).copied().unwrap_or(EMPTY_CHUNK);
+    let mut to_despawn = Vec::new();
+    for (entity, (position, cells)) in world.query_mut::<(&ChunkPosition, &mut ChunkCells)>() {
+        cells.0 = sparse::step_chunk(rule, (position.x, position.y), chunk_at);
+        if sparse::is_empty(&cells.0) {
+            to_despawn.push(entity);
+        }
+    }
+    for entity in to_despawn {
+        world.despawn(entity).expect("Chunk was just queried");
+    }
+}
+
+fn alive_chunk_cells(world: &World) -> Vec<(i32, i32)> {
+    world
+        .query::<(&ChunkPosition, &ChunkCells)>()
+        .iter()
+        .flat_map(|(_, (position, cells))| {
+            sparse::alive_cells((position.x, position.y), &cells.0).collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+/// The state of every cell entity, in the order `cell_stats` visits them.
+fn cell_states(world: &World) -> Vec<u8> {
+    world
+        .query::<(&Position, &State)>()
+        .iter()
+        .map(|(_, (_, state))| state.0)
+        .collect()
+}
+
+/// Counts the cell entities, comparing them with `before` from `cell_states` when given.
+fn cell_stats(world: &World, generation: u64, before: Option<&[u8]>) -> GenerationStats {
+    let mut stats = GenerationStats::new(generation);
+    let mut query = world.query::<(&Position, &State)>();
+    for (index, (_, (position, state))) in query.iter().enumerate() {
+        match before {
+            Some(before) => stats.record(position.x, position.y, before[index], state.0),
+            None => stats.count(position.x, position.y, state.0),
+        }
+    }
+    stats
+}
+
+fn chunk_map(world: &World) -> HashMap<(i32, i32), Chunk> {
+    world
+        .query::<(&ChunkPosition, &ChunkCells)>()
+        .iter()
+        .map(|(_, (position, cells))| ((position.x, position.y), cells.0))
+        .collect()
+}
+
+fn save_stats(generation_stats: &Option<Vec<GenerationStats>>, config: &Config) {
+    if let (Some(generation_stats), Some(filename)) = (generation_stats, &config.stats_output) {
+        stats::save(generation_stats, filename).expect("Unable to write data");
+    }
+}
+
 // pub fn run_simulation() {
 //     let mut world = World::new();
 //     batch_spawn_cells(&mut world, 10);
@@ -126,37 +274,240 @@ fn update_cells_system(world: &mut World) {
 //     }
 // }
 
-pub fn run_simulation_n_times(n: usize, size: usize) {
+/// The state hash and population of the cell entities.
+fn fingerprint(world: &World) -> (u64, usize) {
+    let mut query = world.query::<(&Position, &State)>();
+    let cells: Vec<(i32, i32, u8)> = query
+        .iter()
+        .filter(|(_, (_, state))| state.0 != DEAD)
+        .map(|(_, (position, state))| (position.x, position.y, state.0))
+        .collect();
+    (cycle::hash_cells(cells.iter().copied()), cells.len())
+}
+
+fn chunk_fingerprint(world: &World) -> (u64, usize) {
+    let cells = alive_chunk_cells(world);
+    let hash = cycle::hash_cells(cells.iter().map(|(x, y)| (*x, *y, ALIVE)));
+    (hash, cells.len())
+}
+
+/// Feeds `generation` to the detector, if there is one, and tells whether the run should
+/// stop there.
+fn detect_cycle(
+    detector: &mut Option<CycleDetector>,
+    config: &Config,
+    generation: u64,
+    fingerprint: impl FnOnce() -> (u64, usize),
+) -> bool {
+    let Some(detector) = detector else {
+        return false;
+    };
+    let (hash, population) = fingerprint();
+    match detector.observe(generation, hash, population) {
+        Some(stabilization) => {
+            println!(
+                "Stabilized at generation {}: {} with period {}",
+                stabilization.start(),
+                stabilization.kind(),
+                stabilization.period()
+            );
+            config.cycles == CycleDetection::Stop
+        }
+        None => false,
+    }
+}
+
+fn save_stabilization(detector: &Option<CycleDetector>, config: &Config) {
+    if let (Some(detector), Some(filename)) = (detector, &config.stabilization_output) {
+        cycle::save(detector.result(), filename).expect("Unable to write data");
+    }
+}
+
+/// The unbounded counterpart of `run_simulation`, with one entity per non-empty chunk
+/// instead of one per cell.
+fn run_unbounded_simulation(config: &Config) {
     let mut world = World::new();
-    batch_spawn_cells(&mut world, size);
+    match &config.pattern {
+        Some(path) => {
+            let pattern = Pattern::load(path).expect("Invalid pattern");
+            let (offset_x, offset_y) = config.pattern_offset;
+            let cells = pattern
+                .cells
+                .iter()
+                .map(|(x, y)| (*x as i32 + offset_x, *y as i32 + offset_y));
+            spawn_chunks(&mut world, cells)
+        }
+        None => {
+            let soup = config.soup();
+            println!("Soup seed: {}", soup.seed);
+            let cells = soup
+                .alive_cells(config.width, config.height)
+                .map(|(x, y)| (x as i32, y as i32));
+            spawn_chunks(&mut world, cells)
+        }
+    }
+
+    let mut durations = Vec::new();
+    let mut detector = config.cycle_detector();
+    let mut stop = detect_cycle(&mut detector, config, 0, || chunk_fingerprint(&world));
+    let mut generation_stats = config.stats_output.as_ref().map(|_| {
+        let mut initial = GenerationStats::new(0);
+        for (key, chunk) in &chunk_map(&world) {
+            initial.count_chunk(*key, chunk);
+        }
+        vec![initial]
+    });
     let start_sim = std::time::Instant::now();
-    for _ in 0..n {
-        // let start_loop = std::time::Instant::now();
-        update_neighbors_system(&mut world);
-        update_cells_system(&mut world);
-        update_neighbors_system(&mut world);
+    for generation in 1..=config.generations {
+        if stop {
+            break;
+        }
+        let before = generation_stats.as_ref().map(|_| chunk_map(&world));
+        let start_loop = std::time::Instant::now();
+        update_chunks_system(&mut world, &config.rule);
+        let duration = start_loop.elapsed();
+        durations.push(duration);
+        if let (Some(generation_stats), Some(before)) = (&mut generation_stats, before) {
+            let mut record = stats::chunk_stats(generation, &before, &chunk_map(&world));
+            record.duration = Some(duration);
+            generation_stats.push(record);
+        }
+        stop = detect_cycle(&mut detector, config, generation, || {
+            chunk_fingerprint(&world)
+        });
+    }
+    let total_time = start_sim.elapsed();
+    println!(
+        "Simulation took ({} iterations, {} chunks) {:?}",
+        config.generations,
+        world.len(),
+        total_time
+    );
+
+    save_stabilization(&detector, config);
+    save_stats(&generation_stats, config);
+    if let Some(filename) = &config.durations_output {
+        save_durations_to_file(&durations, filename);
+    }
+    if let Some(filename) = &config.total_time_output {
+        std::fs::write(filename, format!("{:?}", total_time)).expect("Unable to write data");
+    }
+    if let Some(filename) = &config.output {
+        Pattern::from_cells(alive_chunk_cells(&world), Some(config.rule))
+            .save(filename)
+            .expect("Unable to write data");
+    }
+}
+
+pub fn run_simulation(config: &Config) {
+    if config.extent == Extent::Unbounded {
+        return run_unbounded_simulation(config);
+    }
+
+    let mut world = World::new();
+    let grid = Grid {
+        width: config.width,
+        height: config.height,
+        boundary: config.boundary,
+        topology: config.topology,
+    };
+    let stencil = config.topology.stencil(&config.neighborhood);
+    match &config.pattern {
+        Some(path) => {
+            let pattern = Pattern::load(path).expect("Invalid pattern");
+            let (offset_x, offset_y) = config.pattern_offset;
+            spawn_pattern(&mut world, &grid, &pattern, offset_x, offset_y)
+        }
+        None => {
+            let soup = config.soup();
+            println!("Soup seed: {}", soup.seed);
+            batch_spawn_cells(&mut world, &grid, &soup)
+        }
+    }
+    let mut durations = Vec::with_capacity(config.generations as usize);
+    let mut detector = config.cycle_detector();
+    let mut stop = detect_cycle(&mut detector, config, 0, || fingerprint(&world));
+    let mut generation_stats = config
+        .stats_output
+        .as_ref()
+        .map(|_| vec![cell_stats(&world, 0, None)]);
+    let start_sim = std::time::Instant::now();
+    for generation in 1..=config.generations {
+        if stop {
+            break;
+        }
+        let before = generation_stats.as_ref().map(|_| cell_states(&world));
+        let start_loop = std::time::Instant::now();
+        update_neighbors_system(&mut world, &grid, &stencil);
+        update_cells_system(&mut world, &config.rule);
+        update_neighbors_system(&mut world, &grid, &stencil);
+        let duration = start_loop.elapsed();
+        durations.push(duration);
+        if let (Some(generation_stats), Some(before)) = (&mut generation_stats, before) {
+            let mut record = cell_stats(&world, generation, Some(&before));
+            record.duration = Some(duration);
+            generation_stats.push(record);
+        }
         // println!("Loop took {:?}", start_loop.elapsed());
 
         // std::thread::sleep(std::time::Duration::from_secs(1));
+        stop = detect_cycle(&mut detector, config, generation, || fingerprint(&world));
     }
+    let total_time = start_sim.elapsed();
     println!(
         "Simulation took ({} iterations, {} cells) {:?}",
-        n,
-        size * size,
-        start_sim.elapsed()
+        config.generations,
+        config.width * config.height,
+        total_time
     );
+
+    save_stabilization(&detector, config);
+    save_stats(&generation_stats, config);
+    if let Some(filename) = &config.durations_output {
+        save_durations_to_file(&durations, filename);
+    }
+    if let Some(filename) = &config.total_time_output {
+        std::fs::write(filename, format!("{:?}", total_time)).expect("Unable to write data");
+    }
+    if let Some(filename) = &config.output {
+        save_cells_to_pattern_file(&world, &grid, &config.rule, filename);
+    }
 }
 
 #[cfg(test)]
 mod tests {
     pub use super::*;
+    use game_of_life_core::Neighborhood;
+
+    #[test]
+    fn test_unbounded_chunks_follow_glider() {
+        let mut world = World::new();
+        let glider = [(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)];
+        spawn_chunks(&mut world, glider);
+        let rule = Rule::default();
+
+        // 80 generations take the glider 20 cells down and right, into the next chunk.
+        for _ in 0..80 {
+            update_chunks_system(&mut world, &rule);
+        }
+        let mut cells = alive_chunk_cells(&world);
+        cells.sort_by_key(|(x, y)| (*y, *x));
+        assert_eq!(cells, glider.map(|(x, y)| (x + 20, y + 20)));
+
+        let chunks: Vec<ChunkPosition> = world
+            .query::<&ChunkPosition>()
+            .iter()
+            .map(|(_, position)| *position)
+            .collect();
+        assert_eq!(chunks, vec![ChunkPosition { x: 1, y: 1 }]);
+    }
 
     fn spawn_block_pattern(world: &mut World) {
         let to_spawn = vec![
-            (Position { x: 0, y: 0 }, State(true), Neighbors(0)),
-            (Position { x: 0, y: 1 }, State(true), Neighbors(0)),
-            (Position { x: 1, y: 0 }, State(true), Neighbors(0)),
-            (Position { x: 1, y: 1 }, State(true), Neighbors(0)),
+            (Position { x: 0, y: 0 }, State(ALIVE), Neighbors(0)),
+            (Position { x: 0, y: 1 }, State(ALIVE), Neighbors(0)),
+            (Position { x: 1, y: 0 }, State(ALIVE), Neighbors(0)),
+            (Position { x: 1, y: 1 }, State(ALIVE), Neighbors(0)),
         ];
 
         world.spawn_batch(to_spawn);
@@ -164,15 +515,15 @@ mod tests {
 
     fn spawn_blinker_pattern(world: &mut World) {
         let to_spawn = vec![
-            (Position { x: 0, y: 0 }, State(false), Neighbors(0)),
-            (Position { x: 1, y: 0 }, State(true), Neighbors(0)),
-            (Position { x: 2, y: 0 }, State(false), Neighbors(0)),
-            (Position { x: 0, y: 1 }, State(false), Neighbors(0)),
-            (Position { x: 1, y: 1 }, State(true), Neighbors(0)),
-            (Position { x: 2, y: 1 }, State(false), Neighbors(0)),
-            (Position { x: 0, y: 2 }, State(false), Neighbors(0)),
-            (Position { x: 1, y: 2 }, State(true), Neighbors(0)),
-            (Position { x: 2, y: 2 }, State(false), Neighbors(0)),
+            (Position { x: 0, y: 0 }, State(DEAD), Neighbors(0)),
+            (Position { x: 1, y: 0 }, State(ALIVE), Neighbors(0)),
+            (Position { x: 2, y: 0 }, State(DEAD), Neighbors(0)),
+            (Position { x: 0, y: 1 }, State(DEAD), Neighbors(0)),
+            (Position { x: 1, y: 1 }, State(ALIVE), Neighbors(0)),
+            (Position { x: 2, y: 1 }, State(DEAD), Neighbors(0)),
+            (Position { x: 0, y: 2 }, State(DEAD), Neighbors(0)),
+            (Position { x: 1, y: 2 }, State(ALIVE), Neighbors(0)),
+            (Position { x: 2, y: 2 }, State(DEAD), Neighbors(0)),
         ];
 
         world.spawn_batch(to_spawn);
@@ -180,12 +531,12 @@ mod tests {
 
     fn spawn_toad_pattern(world: &mut World) {
         let to_spawn = vec![
-            (Position { x: 0, y: 0 }, State(true), Neighbors(0)),
-            (Position { x: 0, y: 1 }, State(true), Neighbors(0)),
-            (Position { x: 0, y: 2 }, State(true), Neighbors(0)),
-            (Position { x: 1, y: 1 }, State(true), Neighbors(0)),
-            (Position { x: 1, y: 2 }, State(true), Neighbors(0)),
-            (Position { x: 1, y: 3 }, State(true), Neighbors(0)),
+            (Position { x: 0, y: 0 }, State(ALIVE), Neighbors(0)),
+            (Position { x: 0, y: 1 }, State(ALIVE), Neighbors(0)),
+            (Position { x: 0, y: 2 }, State(ALIVE), Neighbors(0)),
+            (Position { x: 1, y: 1 }, State(ALIVE), Neighbors(0)),
+            (Position { x: 1, y: 2 }, State(ALIVE), Neighbors(0)),
+            (Position { x: 1, y: 3 }, State(ALIVE), Neighbors(0)),
         ];
 
         world.spawn_batch(to_spawn);
@@ -194,16 +545,23 @@ mod tests {
     #[test]
     fn test_block_pattern() {
         let mut world = World::new();
+        let grid = Grid {
+            width: 2,
+            height: 2,
+            boundary: Boundary::Dead,
+            topology: Topology::Square,
+        };
+        let moore = Stencil::from(&Neighborhood::default());
         spawn_block_pattern(&mut world);
 
-        update_neighbors_system(&mut world);
-        update_cells_system(&mut world);
+        update_neighbors_system(&mut world, &grid, &moore);
+        update_cells_system(&mut world, &Rule::default());
 
         let expected = vec![
-            (Position { x: 0, y: 0 }, State(true), Neighbors(3)),
-            (Position { x: 0, y: 1 }, State(true), Neighbors(3)),
-            (Position { x: 1, y: 0 }, State(true), Neighbors(3)),
-            (Position { x: 1, y: 1 }, State(true), Neighbors(3)),
+            (Position { x: 0, y: 0 }, State(ALIVE), Neighbors(3)),
+            (Position { x: 0, y: 1 }, State(ALIVE), Neighbors(3)),
+            (Position { x: 1, y: 0 }, State(ALIVE), Neighbors(3)),
+            (Position { x: 1, y: 1 }, State(ALIVE), Neighbors(3)),
         ];
 
         let actual: Vec<(Position, State, Neighbors)> = world
@@ -214,8 +572,8 @@ mod tests {
 
         assert_eq!(expected, actual);
 
-        update_neighbors_system(&mut world);
-        update_cells_system(&mut world);
+        update_neighbors_system(&mut world, &grid, &moore);
+        update_cells_system(&mut world, &Rule::default());
 
         let actual = world
             .query::<(&Position, &State, &Neighbors)>()
@@ -239,15 +597,22 @@ mod tests {
     #[test]
     fn test_toad_pattern() {
         let mut world = World::new();
+        let grid = Grid {
+            width: 2,
+            height: 4,
+            boundary: Boundary::Dead,
+            topology: Topology::Square,
+        };
+        let moore = Stencil::from(&Neighborhood::default());
         spawn_toad_pattern(&mut world);
 
         let expected = vec![
-            (Position { x: 0, y: 0 }, State(true), Neighbors(0)),
-            (Position { x: 0, y: 1 }, State(true), Neighbors(0)),
-            (Position { x: 0, y: 2 }, State(true), Neighbors(0)),
-            (Position { x: 1, y: 1 }, State(true), Neighbors(0)),
-            (Position { x: 1, y: 2 }, State(true), Neighbors(0)),
-            (Position { x: 1, y: 3 }, State(true), Neighbors(0)),
+            (Position { x: 0, y: 0 }, State(ALIVE), Neighbors(0)),
+            (Position { x: 0, y: 1 }, State(ALIVE), Neighbors(0)),
+            (Position { x: 0, y: 2 }, State(ALIVE), Neighbors(0)),
+            (Position { x: 1, y: 1 }, State(ALIVE), Neighbors(0)),
+            (Position { x: 1, y: 2 }, State(ALIVE), Neighbors(0)),
+            (Position { x: 1, y: 3 }, State(ALIVE), Neighbors(0)),
         ];
 
         let actual: Vec<(Position, State, Neighbors)> = world
@@ -261,18 +626,18 @@ mod tests {
         println!("Initial state:");
         print_world_state(&world);
 
-        update_neighbors_system(&mut world);
+        update_neighbors_system(&mut world, &grid, &moore);
 
         println!("After updating neighbors:");
         print_world_state(&world);
 
         let expected = vec![
-            (Position { x: 0, y: 0 }, State(true), Neighbors(1)),
-            (Position { x: 0, y: 1 }, State(true), Neighbors(3)),
-            (Position { x: 0, y: 2 }, State(true), Neighbors(2)),
-            (Position { x: 1, y: 1 }, State(true), Neighbors(2)),
-            (Position { x: 1, y: 2 }, State(true), Neighbors(3)),
-            (Position { x: 1, y: 3 }, State(true), Neighbors(1)),
+            (Position { x: 0, y: 0 }, State(ALIVE), Neighbors(1)),
+            (Position { x: 0, y: 1 }, State(ALIVE), Neighbors(3)),
+            (Position { x: 0, y: 2 }, State(ALIVE), Neighbors(2)),
+            (Position { x: 1, y: 1 }, State(ALIVE), Neighbors(2)),
+            (Position { x: 1, y: 2 }, State(ALIVE), Neighbors(3)),
+            (Position { x: 1, y: 3 }, State(ALIVE), Neighbors(1)),
         ];
 
         let actual: Vec<(Position, State, Neighbors)> = world
@@ -283,24 +648,31 @@ mod tests {
 
         assert_eq!(expected, actual);
 
-        update_cells_system(&mut world);
+        update_cells_system(&mut world, &Rule::default());
     }
 
     #[test]
     fn test_blinker_pattern() {
         let mut world = World::new();
+        let grid = Grid {
+            width: 3,
+            height: 3,
+            boundary: Boundary::Dead,
+            topology: Topology::Square,
+        };
+        let moore = Stencil::from(&Neighborhood::default());
         spawn_blinker_pattern(&mut world);
 
         let expected = vec![
-            (Position { x: 0, y: 0 }, State(false), Neighbors(0)),
-            (Position { x: 1, y: 0 }, State(true), Neighbors(0)),
-            (Position { x: 2, y: 0 }, State(false), Neighbors(0)),
-            (Position { x: 0, y: 1 }, State(false), Neighbors(0)),
-            (Position { x: 1, y: 1 }, State(true), Neighbors(0)),
-            (Position { x: 2, y: 1 }, State(false), Neighbors(0)),
-            (Position { x: 0, y: 2 }, State(false), Neighbors(0)),
-            (Position { x: 1, y: 2 }, State(true), Neighbors(0)),
-            (Position { x: 2, y: 2 }, State(false), Neighbors(0)),
+            (Position { x: 0, y: 0 }, State(DEAD), Neighbors(0)),
+            (Position { x: 1, y: 0 }, State(ALIVE), Neighbors(0)),
+            (Position { x: 2, y: 0 }, State(DEAD), Neighbors(0)),
+            (Position { x: 0, y: 1 }, State(DEAD), Neighbors(0)),
+            (Position { x: 1, y: 1 }, State(ALIVE), Neighbors(0)),
+            (Position { x: 2, y: 1 }, State(DEAD), Neighbors(0)),
+            (Position { x: 0, y: 2 }, State(DEAD), Neighbors(0)),
+            (Position { x: 1, y: 2 }, State(ALIVE), Neighbors(0)),
+            (Position { x: 2, y: 2 }, State(DEAD), Neighbors(0)),
         ];
 
         let actual: Vec<(Position, State, Neighbors)> = world
@@ -311,20 +683,20 @@ mod tests {
 
         assert_eq!(expected, actual);
 
-        update_neighbors_system(&mut world);
-        update_cells_system(&mut world);
-        update_neighbors_system(&mut world);
+        update_neighbors_system(&mut world, &grid, &moore);
+        update_cells_system(&mut world, &Rule::default());
+        update_neighbors_system(&mut world, &grid, &moore);
 
         let expected = vec![
-            (Position { x: 0, y: 0 }, State(false), Neighbors(2)),
-            (Position { x: 1, y: 0 }, State(false), Neighbors(3)),
-            (Position { x: 2, y: 0 }, State(false), Neighbors(2)),
-            (Position { x: 0, y: 1 }, State(true), Neighbors(1)),
-            (Position { x: 1, y: 1 }, State(true), Neighbors(2)),
-            (Position { x: 2, y: 1 }, State(true), Neighbors(1)),
-            (Position { x: 0, y: 2 }, State(false), Neighbors(2)),
-            (Position { x: 1, y: 2 }, State(false), Neighbors(3)),
-            (Position { x: 2, y: 2 }, State(false), Neighbors(2)),
+            (Position { x: 0, y: 0 }, State(DEAD), Neighbors(2)),
+            (Position { x: 1, y: 0 }, State(DEAD), Neighbors(3)),
+            (Position { x: 2, y: 0 }, State(DEAD), Neighbors(2)),
+            (Position { x: 0, y: 1 }, State(ALIVE), Neighbors(1)),
+            (Position { x: 1, y: 1 }, State(ALIVE), Neighbors(2)),
+            (Position { x: 2, y: 1 }, State(ALIVE), Neighbors(1)),
+            (Position { x: 0, y: 2 }, State(DEAD), Neighbors(2)),
+            (Position { x: 1, y: 2 }, State(DEAD), Neighbors(3)),
+            (Position { x: 2, y: 2 }, State(DEAD), Neighbors(2)),
         ];
 
         let actual: Vec<(Position, State, Neighbors)> = world
@@ -335,4 +707,264 @@ mod tests {
 
         assert_eq!(expected, actual);
     }
+
+    #[test]
+    fn test_blinker_cycle_is_detected() {
+        let mut world = World::new();
+        let grid = Grid {
+            width: 3,
+            height: 3,
+            boundary: Boundary::Dead,
+            topology: Topology::Square,
+        };
+        let moore = Stencil::from(&Neighborhood::default());
+        spawn_blinker_pattern(&mut world);
+
+        let mut detector = CycleDetector::new(8);
+        let mut detected = Vec::new();
+        for generation in 0..6 {
+            let (hash, population) = fingerprint(&world);
+            detected.extend(detector.observe(generation, hash, population));
+            update_neighbors_system(&mut world, &grid, &moore);
+            update_cells_system(&mut world, &Rule::default());
+        }
+
+        assert_eq!(
+            detected,
+            [cycle::Stabilization::Cycle {
+                start: 0,
+                period: 2
+            }]
+        );
+    }
+
+    #[test]
+    fn test_blinker_stats() {
+        let mut world = World::new();
+        let grid = Grid {
+            width: 3,
+            height: 3,
+            boundary: Boundary::Dead,
+            topology: Topology::Square,
+        };
+        let moore = Stencil::from(&Neighborhood::default());
+        spawn_blinker_pattern(&mut world);
+
+        let initial = cell_stats(&world, 0, None);
+        let before = cell_states(&world);
+        update_neighbors_system(&mut world, &grid, &moore);
+        update_cells_system(&mut world, &Rule::default());
+        let next = cell_stats(&world, 1, Some(&before));
+
+        assert_eq!((initial.population, initial.changed), (3, 0));
+        assert_eq!(
+            (next.population, next.births, next.deaths, next.changed),
+            (3, 2, 2, 4)
+        );
+        assert_eq!(
+            next.bounds,
+            Some(stats::Bounds {
+                min_x: 0,
+                min_y: 1,
+                max_x: 2,
+                max_y: 1
+            })
+        );
+    }
+
+    #[test]
+    fn test_blinker_on_torus() {
+        let mut world = World::new();
+        let grid = Grid {
+            width: 3,
+            height: 3,
+            boundary: Boundary::Torus,
+            topology: Topology::Square,
+        };
+        let moore = Stencil::from(&Neighborhood::default());
+        spawn_blinker_pattern(&mut world);
+
+        update_neighbors_system(&mut world, &grid, &moore);
+        update_cells_system(&mut world, &Rule::default());
+
+        // On a 3x3 torus every dead cell sees all three blinker cells and is
+        // born, while the blinker cells see the other two and survive.
+        let alive = world
+            .query::<&State>()
+            .iter()
+            .filter(|(_, state)| state.is_alive())
+            .count();
+
+        assert_eq!(alive, 9);
+    }
+
+    #[test]
+    fn test_brians_brain_dying_state() {
+        let mut world = World::new();
+        let grid = Grid {
+            width: 4,
+            height: 3,
+            boundary: Boundary::Dead,
+            topology: Topology::Square,
+        };
+        let moore = Stencil::from(&Neighborhood::default());
+        let to_spawn = (0..12).map(|i| {
+            let position = Position { x: i % 4, y: i / 4 };
+            let alive = position.y == 1 && (position.x == 1 || position.x == 2);
+            (position, State(alive as u8), Neighbors(0))
+        });
+        world.spawn_batch(to_spawn);
+        let rule: Rule = "B2/S/3".parse().unwrap();
+
+        let states = |world: &World| {
+            let mut states: Vec<(i32, i32, u8)> = world
+                .query::<(&Position, &State)>()
+                .iter()
+                .map(|(_, (position, state))| (position.y, position.x, state.0))
+                .collect();
+            states.sort();
+            states
+                .into_iter()
+                .map(|(_, _, state)| state)
+                .collect::<Vec<_>>()
+        };
+
+        update_neighbors_system(&mut world, &grid, &moore);
+        update_cells_system(&mut world, &rule);
+        assert_eq!(states(&world), vec![0, 1, 1, 0, 0, 2, 2, 0, 0, 1, 1, 0]);
+
+        // Dying cells turn dead and are not counted as neighbours, so the cells at
+        // either end of the middle row only see the two newborn cells beside them.
+        update_neighbors_system(&mut world, &grid, &moore);
+        update_cells_system(&mut world, &rule);
+        assert_eq!(states(&world), vec![0, 2, 2, 0, 1, 0, 0, 1, 0, 2, 2, 0]);
+    }
+
+    #[test]
+    fn test_von_neumann_range_two() {
+        let mut world = World::new();
+        let grid = Grid {
+            width: 5,
+            height: 5,
+            boundary: Boundary::Dead,
+            topology: Topology::Square,
+        };
+        let stencil = Stencil::from(&Neighborhood::VonNeumann(2));
+        world.spawn_batch((0..25).map(|i| {
+            let position = Position { x: i % 5, y: i / 5 };
+            let alive = (position.x, position.y) == (2, 2);
+            (position, State(alive as u8), Neighbors(0))
+        }));
+
+        update_neighbors_system(&mut world, &grid, &stencil);
+        update_cells_system(&mut world, &"B1/S".parse().unwrap());
+
+        // The seed dies and the diamond of cells within two steps of it is born.
+        for (_, (position, state)) in world.query::<(&Position, &State)>().iter() {
+            let distance = (position.x - 2).abs() + (position.y - 2).abs();
+            assert_eq!(
+                state.is_alive(),
+                (1..=2).contains(&distance),
+                "{:?}",
+                position
+            );
+        }
+    }
+
+    #[test]
+    fn test_triangle_edge_neighbours() {
+        let mut world = World::new();
+        let grid = Grid {
+            width: 5,
+            height: 5,
+            boundary: Boundary::Dead,
+            topology: Topology::Triangular,
+        };
+        let stencil = grid.topology.stencil(&Neighborhood::VonNeumann(1));
+        world.spawn_batch((0..25).map(|i| {
+            let position = Position { x: i % 5, y: i / 5 };
+            let alive = (position.x, position.y) == (2, 2);
+            (position, State(alive as u8), Neighbors(0))
+        }));
+
+        update_neighbors_system(&mut world, &grid, &stencil);
+        update_cells_system(&mut world, &"B1/S".parse().unwrap());
+
+        // (2, 2) points up, so it shares its base with the triangle below it.
+        let mut alive: Vec<(i32, i32)> = world
+            .query::<(&Position, &State)>()
+            .iter()
+            .filter(|(_, (_, state))| state.is_alive())
+            .map(|(_, (position, _))| (position.x, position.y))
+            .collect();
+        alive.sort();
+        assert_eq!(alive, vec![(1, 2), (2, 3), (3, 2)]);
+    }
+
+    #[test]
+    fn test_batch_spawn_cells_matches_soup() {
+        let mut world = World::new();
+        let grid = Grid {
+            width: 7,
+            height: 5,
+            boundary: Boundary::Dead,
+            topology: Topology::Square,
+        };
+        let soup = Soup::new(42, 0.5);
+        batch_spawn_cells(&mut world, &grid, &soup);
+
+        let mut alive: Vec<(u32, u32)> = world
+            .query::<(&Position, &State)>()
+            .iter()
+            .filter(|(_, (_, state))| state.is_alive())
+            .map(|(_, (position, _))| (position.x as u32, position.y as u32))
+            .collect();
+        alive.sort_by_key(|(x, y)| (*y, *x));
+
+        assert_eq!(world.len(), 35);
+        assert_eq!(alive, soup.alive_cells(7, 5).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_rle_pattern_round_trip() {
+        let mut world = World::new();
+        let grid = Grid {
+            width: 5,
+            height: 5,
+            boundary: Boundary::Dead,
+            topology: Topology::Square,
+        };
+        let glider = game_of_life_core::pattern::rle::parse("x = 3, y = 3\nbo$2bo$3o!").unwrap();
+        spawn_pattern(&mut world, &grid, &glider, 1, 2);
+
+        let alive: Vec<Position> = world
+            .query::<(&Position, &State)>()
+            .iter()
+            .filter(|(_, (_, state))| state.is_alive())
+            .map(|(_, (position, _))| position.clone())
+            .collect();
+
+        assert_eq!(world.len(), 25);
+        assert_eq!(
+            alive,
+            vec![
+                Position { x: 2, y: 2 },
+                Position { x: 3, y: 3 },
+                Position { x: 1, y: 4 },
+                Position { x: 2, y: 4 },
+                Position { x: 3, y: 4 },
+            ]
+        );
+
+        let filename = std::env::temp_dir().join("hecs_test_rle_pattern_round_trip.rle");
+        let filename = filename.to_str().unwrap();
+        save_cells_to_pattern_file(&world, &grid, &Rule::default(), filename);
+        let saved = Pattern::load(filename).unwrap();
+        std::fs::remove_file(filename).unwrap();
+
+        assert_eq!(
+            saved.cells_at_offset(0, 0, 5, 5),
+            glider.cells_at_offset(1, 2, 5, 5)
+        );
+    }
 }
@@ -1,12 +1,40 @@
 mod game_of_life {
     use std::io::Write;
 
-    use rand::Rng;
+    use game_of_life_core::{
+        config::{CycleDetection, Extent},
+        cycle::{self, CycleDetector, Stabilization},
+        neighborhood::Offset,
+        rule::{ALIVE, DEAD},
+        sparse::SparseLife,
+        stats::{self, GenerationStats},
+        Boundary, Config, Neighborhood, Pattern, Rule, Soup, Topology,
+    };
 
     #[derive(Debug, PartialEq, Clone, Copy)]
     enum Cell {
-        Dead = 0,
-        Alive = 1,
+        Dead,
+        Alive,
+        /// One of the refractory states `2..states` of a Generations rule.
+        Dying(u8),
+    }
+
+    impl Cell {
+        fn from_state(state: u8) -> Self {
+            match state {
+                DEAD => Cell::Dead,
+                ALIVE => Cell::Alive,
+                dying => Cell::Dying(dying),
+            }
+        }
+
+        fn state(self) -> u8 {
+            match self {
+                Cell::Dead => DEAD,
+                Cell::Alive => ALIVE,
+                Cell::Dying(dying) => dying,
+            }
+        }
     }
 
     #[derive(Default)]
@@ -14,6 +42,10 @@ mod game_of_life {
         width: u32,
         height: u32,
         cells: Vec<Cell>,
+        rule: Rule,
+        neighborhood: Neighborhood,
+        topology: Topology,
+        boundary: Boundary,
         durations: Vec<std::time::Duration>,
     }
 
@@ -31,16 +63,31 @@ mod game_of_life {
         cells
     }
 
-    fn randomize(cells: &mut Vec<Cell>) {
-        let mut rng = rand::thread_rng();
-        for cell in cells.iter_mut() {
-            *cell = match rng.gen_range(0..2) {
-                0 => Cell::Dead,
-                _ => Cell::Alive,
+    fn randomize(cells: &mut [Cell], width: u32, soup: &Soup) {
+        for (i, cell) in cells.iter_mut().enumerate() {
+            let x = i as u32 % width;
+            let y = i as u32 / width;
+            *cell = if soup.is_alive(x, y) {
+                Cell::Alive
+            } else {
+                Cell::Dead
             };
         }
     }
 
+    fn place_pattern(
+        cells: &mut Vec<Cell>,
+        width: u32,
+        height: u32,
+        pattern: &Pattern,
+        offset_x: i32,
+        offset_y: i32,
+    ) {
+        for (x, y) in pattern.cells_at_offset(offset_x, offset_y, width, height) {
+            set_cell_by_position(cells, width, x as u32, y as u32, Cell::Alive);
+        }
+    }
+
     fn get_cell_by_position(cells: &Vec<Cell>, width: u32, x: u32, y: u32) -> &Cell {
         &cells[(y * width + x) as usize]
     }
@@ -53,24 +100,20 @@ mod game_of_life {
         cells: &Vec<Cell>,
         width: u32,
         height: u32,
+        boundary: Boundary,
+        offsets: &[Offset],
         x: u32,
         y: u32,
     ) -> u32 {
         // let start = std::time::Instant::now();
         let mut count = 0;
-        for i in -1..2 {
-            for j in -1..2 {
-                if i == 0 && j == 0 {
-                    continue;
-                }
-                let new_x = x as i32 + i;
-                let new_y = y as i32 + j;
-                if new_x < 0 || new_x >= width as i32 || new_y < 0 || new_y >= height as i32 {
-                    continue;
-                }
-                if *get_cell_by_position(cells, width, new_x as u32, new_y as u32) == Cell::Alive {
-                    count += 1;
-                }
+        for ((i, j), weight) in offsets {
+            let Some((new_x, new_y)) = boundary.resolve(x as i32 + i, y as i32 + j, width, height)
+            else {
+                continue;
+            };
+            if *get_cell_by_position(cells, width, new_x as u32, new_y as u32) == Cell::Alive {
+                count += *weight as u32;
             }
         }
 
@@ -85,6 +128,7 @@ mod game_of_life {
 
     fn run_iteration(universe: &mut Universe) {
         let mut new_cells = universe.cells.clone();
+        let stencil = universe.topology.stencil(&universe.neighborhood);
         for y in 0..universe.height {
             for x in 0..universe.width {
                 let cell = get_cell_by_position(&universe.cells, universe.width, x, y);
@@ -92,31 +136,49 @@ mod game_of_life {
                     &universe.cells,
                     universe.width,
                     universe.height,
+                    universe.boundary,
+                    stencil.at(x as i32, y as i32),
                     x,
                     y,
                 );
-                let new_cell = match (cell, alive_neighbours) {
-                    (Cell::Alive, 2) | (Cell::Alive, 3) => Cell::Alive,
-                    (Cell::Dead, 3) => Cell::Alive,
-                    _ => Cell::Dead,
-                };
+                let new_cell =
+                    Cell::from_state(universe.rule.next(cell.state(), alive_neighbours as u16));
                 set_cell_by_position(&mut new_cells, universe.width, x, y, new_cell);
             }
         }
         universe.cells = new_cells;
     }
 
-    fn print_cells(cells: &Vec<Cell>, width: u32, height: u32) {
+    /// Dying cells fade from dark to light shades as they get closer to dead.
+    fn dying_glyph(state: u8, states: u8) -> &'static str {
+        const SHADES: [&str; 3] = ["▓", "▒", "░"];
+        let age = (state - 2) as usize * SHADES.len() / (states - 2) as usize;
+        SHADES[age.min(SHADES.len() - 1)]
+    }
+
+    /// Hexagon rows are shifted half a cell right of the row below, triangles alternate
+    /// between pointing up and down.
+    fn print_cells(cells: &Vec<Cell>, width: u32, height: u32, states: u8, topology: Topology) {
         for y in 0..height {
+            if topology == Topology::Hexagonal {
+                print!("{}", " ".repeat((height - 1 - y) as usize));
+            }
             for x in 0..width {
                 let cell = get_cell_by_position(cells, width, x, y);
-                print!(
-                    "{}",
-                    match cell {
-                        Cell::Alive => "■",
-                        Cell::Dead => "□",
-                    }
-                );
+                let up = (x + y) % 2 == 0;
+                let glyph = match (cell, topology) {
+                    (Cell::Dying(state), _) => dying_glyph(*state, states),
+                    (Cell::Alive, Topology::Triangular) if up => "▲",
+                    (Cell::Alive, Topology::Triangular) => "▼",
+                    (Cell::Dead, Topology::Triangular) if up => "△",
+                    (Cell::Dead, Topology::Triangular) => "▽",
+                    (Cell::Alive, _) => "■",
+                    (Cell::Dead, _) => "□",
+                };
+                print!("{}", glyph);
+                if topology == Topology::Hexagonal {
+                    print!(" ");
+                }
             }
             println!();
         }
@@ -129,7 +191,7 @@ mod game_of_life {
                 let cell = get_cell_by_position(cells, width, x, y);
                 let cell_str = match cell {
                     Cell::Alive => "1",
-                    Cell::Dead => "0",
+                    Cell::Dead | Cell::Dying(_) => "0",
                 };
                 file.write_all(cell_str.as_bytes())
                     .expect("Unable to write data");
@@ -138,6 +200,25 @@ mod game_of_life {
         }
     }
 
+    /// Saves the cells in the pattern format matching the file extension, e.g. `.rle` or `.cells`.
+    fn save_cells_to_pattern_file(
+        cells: &[Cell],
+        width: u32,
+        height: u32,
+        rule: Rule,
+        topology: Topology,
+        filename: &str,
+    ) {
+        let alive_cells = cells
+            .iter()
+            .enumerate()
+            .filter(|(_, cell)| **cell == Cell::Alive)
+            .map(|(i, _)| ((i as u32 % width) as i32, (i as u32 / width) as i32));
+        let pattern =
+            Pattern::from_grid(width, height, alive_cells, Some(rule)).with_topology(topology);
+        pattern.save(filename).expect("Unable to write data");
+    }
+
     fn save_durations_to_file(durations: &Vec<std::time::Duration>, filename: &str) {
         let mut file = std::fs::File::create(filename).expect("Unable to create file");
         for duration in durations.iter() {
@@ -148,31 +229,289 @@ mod game_of_life {
         }
     }
 
-    pub fn run_simulation(width: u32, height: u32, iterations: u32, should_print_cells: bool) {
+    /// The starting cells on the unbounded plane. Patterns are placed without clipping,
+    /// only the soup is limited to the `width x height` rectangle.
+    fn initial_unbounded_cells(config: &Config) -> Vec<(i32, i32)> {
+        match &config.pattern {
+            Some(path) => {
+                let pattern = Pattern::load(path).expect("Invalid pattern");
+                let (offset_x, offset_y) = config.pattern_offset;
+                pattern
+                    .cells
+                    .iter()
+                    .map(|(x, y)| (*x as i32 + offset_x, *y as i32 + offset_y))
+                    .collect()
+            }
+            None => {
+                let soup = config.soup();
+                println!("Soup seed: {}", soup.seed);
+                soup.alive_cells(config.width, config.height)
+                    .map(|(x, y)| (x as i32, y as i32))
+                    .collect()
+            }
+        }
+    }
+
+    /// The state hash and population of a generation.
+    fn fingerprint(cells: &[Cell], width: u32) -> (u64, usize) {
+        let hash = cycle::hash_cells(cells.iter().enumerate().map(|(index, cell)| {
+            let index = index as u32;
+            ((index % width) as i32, (index / width) as i32, cell.state())
+        }));
+        let population = cells.iter().filter(|cell| **cell != Cell::Dead).count();
+        (hash, population)
+    }
+
+    fn unbounded_fingerprint(life: &SparseLife) -> (u64, usize) {
+        let cells = life.alive_cells();
+        let hash = cycle::hash_cells(cells.iter().map(|(x, y)| (*x, *y, ALIVE)));
+        (hash, cells.len())
+    }
+
+    /// Feeds `generation` to the detector, reporting a stabilisation, and tells whether
+    /// the run should stop there.
+    fn observe(
+        detector: &mut Option<CycleDetector>,
+        config: &Config,
+        generation: u64,
+        fingerprint: impl FnOnce() -> (u64, usize),
+    ) -> bool {
+        let Some(detector) = detector else {
+            return false;
+        };
+        let (hash, population) = fingerprint();
+        match detector.observe(generation, hash, population) {
+            Some(stabilization) => {
+                report_stabilization(stabilization);
+                config.cycles == CycleDetection::Stop
+            }
+            None => false,
+        }
+    }
+
+    fn report_stabilization(stabilization: Stabilization) {
+        println!(
+            "Stabilized at generation {}: {} with period {}",
+            stabilization.start(),
+            stabilization.kind(),
+            stabilization.period()
+        );
+    }
+
+    /// Counts `cells`, comparing them with the previous generation when there is one.
+    fn cell_stats(
+        generation: u64,
+        before: Option<&[Cell]>,
+        cells: &[Cell],
+        width: u32,
+    ) -> GenerationStats {
+        let mut stats = GenerationStats::new(generation);
+        for (index, cell) in cells.iter().enumerate() {
+            let (x, y) = ((index as u32 % width) as i32, (index as u32 / width) as i32);
+            match before {
+                Some(before) => stats.record(x, y, before[index].state(), cell.state()),
+                None => stats.count(x, y, cell.state()),
+            }
+        }
+        stats
+    }
+
+    fn save_stabilization(detector: &Option<CycleDetector>, config: &Config) {
+        if let (Some(detector), Some(filename)) = (detector, &config.stabilization_output) {
+            cycle::save(detector.result(), filename).expect("Unable to write data");
+        }
+    }
+
+    fn run_unbounded_simulation(config: &Config) -> std::time::Duration {
+        let mut life = SparseLife::new(config.rule, initial_unbounded_cells(config));
+        let mut durations = Vec::new();
+        let mut detector = config.cycle_detector();
+        let mut stop = observe(&mut detector, config, 0, || unbounded_fingerprint(&life));
+        let mut generation_stats = config.stats_output.as_ref().map(|_| {
+            let mut initial = GenerationStats::new(0);
+            for (key, chunk) in &life.chunks {
+                initial.count_chunk(*key, chunk);
+            }
+            vec![initial]
+        });
+        let start_sim = std::time::Instant::now();
+        for generation in 1..=config.generations {
+            if stop {
+                break;
+            }
+            let before = generation_stats.as_ref().map(|_| life.chunks.clone());
+            let start = std::time::Instant::now();
+            life.step();
+            let duration = start.elapsed();
+            durations.push(duration);
+            if let (Some(generation_stats), Some(before)) = (&mut generation_stats, before) {
+                let mut record = stats::chunk_stats(generation, &before, &life.chunks);
+                record.duration = Some(duration);
+                generation_stats.push(record);
+            }
+            stop = observe(&mut detector, config, generation, || {
+                unbounded_fingerprint(&life)
+            });
+        }
+        let total_time = start_sim.elapsed();
+        save_stabilization(&detector, config);
+        if let (Some(generation_stats), Some(filename)) = (&generation_stats, &config.stats_output)
+        {
+            stats::save(generation_stats, filename).expect("Unable to write data");
+        }
+        println!(
+            "Population {} in {} chunks",
+            life.population(),
+            life.chunks.len()
+        );
+
+        if let Some(filename) = &config.durations_output {
+            save_durations_to_file(&durations, filename);
+        }
+        if let Some(filename) = &config.output {
+            life.to_pattern()
+                .save(filename)
+                .expect("Unable to write data");
+        }
+        total_time
+    }
+
+    /// Returns how long the generation loop took, leaving out setup and output.
+    pub fn run_simulation(config: &Config, should_print_cells: bool) -> std::time::Duration {
+        if config.extent == Extent::Unbounded {
+            return run_unbounded_simulation(config);
+        }
+
+        let width = config.width;
+        let height = config.height;
         let mut universe = Universe {
             width,
             height,
             cells: initialize_cells(width, height),
+            rule: config.rule,
+            neighborhood: config.neighborhood.clone(),
+            topology: config.topology,
+            boundary: config.boundary,
             ..Default::default()
         };
-        randomize(&mut universe.cells);
-        for i in 0..iterations {
+        match &config.pattern {
+            Some(path) => {
+                let pattern = Pattern::load(path).expect("Invalid pattern");
+                let (offset_x, offset_y) = config.pattern_offset;
+                place_pattern(
+                    &mut universe.cells,
+                    width,
+                    height,
+                    &pattern,
+                    offset_x,
+                    offset_y,
+                );
+            }
+            None => {
+                let soup = config.soup();
+                println!("Soup seed: {}", soup.seed);
+                randomize(&mut universe.cells, width, &soup);
+            }
+        }
+        let mut detector = config.cycle_detector();
+        let mut stop = observe(&mut detector, config, 0, || {
+            fingerprint(&universe.cells, width)
+        });
+        let mut generation_stats = config
+            .stats_output
+            .as_ref()
+            .map(|_| vec![cell_stats(0, None, &universe.cells, width)]);
+        let start_sim = std::time::Instant::now();
+        for i in 0..config.generations {
+            if stop {
+                break;
+            }
+            let before = generation_stats.as_ref().map(|_| universe.cells.clone());
             let start = std::time::Instant::now();
             if should_print_cells {
                 println!("Iteration {}", i);
-                print_cells(&universe.cells, width, height);
+                print_cells(
+                    &universe.cells,
+                    width,
+                    height,
+                    config.rule.states(),
+                    config.topology,
+                );
                 save_cells_to_file(&universe.cells, width, height, "cells.txt");
+                save_cells_to_pattern_file(
+                    &universe.cells,
+                    width,
+                    height,
+                    config.rule,
+                    config.topology,
+                    "cells.rle",
+                );
             }
             run_iteration(&mut universe);
             let duration = start.elapsed();
             universe.durations.push(duration);
             //println!("Time elapsed in running the iteration is: {:?}", duration);
+            if let (Some(generation_stats), Some(before)) = (&mut generation_stats, before) {
+                let mut record = cell_stats(i + 1, Some(&before), &universe.cells, width);
+                record.duration = Some(duration);
+                generation_stats.push(record);
+            }
+            stop = observe(&mut detector, config, i + 1, || {
+                fingerprint(&universe.cells, width)
+            });
+        }
+        let total_time = start_sim.elapsed();
+        save_stabilization(&detector, config);
+        if let (Some(generation_stats), Some(filename)) = (&generation_stats, &config.stats_output)
+        {
+            stats::save(generation_stats, filename).expect("Unable to write data");
         }
 
-        //save_durations_to_file(&universe.durations, "durations.txt");
+        if let Some(filename) = &config.durations_output {
+            save_durations_to_file(&universe.durations, filename);
+        }
+        if let Some(filename) = &config.output {
+            save_cells_to_pattern_file(
+                &universe.cells,
+                width,
+                height,
+                config.rule,
+                config.topology,
+                filename,
+            );
+        }
+        total_time
     }
 
+    #[cfg(test)]
     mod tests {
+        use game_of_life_core::Pattern;
+
+        fn fixture_path(name: &str) -> String {
+            format!("{}/../fixtures/{}", env!("CARGO_MANIFEST_DIR"), name)
+        }
+
+        fn load_universe(name: &str) -> super::Universe {
+            load_universe_from_file(&fixture_path(name))
+        }
+
+        fn load_universe_from_file(filename: &str) -> super::Universe {
+            let pattern = Pattern::load(filename).unwrap();
+            let mut cells = super::initialize_cells(pattern.width, pattern.height);
+            super::place_pattern(&mut cells, pattern.width, pattern.height, &pattern, 0, 0);
+
+            super::Universe {
+                width: pattern.width,
+                height: pattern.height,
+                cells,
+                ..Default::default()
+            }
+        }
+
+        fn load_cells(name: &str) -> Vec<super::Cell> {
+            load_universe(name).cells
+        }
+
         #[test]
         fn test_initialize_cells() {
             let width = 3;
@@ -184,8 +523,61 @@ mod game_of_life {
             }
         }
 
+        #[test]
+        fn test_randomize_matches_soup() {
+            let width = 7;
+            let height = 5;
+            let soup = game_of_life_core::Soup::new(42, 0.5);
+            let mut cells = super::initialize_cells(width, height);
+            super::randomize(&mut cells, width, &soup);
+
+            for (x, y) in soup.alive_cells(width, height) {
+                assert_eq!(
+                    *super::get_cell_by_position(&cells, width, x, y),
+                    super::Cell::Alive
+                );
+            }
+            let alive = cells.iter().filter(|cell| **cell == super::Cell::Alive);
+            assert_eq!(alive.count(), soup.alive_cells(width, height).count());
+        }
+
         #[test]
         fn test_block_pattern() {
+            let mut universe = load_universe("block.cells");
+            let expected_cells = load_cells("block.cells");
+
+            assert_eq!(universe.cells, expected_cells);
+
+            super::run_iteration(&mut universe);
+
+            assert_eq!(universe.cells, expected_cells);
+        }
+
+        #[test]
+        fn test_beehive_pattern() {
+            let mut universe = load_universe("beehive.cells");
+            let expected_cells = load_cells("beehive.cells");
+
+            assert_eq!(universe.cells, expected_cells);
+
+            super::run_iteration(&mut universe);
+
+            assert_eq!(universe.cells, expected_cells);
+        }
+
+        #[test]
+        fn test_blinker_pattern() {
+            let mut universe = load_universe("blinker.cells");
+
+            super::run_iteration(&mut universe);
+            assert_eq!(universe.cells, load_cells("blinker_1.cells"));
+
+            super::run_iteration(&mut universe);
+            assert_eq!(universe.cells, load_cells("blinker.cells"));
+        }
+
+        #[test]
+        fn test_seeds_rule() {
             let width = 4;
             let height = 4;
             let cells = super::initialize_cells(width, height);
@@ -193,298 +585,360 @@ mod game_of_life {
                 width,
                 height,
                 cells,
+                rule: "B2/S".parse().unwrap(),
                 ..Default::default()
             };
+
             super::set_cell_by_position(&mut universe.cells, width, 1, 1, super::Cell::Alive);
-            super::set_cell_by_position(&mut universe.cells, width, 1, 2, super::Cell::Alive);
             super::set_cell_by_position(&mut universe.cells, width, 2, 1, super::Cell::Alive);
-            super::set_cell_by_position(&mut universe.cells, width, 2, 2, super::Cell::Alive);
-
-            let expected_cells = vec![
-                super::Cell::Dead,
-                super::Cell::Dead,
-                super::Cell::Dead,
-                super::Cell::Dead,
-                super::Cell::Dead,
-                super::Cell::Alive,
-                super::Cell::Alive,
-                super::Cell::Dead,
-                super::Cell::Dead,
-                super::Cell::Alive,
-                super::Cell::Alive,
-                super::Cell::Dead,
-                super::Cell::Dead,
-                super::Cell::Dead,
-                super::Cell::Dead,
-                super::Cell::Dead,
-            ];
-
-            for i in 0..(width * height) as usize {
-                assert_eq!(universe.cells[i], expected_cells[i]);
-            }
 
             super::run_iteration(&mut universe);
 
-            for i in 0..(width * height) as usize {
-                assert_eq!(universe.cells[i], expected_cells[i]);
-            }
+            let alive: Vec<usize> = universe
+                .cells
+                .iter()
+                .enumerate()
+                .filter(|(_, cell)| **cell == super::Cell::Alive)
+                .map(|(i, _)| i)
+                .collect();
+
+            // Both seeds die, and the cells above and below them are born.
+            assert_eq!(alive, vec![1, 2, 9, 10]);
         }
 
         #[test]
-        fn test_beehive_pattern() {
-            let width = 6;
-            let height = 5;
+        fn test_brians_brain_dying_state() {
+            let width = 4;
+            let height = 4;
             let cells = super::initialize_cells(width, height);
             let mut universe = super::Universe {
                 width,
                 height,
                 cells,
+                rule: "B2/S/3".parse().unwrap(),
                 ..Default::default()
             };
 
-            super::set_cell_by_position(&mut universe.cells, width, 1, 2, super::Cell::Alive);
+            super::set_cell_by_position(&mut universe.cells, width, 1, 1, super::Cell::Alive);
             super::set_cell_by_position(&mut universe.cells, width, 2, 1, super::Cell::Alive);
-            super::set_cell_by_position(&mut universe.cells, width, 2, 3, super::Cell::Alive);
-            super::set_cell_by_position(&mut universe.cells, width, 3, 1, super::Cell::Alive);
-            super::set_cell_by_position(&mut universe.cells, width, 3, 3, super::Cell::Alive);
-            super::set_cell_by_position(&mut universe.cells, width, 4, 2, super::Cell::Alive);
-
-            let expected_cells = vec![
-                super::Cell::Dead,
-                super::Cell::Dead,
-                super::Cell::Dead,
-                super::Cell::Dead,
-                super::Cell::Dead,
-                super::Cell::Dead,
-                super::Cell::Dead,
-                super::Cell::Dead,
-                super::Cell::Alive,
-                super::Cell::Alive,
-                super::Cell::Dead,
-                super::Cell::Dead,
-                super::Cell::Dead,
-                super::Cell::Alive,
-                super::Cell::Dead,
-                super::Cell::Dead,
-                super::Cell::Alive,
-                super::Cell::Dead,
-                super::Cell::Dead,
-                super::Cell::Dead,
-                super::Cell::Alive,
-                super::Cell::Alive,
-                super::Cell::Dead,
-                super::Cell::Dead,
-                super::Cell::Dead,
-                super::Cell::Dead,
-                super::Cell::Dead,
-                super::Cell::Dead,
-                super::Cell::Dead,
-                super::Cell::Dead,
-            ];
-
-            for i in 0..(width * height) as usize {
-                assert_eq!(universe.cells[i], expected_cells[i]);
-            }
 
             super::run_iteration(&mut universe);
 
-            for i in 0..(width * height) as usize {
-                assert_eq!(universe.cells[i], expected_cells[i]);
-            }
+            // Nothing survives in Brian's Brain, both cells start dying while the
+            // cells above and below them are born.
+            let states: Vec<u8> = universe.cells.iter().map(|cell| cell.state()).collect();
+            assert_eq!(states, vec![0, 1, 1, 0, 0, 2, 2, 0, 0, 1, 1, 0, 0, 0, 0, 0]);
+
+            // The dying cells are dead one generation later and block no births.
+            super::run_iteration(&mut universe);
+            assert_eq!(
+                *super::get_cell_by_position(&universe.cells, width, 1, 1),
+                super::Cell::Dead
+            );
+            assert_eq!(
+                *super::get_cell_by_position(&universe.cells, width, 1, 0),
+                super::Cell::Dying(2)
+            );
+            assert_eq!(super::dying_glyph(2, 3), "▓");
+            assert_eq!(super::dying_glyph(5, 6), "░");
         }
 
         #[test]
-        fn test_blinker_pattern() {
-            let width = 5;
-            let height = 5;
+        fn test_range_two_neighborhood() {
+            let width = 7;
+            let height = 7;
             let cells = super::initialize_cells(width, height);
             let mut universe = super::Universe {
                 width,
                 height,
                 cells,
+                rule: "B1/S".parse().unwrap(),
+                neighborhood: super::Neighborhood::Moore(2),
                 ..Default::default()
             };
 
-            super::set_cell_by_position(&mut universe.cells, width, 2, 1, super::Cell::Alive);
-            super::set_cell_by_position(&mut universe.cells, width, 2, 2, super::Cell::Alive);
-            super::set_cell_by_position(&mut universe.cells, width, 2, 3, super::Cell::Alive);
-
-            let expected_cells = vec![
-                super::Cell::Dead,
-                super::Cell::Dead,
-                super::Cell::Dead,
-                super::Cell::Dead,
-                super::Cell::Dead,
-                // first row
-                super::Cell::Dead,
-                super::Cell::Dead,
-                super::Cell::Alive,
-                super::Cell::Dead,
-                super::Cell::Dead,
-                // second row
-                super::Cell::Dead,
-                super::Cell::Dead,
-                super::Cell::Alive,
-                super::Cell::Dead,
-                super::Cell::Dead,
-                // third row
-                super::Cell::Dead,
-                super::Cell::Dead,
-                super::Cell::Alive,
-                super::Cell::Dead,
-                super::Cell::Dead,
-                // fourth row
-                super::Cell::Dead,
-                super::Cell::Dead,
-                super::Cell::Dead,
-                super::Cell::Dead,
-                super::Cell::Dead,
-                // fifth row
-            ];
-
-            for i in 0..(width * height) as usize {
-                assert_eq!(universe.cells[i], expected_cells[i]);
-            }
+            super::set_cell_by_position(&mut universe.cells, width, 3, 3, super::Cell::Alive);
 
             super::run_iteration(&mut universe);
 
-            let expected_cells = vec![
-                super::Cell::Dead,
-                super::Cell::Dead,
-                super::Cell::Dead,
-                super::Cell::Dead,
-                super::Cell::Dead,
-                // first row
-                super::Cell::Dead,
-                super::Cell::Dead,
-                super::Cell::Dead,
-                super::Cell::Dead,
-                super::Cell::Dead,
-                // second row
-                super::Cell::Dead,
-                super::Cell::Alive,
-                super::Cell::Alive,
-                super::Cell::Alive,
-                super::Cell::Dead,
-                // third row
-                super::Cell::Dead,
-                super::Cell::Dead,
-                super::Cell::Dead,
-                super::Cell::Dead,
-                super::Cell::Dead,
-                // fourth row
-                super::Cell::Dead,
-                super::Cell::Dead,
-                super::Cell::Dead,
-                super::Cell::Dead,
-                super::Cell::Dead,
-                // fifth row
-            ];
-
-            for i in 0..(width * height) as usize {
-                assert_eq!(universe.cells[i], expected_cells[i]);
+            // The seed dies and its whole 5x5 neighbourhood is born around it.
+            for y in 0..height {
+                for x in 0..width {
+                    let expected = (1..=5).contains(&x) && (1..=5).contains(&y) && (x, y) != (3, 3);
+                    assert_eq!(
+                        *super::get_cell_by_position(&universe.cells, width, x, y)
+                            == super::Cell::Alive,
+                        expected,
+                        "({}, {})",
+                        x,
+                        y
+                    );
+                }
             }
         }
 
         #[test]
-        fn test_toad_pattern() {
-            let width = 4;
-            let height = 4;
+        fn test_hexagonal_neighbours() {
+            let width = 3;
+            let height = 3;
             let cells = super::initialize_cells(width, height);
             let mut universe = super::Universe {
                 width,
                 height,
                 cells,
+                rule: "B1/S".parse().unwrap(),
+                topology: super::Topology::Hexagonal,
                 ..Default::default()
             };
 
-            super::set_cell_by_position(&mut universe.cells, width, 1, 0, super::Cell::Alive);
-
             super::set_cell_by_position(&mut universe.cells, width, 1, 1, super::Cell::Alive);
-            super::set_cell_by_position(&mut universe.cells, width, 2, 1, super::Cell::Alive);
 
-            super::set_cell_by_position(&mut universe.cells, width, 1, 2, super::Cell::Alive);
-            super::set_cell_by_position(&mut universe.cells, width, 2, 2, super::Cell::Alive);
-
-            super::set_cell_by_position(&mut universe.cells, width, 2, 3, super::Cell::Alive);
-
-            let expected_cells = vec![
-                super::Cell::Dead,
-                super::Cell::Alive,
-                super::Cell::Dead,
-                super::Cell::Dead,
-                // first row
-                super::Cell::Dead,
-                super::Cell::Alive,
-                super::Cell::Alive,
-                super::Cell::Dead,
-                // second row
-                super::Cell::Dead,
-                super::Cell::Alive,
-                super::Cell::Alive,
-                super::Cell::Dead,
-                // third row
-                super::Cell::Dead,
-                super::Cell::Dead,
-                super::Cell::Alive,
-                super::Cell::Dead,
-                // fourth row
-            ];
-
-            for i in 0..(width * height) as usize {
-                assert_eq!(universe.cells[i], expected_cells[i]);
+            super::run_iteration(&mut universe);
+
+            // Only the six hexagons around the seed are born, not the two corners that
+            // would touch it on a square grid.
+            let alive: Vec<usize> = universe
+                .cells
+                .iter()
+                .enumerate()
+                .filter(|(_, cell)| **cell == super::Cell::Alive)
+                .map(|(i, _)| i)
+                .collect();
+            assert_eq!(alive, vec![0, 1, 3, 5, 7, 8]);
+        }
+
+        #[test]
+        fn test_glider_wraps_on_torus() {
+            let width = 5;
+            let height = 5;
+            let cells = super::initialize_cells(width, height);
+            let mut universe = super::Universe {
+                width,
+                height,
+                cells,
+                boundary: super::Boundary::Torus,
+                ..Default::default()
+            };
+
+            for (x, y) in [(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)] {
+                super::set_cell_by_position(&mut universe.cells, width, x, y, super::Cell::Alive);
+            }
+            let initial_cells = universe.cells.clone();
+
+            // A glider moves one cell diagonally every 4 generations, so after
+            // 20 generations it is back where it started on a 5x5 torus.
+            for _ in 0..20 {
+                super::run_iteration(&mut universe);
             }
 
+            assert_eq!(universe.cells, initial_cells);
+        }
+
+        #[test]
+        fn test_rle_pattern_round_trip() {
+            let width = 5;
+            let height = 5;
+            let glider =
+                game_of_life_core::pattern::rle::parse("x = 3, y = 3\nbo$2bo$3o!").unwrap();
+            let mut cells = super::initialize_cells(width, height);
+            super::place_pattern(&mut cells, width, height, &glider, 1, 2);
+
+            assert_eq!(
+                *super::get_cell_by_position(&cells, width, 2, 2),
+                super::Cell::Alive
+            );
+            assert_eq!(
+                *super::get_cell_by_position(&cells, width, 1, 4),
+                super::Cell::Alive
+            );
+            assert_eq!(
+                cells
+                    .iter()
+                    .filter(|cell| **cell == super::Cell::Alive)
+                    .count(),
+                5
+            );
+
+            let filename = std::env::temp_dir().join("no_ecs_test_rle_pattern_round_trip.rle");
+            let filename = filename.to_str().unwrap();
+            super::save_cells_to_pattern_file(
+                &cells,
+                width,
+                height,
+                super::Rule::default(),
+                super::Topology::Square,
+                filename,
+            );
+            let saved = super::Pattern::load(filename).unwrap();
+            std::fs::remove_file(filename).unwrap();
+
+            assert_eq!(saved.width, width);
+            assert_eq!(saved.height, height);
+            assert_eq!(
+                saved.cells_at_offset(0, 0, width, height),
+                glider.cells_at_offset(1, 2, width, height)
+            );
+        }
+
+        #[test]
+        fn test_cells_dump_still_loads() {
+            let universe = load_universe("toad.cells");
+
+            let filename = std::env::temp_dir().join("no_ecs_test_cells_dump_still_loads.txt");
+            let filename = filename.to_str().unwrap();
+            super::save_cells_to_file(&universe.cells, universe.width, universe.height, filename);
+            let saved = load_universe_from_file(filename);
+            std::fs::remove_file(filename).unwrap();
+
+            assert_eq!(saved.width, universe.width);
+            assert_eq!(saved.height, universe.height);
+            assert_eq!(saved.cells, universe.cells);
+        }
+
+        #[test]
+        fn test_unbounded_glider_leaves_the_grid() {
+            let output = std::env::temp_dir().join("no_ecs_test_unbounded_glider.rle");
+            let config = super::Config {
+                width: 8,
+                height: 8,
+                generations: 64,
+                extent: super::Extent::Unbounded,
+                pattern: Some(fixture_path("glider.rle")),
+                output: Some(output.to_str().unwrap().to_string()),
+                ..Default::default()
+            };
+            super::run_simulation(&config, false);
+            let saved = Pattern::load(&output).unwrap();
+            std::fs::remove_file(&output).unwrap();
+
+            // 16 cells down and right, well past the 8x8 grid, and still a glider.
+            assert_eq!(saved, Pattern::load(fixture_path("glider.rle")).unwrap());
+        }
+
+        #[test]
+        fn test_stops_once_the_blinker_repeats() {
+            let output = std::env::temp_dir().join("no_ecs_test_blinker_stabilization.txt");
+            let config = super::Config {
+                width: 9,
+                height: 9,
+                generations: 100,
+                pattern: Some(fixture_path("blinker.cells")),
+                pattern_offset: (2, 2),
+                cycles: super::CycleDetection::Stop,
+                stabilization_output: Some(output.to_str().unwrap().to_string()),
+                ..Default::default()
+            };
+            super::run_simulation(&config, false);
+            let stabilization = super::cycle::load(output.to_str().unwrap()).unwrap();
+            std::fs::remove_file(&output).unwrap();
+
+            assert_eq!(
+                stabilization,
+                Some(super::Stabilization::Cycle {
+                    start: 0,
+                    period: 2
+                })
+            );
+        }
+
+        #[test]
+        fn test_stats_follow_the_blinker() {
+            let output = std::env::temp_dir().join("no_ecs_test_blinker_stats.csv");
+            let config = super::Config {
+                width: 9,
+                height: 9,
+                generations: 2,
+                pattern: Some(fixture_path("blinker.cells")),
+                pattern_offset: (2, 2),
+                stats_output: Some(output.to_str().unwrap().to_string()),
+                ..Default::default()
+            };
+            super::run_simulation(&config, false);
+            let csv = std::fs::read_to_string(&output).unwrap();
+            std::fs::remove_file(&output).unwrap();
+
+            let rows: Vec<Vec<&str>> = csv.lines().map(|line| line.split(',').collect()).collect();
+            assert_eq!(rows.len(), 4);
+            // generation, population, dying, births, deaths, changed and the bounds.
+            assert_eq!(
+                rows[1][..10],
+                ["0", "3", "0", "0", "0", "0", "4", "3", "4", "5"]
+            );
+            assert_eq!(
+                rows[2][..10],
+                ["1", "3", "0", "2", "2", "4", "3", "4", "5", "4"]
+            );
+            assert_eq!(rows[3][..6], ["2", "3", "0", "2", "2", "4"]);
+            assert!(!rows[2][10].is_empty());
+        }
+
+        #[test]
+        fn test_toad_pattern() {
+            let mut universe = load_universe("toad.cells");
+
             super::run_iteration(&mut universe);
+            assert_eq!(universe.cells, load_cells("toad_1.cells"));
 
-            let expected_cells = vec![
-                super::Cell::Dead,
-                super::Cell::Alive,
-                super::Cell::Alive,
-                super::Cell::Dead,
-                // first row
-                super::Cell::Alive,
-                super::Cell::Dead,
-                super::Cell::Dead,
-                super::Cell::Dead,
-                // second row
-                super::Cell::Dead,
-                super::Cell::Dead,
-                super::Cell::Dead,
-                super::Cell::Alive,
-                // third row
-                super::Cell::Dead,
-                super::Cell::Alive,
-                super::Cell::Alive,
-                super::Cell::Dead,
-                // fourth row
-            ];
-
-            for i in 0..(width * height) as usize {
-                assert_eq!(universe.cells[i], expected_cells[i]);
-            }
+            super::run_iteration(&mut universe);
+            assert_eq!(universe.cells, load_cells("toad.cells"));
         }
     }
 }
 
 fn main() {
     let args: Vec<String> = std::env::args().collect();
-    if args.len() < 4 {
-        println!("Usage: {} <width> <height> <iterations>", args[0]);
-        std::process::exit(1);
-    }
-
-    let width: u32 = args[1].parse().expect("Invalid width");
-    let height: u32 = args[2].parse().expect("Invalid height");
-    let iterations: u32 = args[3].parse().expect("Invalid iterations");
+    let config = match args.get(1) {
+        Some(arg) if !arg.starts_with('-') => parse_positional_args(&args),
+        _ => game_of_life_core::Config::default().parse_env_or_exit(),
+    };
 
-    let start = std::time::Instant::now();
-    game_of_life::run_simulation(width, height, iterations, false);
-    let duration = start.elapsed();
+    let duration = game_of_life::run_simulation(&config, false);
     println!(
         "Time elapsed in running the simulation ({} iterations, {} cells) is: {:?}",
-        iterations,
-        width * height,
+        config.generations,
+        config.width * config.height,
         duration
     );
+
+    if let Some(filename) = &config.total_time_output {
+        std::fs::write(filename, format!("{:?}", duration)).expect("Unable to write data");
+    }
+}
+
+/// The original `<width> <height> <iterations> [rule] [boundary] [pattern] [x] [y]` arguments.
+fn parse_positional_args(args: &[String]) -> game_of_life_core::Config {
+    if args.len() < 4 {
+        println!(
+            "Usage: {} <width> <height> <iterations> [rule] [boundary] [pattern.rle] [x] [y]",
+            args[0]
+        );
+        println!(
+            "   or: {} [options]\n\n{}",
+            args[0],
+            game_of_life_core::config::USAGE
+        );
+        std::process::exit(1);
+    }
+
+    let mut config = game_of_life_core::Config {
+        width: args[1].parse().expect("Invalid width"),
+        height: args[2].parse().expect("Invalid height"),
+        generations: args[3].parse().expect("Invalid iterations"),
+        pattern: args.get(6).cloned(),
+        ..Default::default()
+    };
+    if let Some(rule) = args.get(4) {
+        config.rule = rule.parse().expect("Invalid rule");
+    }
+    if let Some(boundary) = args.get(5) {
+        config.boundary = boundary.parse().expect("Invalid boundary");
+    }
+    if let Some(x) = args.get(7) {
+        config.pattern_offset.0 = x.parse().expect("Invalid x offset");
+    }
+    if let Some(y) = args.get(8) {
+        config.pattern_offset.1 = y.parse().expect("Invalid y offset");
+    }
+
+    config
 }
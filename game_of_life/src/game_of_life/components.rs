@@ -1,4 +1,8 @@
 use bevy::prelude::*;
+use game_of_life_core::{
+    rule::{ALIVE, DEAD},
+    sparse::Chunk,
+};
 
 #[derive(Component, PartialEq, Eq, Copy, Clone, Debug, Default)]
 pub struct Position {
@@ -6,21 +10,41 @@ pub struct Position {
     pub y: i32,
 }
 
+/// `DEAD`, `ALIVE`, or one of the dying states `2..states` of a Generations rule.
 #[derive(Component, PartialEq, Eq, Default)]
-pub struct State(pub bool);
+pub struct State(pub u8);
+
+impl State {
+    pub fn is_alive(&self) -> bool {
+        self.0 == ALIVE
+    }
+
+    /// Alive cells are green, dying cells fade from yellow to dark red as they count
+    /// down towards dead.
+    pub fn color(&self, states: u8) -> Color {
+        match self.0 {
+            DEAD => Color::BLACK,
+            ALIVE => Color::GREEN,
+            dying => {
+                let fade = (dying - 2) as f32 / states.saturating_sub(3).max(1) as f32;
+                Color::rgb(1.0 - 0.5 * fade, 1.0 - fade, 0.0)
+            }
+        }
+    }
+}
 
 impl std::fmt::Debug for State {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        if self.0 {
-            write!(f, "Alive")
-        } else {
-            write!(f, "Dead")
+        match self.0 {
+            DEAD => write!(f, "Dead"),
+            ALIVE => write!(f, "Alive"),
+            dying => write!(f, "Dying({})", dying),
         }
     }
 }
 
 #[derive(Component, Debug, Default, PartialEq, Eq)]
-pub struct Neighbors(pub u8);
+pub struct Neighbors(pub u16);
 
 #[derive(Bundle, Default)]
 pub struct CellBundle {
@@ -29,3 +53,13 @@ pub struct CellBundle {
     pub neighbors: Neighbors,
     pub sprite: SpriteBundle,
 }
+
+/// Position of a chunk of the unbounded universe, in units of `sparse::CHUNK_SIZE` cells.
+#[derive(Component, PartialEq, Eq, Copy, Clone, Debug, Default)]
+pub struct ChunkPosition {
+    pub x: i32,
+    pub y: i32,
+}
+
+#[derive(Component, PartialEq, Eq, Clone, Debug)]
+pub struct ChunkCells(pub Chunk);
@@ -0,0 +1,16 @@
+use bevy::prelude::*;
+use game_of_life_core::cycle::Stabilization;
+
+/// Sent once, the generation the run dies out or repeats a state still in the
+/// `--cycle-window`.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct Stabilized(pub Stabilization);
+
+/// Moves the `HistoryCursor`. The arrow keys and Home and End send these in a window,
+/// anything driving a headless run can send them directly.
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rewind {
+    StepBack,
+    StepForward,
+    JumpTo(u64),
+}
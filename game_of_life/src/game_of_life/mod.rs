@@ -1,55 +1,84 @@
-use std::{collections::HashMap, time::Instant};
+use std::time::Instant;
 
 use bevy::prelude::*;
+use game_of_life_core::{
+    config::{self, CycleDetection, NeighborCounting},
+    Config, Pattern, Snapshot,
+};
 use resources::{Durations, Generations, GlobalTime, SystemsMeasureTime};
 
-use self::resources::{CellPositions, CellsChanged, Grid, PlacementMode};
+use self::parallel::{Batching, SystemTimings, Tracing};
+use self::resources::{
+    ActivityLog, CellEntities, CellPositions, Chunks, Cycles, Extent, Grid, HistoryBudget,
+    HistoryCursor, InitialPattern, MaxGenerations, NeighborOffsets, OutputFiles, PlacementMode,
+    ResumedSnapshot, Rule, Soup,
+};
 
 mod components;
+mod events;
+mod parallel;
 mod resources;
 mod systems;
 mod utils;
 
-pub struct GameOfLifePlugin;
+#[derive(Default)]
+pub struct GameOfLifePlugin {
+    pub config: Config,
+}
 
 impl Plugin for GameOfLifePlugin {
     fn build(&self, app: &mut App) {
+        // A resumed run takes its grid and rule from the snapshot and carries on counting
+        // generations from where the snapshot was taken.
+        let mut config = self.config.clone();
+        let resumed = self.config.resume.as_ref().map(|path| {
+            let snapshot = Snapshot::load(path)
+                .unwrap_or_else(|error| panic!("Unable to load snapshot {}: {}", path, error));
+            snapshot.configure(&mut config);
+            snapshot
+        });
+        let config = &config;
+        let start = resumed.as_ref().map_or(0, |snapshot| snapshot.generation);
+
         app.add_state::<SimulationState>()
             .insert_resource(ClearColor(Color::rgb(0.0, 0.0, 0.0)))
             .insert_resource(Grid {
-                width: 600,
-                height: 400,
+                width: config.width,
+                height: config.height,
+                boundary: config.boundary,
+                topology: config.topology,
+                neighborhood: config.neighborhood.clone(),
             })
-            .insert_resource(CellsChanged(true))
-            .insert_resource(CellPositions {
-                map: HashMap::new(),
+            .insert_resource(Extent(config.extent))
+            .insert_resource(Rule(config.rule))
+            .insert_resource(Batching(config.batching))
+            .insert_resource(NeighborOffsets(
+                config.topology.stencil(&config.neighborhood),
+            ))
+            .insert_resource(MaxGenerations(start + config.generations))
+            .insert_resource(Soup(config.soup()))
+            .insert_resource(OutputFiles {
+                durations: config.durations_output.clone(),
+                total_time: config.total_time_output.clone(),
+                pattern: config.output.clone(),
+                stabilization: config.stabilization_output.clone(),
+                stats: config.stats_output.clone(),
+                snapshot: config.snapshot_output.clone(),
+                system_timings: config.system_timings_output.clone(),
+                trace: config.trace_output.clone(),
             })
+            .insert_resource(CellPositions::new(
+                config.cell_index,
+                config.width,
+                config.height,
+            ))
             .insert_resource(PlacementMode::Single)
             .insert_resource(Durations(Vec::new()))
             .insert_resource(SystemsMeasureTime(Instant::now()))
             .insert_resource(GlobalTime(Instant::now()))
-            .insert_resource(Generations(0))
-            .add_systems(
-                Startup,
-                (
-                    systems::spawn_cells_without_graphic,
-                    systems::initialize.before(systems::spawn_cells),
-                ),
-            )
-            .add_systems(
-                Update,
-                (
-                    //systems::start_measurement,
-                    systems::rebuild_cell_positions,
-                    systems::update_neighbors_brute_force_system,
-                    systems::update_cells_system,
-                    systems::rebuild_cell_positions,
-                    systems::update_neighbors_brute_force_system,
-                    //systems::stop_measurement,
-                )
-                    .chain()
-                    .run_if(in_state(SimulationState::Running)),
-            )
+            .insert_resource(Generations(start))
+            .add_systems(Startup, systems::initialize.before(systems::spawn_cells))
+            .add_systems(First, systems::start_clock_system.run_if(run_once()))
             .add_systems(
                 Update,
                 (
@@ -58,9 +87,149 @@ impl Plugin for GameOfLifePlugin {
                     // systems::handle_cell_click_system,
                     // systems::toggle_simulation_system,
                     // systems::do_one_step_system,
-                    systems::exit_after_n_generations_system,
+                    systems::exit_after_n_generations_system.after(systems::detect_cycles_system),
                 ),
+            )
+            .add_event::<events::Stabilized>();
+
+        // Generation 0 is checked once the cells spawned at startup exist, every later
+        // one at the end of the step that produced it.
+        if let Some(detector) = config.cycle_detector() {
+            app.insert_resource(Cycles {
+                detector,
+                stop: config.cycles == CycleDetection::Stop,
+            })
+            .add_systems(PostStartup, systems::detect_cycles_system)
+            .add_systems(
+                Update,
+                systems::detect_cycles_system
+                    .after(systems::stop_measurement)
+                    .run_if(in_state(SimulationState::Running)),
             );
+        }
+
+        match config.extent {
+            config::Extent::Bounded => {
+                let resuming = resumed.is_some();
+                match resumed {
+                    Some(snapshot) => app
+                        .insert_resource(ResumedSnapshot(snapshot))
+                        .add_systems(Startup, systems::spawn_cells_from_snapshot),
+                    None => app.add_systems(Startup, systems::spawn_cells_without_graphic),
+                };
+                match config.neighbor_counting {
+                    NeighborCounting::BruteForce => app.add_systems(
+                        Update,
+                        (
+                            systems::start_measurement,
+                            systems::sync_cell_positions,
+                            systems::update_neighbors_brute_force_system,
+                            systems::update_cells_system,
+                            systems::stop_measurement,
+                        )
+                            .chain()
+                            .run_if(in_state(SimulationState::Running)),
+                    ),
+                    NeighborCounting::Incremental => app
+                        .insert_resource(CellEntities::new(config.width, config.height))
+                        .add_systems(
+                            Update,
+                            (
+                                systems::start_measurement,
+                                systems::update_neighbors_incremental_system,
+                                systems::sync_cell_positions,
+                                // A snapshot keeps the counts the generation it saved was
+                                // stepped from, which the incremental counts cannot carry
+                                // on from, so the first step after resuming recounts them.
+                                systems::update_neighbors_brute_force_system
+                                    .run_if(move || resuming)
+                                    .run_if(run_once()),
+                                systems::update_cells_system,
+                                systems::stop_measurement,
+                            )
+                                .chain()
+                                .run_if(in_state(SimulationState::Running)),
+                        ),
+                };
+            }
+            config::Extent::Unbounded => {
+                app.init_resource::<Chunks>()
+                    .add_systems(Startup, systems::spawn_chunks_without_graphic)
+                    .add_systems(
+                        Update,
+                        (
+                            systems::start_measurement,
+                            systems::sync_chunks_system,
+                            apply_deferred,
+                            systems::update_chunks_system,
+                            systems::despawn_empty_chunks_system,
+                            apply_deferred,
+                            systems::stop_measurement,
+                        )
+                            .chain()
+                            .run_if(in_state(SimulationState::Running)),
+                    );
+            }
+        }
+
+        if config.stats_output.is_some() {
+            let record_stats = match config.extent {
+                config::Extent::Bounded => systems::record_stats_system.into_configs(),
+                config::Extent::Unbounded => systems::record_chunk_stats_system.into_configs(),
+            };
+            app.init_resource::<ActivityLog>()
+                .add_systems(PostStartup, systems::record_initial_stats_system)
+                .add_systems(
+                    Update,
+                    record_stats
+                        .after(systems::stop_measurement)
+                        .before(systems::exit_after_n_generations_system)
+                        .run_if(in_state(SimulationState::Running)),
+                );
+        }
+
+        if config.system_timings_output.is_some() {
+            app.init_resource::<SystemTimings>();
+        }
+        if config.trace_output.is_some() {
+            app.init_resource::<Tracing>();
+        }
+        parallel::set_executor(app, config.executor);
+
+        // Rewinding pauses the run on the generation it lands on. Stepping on from there
+        // replaces the generations that came after it.
+        if let Some(budget) = config.history_budget {
+            app.insert_resource(HistoryBudget(budget))
+                .insert_resource(HistoryCursor {
+                    generation: start,
+                    target: None,
+                })
+                .add_event::<events::Rewind>()
+                .add_systems(PostStartup, systems::start_history_system)
+                .add_systems(
+                    Update,
+                    (
+                        systems::record_history_system
+                            .after(systems::stop_measurement)
+                            .run_if(in_state(SimulationState::Running)),
+                        systems::history_controls_system,
+                        systems::rewind_system
+                            .after(systems::record_history_system)
+                            .after(systems::history_controls_system)
+                            .before(systems::exit_after_n_generations_system),
+                    ),
+                );
+        }
+
+        if let Some(path) = config.pattern.as_ref().filter(|_| config.resume.is_none()) {
+            let pattern = Pattern::load(path)
+                .unwrap_or_else(|error| panic!("Unable to load pattern {}: {}", path, error));
+            app.insert_resource(InitialPattern {
+                pattern,
+                offset_x: config.pattern_offset.0,
+                offset_y: config.pattern_offset.1,
+            });
+        }
     }
 }
 
@@ -71,3 +240,176 @@ pub enum SimulationState {
     Running,
     Exit,
 }
+
+#[cfg(test)]
+mod tests {
+    use game_of_life_core::Boundary;
+
+    use super::components::{Neighbors, Position, State};
+    use super::events::Rewind;
+    use super::resources::History;
+    use super::utils::save_cells_to_snapshot_file;
+    use super::*;
+
+    /// A small seeded soup that would run far longer than any test steps it, so the exit
+    /// system never ends the test process.
+    fn soup_config() -> Config {
+        Config {
+            width: 24,
+            height: 16,
+            seed: Some(7),
+            generations: u64::MAX / 2,
+            ..default()
+        }
+    }
+
+    fn app(config: Config) -> App {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .add_plugins(GameOfLifePlugin { config });
+        app
+    }
+
+    /// Every cell's position, state and neighbour count, in row order.
+    fn cells(app: &mut App) -> Vec<(i32, i32, u8, u16)> {
+        let mut cells: Vec<_> = app
+            .world
+            .query::<(&Position, &State, &Neighbors)>()
+            .iter(&app.world)
+            .map(|(position, state, neighbors)| (position.y, position.x, state.0, neighbors.0))
+            .collect();
+        cells.sort_unstable();
+        cells
+    }
+
+    fn states(app: &mut App) -> Vec<u8> {
+        cells(app)
+            .into_iter()
+            .map(|(_, _, state, _)| state)
+            .collect()
+    }
+
+    fn generation(app: &App) -> u64 {
+        app.world.resource::<Generations>().0
+    }
+
+    /// Sends `rewind` and runs the frame that moves the cells to where it points.
+    fn rewind(app: &mut App, rewind: Rewind) {
+        app.world.send_event(rewind);
+        app.update();
+    }
+
+    #[test]
+    fn test_rewind_restores_recorded_generations() {
+        let mut app = app(Config {
+            history_budget: Some(1 << 20),
+            ..soup_config()
+        });
+        app.update();
+        let mut recorded = vec![states(&mut app)];
+        for _ in 0..5 {
+            app.update();
+            recorded.push(states(&mut app));
+        }
+        assert_eq!(generation(&app), 6);
+        assert!(recorded.windows(2).any(|pair| pair[0] != pair[1]));
+
+        // The frame the rewind arrives in still steps to generation 7 first.
+        rewind(&mut app, Rewind::JumpTo(2));
+        assert_eq!(generation(&app), 2);
+        assert_eq!(app.world.resource::<History>().0.last(), 7);
+        assert_eq!(states(&mut app), recorded[1]);
+
+        rewind(&mut app, Rewind::StepBack);
+        assert_eq!(generation(&app), 1);
+        assert_eq!(states(&mut app), recorded[0]);
+
+        rewind(&mut app, Rewind::StepForward);
+        rewind(&mut app, Rewind::StepForward);
+        assert_eq!(generation(&app), 3);
+        assert_eq!(states(&mut app), recorded[2]);
+
+        // Running on from generation 3 replays the same generations and drops the ones
+        // recorded after it.
+        app.world
+            .insert_resource(NextState(Some(SimulationState::Running)));
+        for expected in &recorded[3..] {
+            app.update();
+            assert_eq!(&states(&mut app), expected);
+        }
+        assert_eq!(generation(&app), 6);
+        assert_eq!(app.world.resource::<History>().0.last(), 6);
+
+        // Generation 0 is the soup recorded at startup, which steps to generation 1 again.
+        rewind(&mut app, Rewind::JumpTo(0));
+        assert_eq!(generation(&app), 0);
+        app.world
+            .insert_resource(NextState(Some(SimulationState::Running)));
+        app.update();
+        assert_eq!(states(&mut app), recorded[0]);
+    }
+
+    #[test]
+    fn test_incremental_resume_keeps_saved_counts() {
+        let filename = std::env::temp_dir().join("ecs_test_incremental_resume.snap");
+        let filename = filename.to_str().unwrap().to_string();
+        let incremental = |resume| Config {
+            neighbor_counting: NeighborCounting::Incremental,
+            resume,
+            ..soup_config()
+        };
+
+        let mut saved = app(incremental(None));
+        for _ in 0..5 {
+            saved.update();
+        }
+        let mut query = saved.world.query::<(&Position, &State, &Neighbors)>();
+        save_cells_to_snapshot_file(
+            query.iter(&saved.world),
+            saved.world.resource::<Grid>(),
+            saved.world.resource::<Rule>(),
+            generation(&saved),
+            &filename,
+        );
+
+        // Startup alone spawns the saved cells without stepping them.
+        let mut resumed = app(incremental(Some(filename.clone())));
+        resumed.world.run_schedule(Startup);
+        assert_eq!(cells(&mut resumed), cells(&mut saved));
+
+        let mut resumed = app(incremental(Some(filename.clone())));
+        for _ in 0..4 {
+            saved.update();
+            resumed.update();
+            assert_eq!(generation(&resumed), generation(&saved));
+            assert_eq!(cells(&mut resumed), cells(&mut saved));
+        }
+        std::fs::remove_file(filename).unwrap();
+    }
+
+    #[test]
+    fn test_incremental_counting_matches_brute_force() {
+        for boundary in [Boundary::Torus, Boundary::Mirror] {
+            let config = |neighbor_counting| Config {
+                boundary,
+                neighbor_counting,
+                ..soup_config()
+            };
+            let mut brute_force = app(config(NeighborCounting::BruteForce));
+            let mut incremental = app(config(NeighborCounting::Incremental));
+
+            for generation in 1..=12 {
+                brute_force.update();
+                incremental.update();
+                assert_eq!(incremental.world.resource::<Generations>().0, generation);
+                assert_eq!(
+                    cells(&mut incremental),
+                    cells(&mut brute_force),
+                    "generation {} on the {:?} boundary",
+                    generation,
+                    boundary
+                );
+            }
+        }
+    }
+}
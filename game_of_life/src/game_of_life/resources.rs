@@ -1,4 +1,5 @@
 use bevy::prelude::*;
+use game_of_life_core::{config::CellIndex, sparse::Chunk, topology::Stencil};
 use std::{
     collections::HashMap,
     time::{Duration, Instant},
@@ -8,18 +9,170 @@ use std::{
 pub struct Grid {
     pub width: u32,
     pub height: u32,
+    pub boundary: game_of_life_core::Boundary,
+    pub topology: game_of_life_core::Topology,
+    pub neighborhood: game_of_life_core::Neighborhood,
 }
 
 #[derive(Resource)]
-pub struct Generations(pub u32);
+pub struct Generations(pub u64);
 
 #[derive(Resource)]
-pub struct CellPositions {
-    pub map: HashMap<(i32, i32), bool>,
+pub struct MaxGenerations(pub u64);
+
+#[derive(Resource, Debug, Clone, Copy)]
+pub struct Rule(pub game_of_life_core::Rule);
+
+/// The weighted offsets of the configured neighbourhood on the grid's topology, worked
+/// out once at startup.
+#[derive(Resource, Debug, Clone)]
+pub struct NeighborOffsets(pub Stencil);
+
+#[derive(Resource)]
+pub struct InitialPattern {
+    pub pattern: game_of_life_core::Pattern,
+    pub offset_x: i32,
+    pub offset_y: i32,
+}
+
+/// The snapshot a `--resume` run starts from, spawned instead of the soup or pattern.
+#[derive(Resource)]
+pub struct ResumedSnapshot(pub game_of_life_core::Snapshot);
+
+#[derive(Resource, Debug, Clone, Copy)]
+pub struct Soup(pub game_of_life_core::Soup);
+
+#[derive(Resource)]
+pub struct OutputFiles {
+    pub durations: Option<String>,
+    pub total_time: Option<String>,
+    pub pattern: Option<String>,
+    pub stabilization: Option<String>,
+    pub stats: Option<String>,
+    pub snapshot: Option<String>,
+    pub system_timings: Option<String>,
+    pub trace: Option<String>,
+}
+
+/// One record per generation, only inserted when `--stats-output` is given.
+#[derive(Resource, Default)]
+pub struct ActivityLog(pub Vec<game_of_life_core::stats::GenerationStats>);
+
+/// Bytes of past generations to keep, only inserted when `--history-budget` is given.
+#[derive(Resource, Debug, Clone, Copy)]
+pub struct HistoryBudget(pub usize);
+
+/// Past generations to rewind through, started once the cells are spawned.
+#[derive(Resource)]
+pub struct History(pub game_of_life_core::history::History);
+
+/// The generation the cells are showing, and the one to move them to next.
+#[derive(Resource, Debug, Default)]
+pub struct HistoryCursor {
+    pub generation: u64,
+    pub target: Option<u64>,
+}
+
+impl HistoryCursor {
+    pub fn step_back(&mut self) {
+        let from = self.target.unwrap_or(self.generation);
+        self.target = Some(from.saturating_sub(1));
+    }
+
+    pub fn step_forward(&mut self) {
+        let from = self.target.unwrap_or(self.generation);
+        self.target = Some(from + 1);
+    }
+
+    pub fn jump_to(&mut self, generation: u64) {
+        self.target = Some(generation);
+    }
 }
 
+/// Only inserted when `--cycles` is on.
 #[derive(Resource)]
-pub struct CellsChanged(pub bool);
+pub struct Cycles {
+    pub detector: game_of_life_core::cycle::CycleDetector,
+    /// Whether the run exits once it has stabilised.
+    pub stop: bool,
+}
+
+/// The front buffer: every cell's state as of the previous generation, read while the
+/// `State` components (the back buffer) are rewritten in parallel.
+#[derive(Resource)]
+pub enum CellPositions {
+    Map(HashMap<(i32, i32), bool>),
+    /// One byte per cell at `y * width + x`, so lookups skip hashing entirely.
+    Dense {
+        width: u32,
+        cells: Vec<u8>,
+    },
+}
+
+impl CellPositions {
+    pub fn new(index: CellIndex, width: u32, height: u32) -> Self {
+        match index {
+            CellIndex::Map => CellPositions::Map(HashMap::new()),
+            CellIndex::Dense => CellPositions::Dense {
+                width,
+                cells: vec![0; (width * height) as usize],
+            },
+        }
+    }
+
+    /// Whether the cell at an on-grid position is alive.
+    pub fn is_alive(&self, x: i32, y: i32) -> bool {
+        match self {
+            CellPositions::Map(map) => map.get(&(x, y)).copied().unwrap_or(false),
+            CellPositions::Dense { width, cells } => {
+                cells[(y as u32 * width + x as u32) as usize] != 0
+            }
+        }
+    }
+
+    pub fn set(&mut self, x: i32, y: i32, alive: bool) {
+        match self {
+            CellPositions::Map(map) => {
+                map.insert((x, y), alive);
+            }
+            CellPositions::Dense { width, cells } => {
+                cells[(y as u32 * *width + x as u32) as usize] = alive as u8;
+            }
+        }
+    }
+}
+
+/// The entity of every cell at `y * width + x`, used to push incremental neighbour counts.
+#[derive(Resource)]
+pub struct CellEntities {
+    pub width: u32,
+    pub entities: Vec<Entity>,
+}
+
+impl CellEntities {
+    pub fn new(width: u32, height: u32) -> Self {
+        CellEntities {
+            width,
+            entities: vec![Entity::PLACEHOLDER; (width * height) as usize],
+        }
+    }
+
+    pub fn get(&self, x: i32, y: i32) -> Entity {
+        self.entities[(y as u32 * self.width + x as u32) as usize]
+    }
+
+    pub fn set(&mut self, x: i32, y: i32, entity: Entity) {
+        self.entities[(y as u32 * self.width + x as u32) as usize] = entity;
+    }
+}
+
+/// The front buffer of the unbounded universe: every chunk as of the previous
+/// generation, read while the `ChunkCells` components are rewritten in parallel.
+#[derive(Resource, Default)]
+pub struct Chunks(pub HashMap<(i32, i32), Chunk>);
+
+#[derive(Resource, Debug, Clone, Copy)]
+pub struct Extent(pub game_of_life_core::config::Extent);
 
 #[derive(Resource)]
 pub struct CellMaterials {
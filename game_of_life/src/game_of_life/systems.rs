@@ -1,42 +1,68 @@
 use bevy::input::mouse::MouseWheel;
 use bevy::prelude::*;
 use bevy::window::PrimaryWindow;
+use game_of_life_core::{
+    config, cycle, history,
+    rule::DEAD,
+    sparse::{self, EMPTY_CHUNK},
+    stats::{self, GenerationStats},
+    Topology,
+};
 use rand::Rng;
 use std::time::Instant;
 
-use crate::game_of_life::utils::save_durations_to_file;
+use crate::game_of_life::utils::{
+    cell_states, save_cells_to_pattern_file, save_cells_to_snapshot_file,
+    save_chunks_to_pattern_file, save_durations_to_file,
+};
 
-use super::components::{CellBundle, Neighbors, Position};
+use super::components::{CellBundle, ChunkCells, ChunkPosition, Neighbors, Position};
+use super::events::{Rewind, Stabilized};
+use super::parallel::{Batching, Instruments, SystemTimings, Tracing};
 use super::resources::{
-    CellPositions, CellsChanged, Durations, Generations, GlobalTime, Grid, PlacementMode,
-    SystemsMeasureTime,
+    ActivityLog, CellEntities, CellPositions, Chunks, Cycles, Durations, Extent, Generations,
+    GlobalTime, Grid, History, HistoryBudget, HistoryCursor, InitialPattern, MaxGenerations,
+    NeighborOffsets, OutputFiles, PlacementMode, ResumedSnapshot, Rule, Soup, SystemsMeasureTime,
 };
 use super::SimulationState;
 
 use super::components;
 
-pub fn rebuild_cell_positions(
-    query: Query<(&Position, &components::State)>,
+/// Copies the states written by the last generation into the `CellPositions` front buffer.
+///
+/// Only cells whose `State` changed are visited and they are overwritten in place, so a
+/// still grid costs close to nothing.
+pub fn sync_cell_positions(
+    query: Query<(&Position, &components::State), Changed<components::State>>,
     mut cell_positions: ResMut<CellPositions>,
-    mut cells_changed: ResMut<CellsChanged>,
+    mut instruments: Instruments,
 ) {
-    if !cells_changed.0 {
-        return;
-    }
-
-    cell_positions.map.clear();
+    let start = Instant::now();
     for (pos, state) in query.iter() {
-        cell_positions.map.insert((pos.x, pos.y), state.0);
+        cell_positions.set(pos.x, pos.y, state.is_alive());
     }
-
-    cells_changed.0 = false;
+    instruments.finish("sync_cell_positions", start);
 }
 
-pub fn spawn_cells_without_graphic(mut commands: Commands, grid: Res<Grid>) {
+pub fn spawn_cells_without_graphic(
+    mut commands: Commands,
+    grid: Res<Grid>,
+    soup: Res<Soup>,
+    initial_pattern: Option<Res<InitialPattern>>,
+) {
     let start = Instant::now();
-    let width = grid.width.clone();
+    let width = grid.width;
     let height = grid.height;
     let cells_to_spawn_count = width * height;
+    let pattern_cells = initial_pattern.map(|initial| {
+        initial
+            .pattern
+            .cells_at_offset(initial.offset_x, initial.offset_y, width, height)
+    });
+    if pattern_cells.is_none() {
+        println!("Soup seed: {}", soup.0.seed);
+    }
+    let soup = soup.0;
     let to_spawn = (0..cells_to_spawn_count).map(move |i| {
         let x = i % width;
         let y = i / width;
@@ -44,8 +70,10 @@ pub fn spawn_cells_without_graphic(mut commands: Commands, grid: Res<Grid>) {
             x: x as i32,
             y: y as i32,
         };
-        let mut rng = rand::thread_rng();
-        let state = components::State(rng.gen_bool(0.5));
+        let state = match &pattern_cells {
+            Some(cells) => components::State(cells.contains(&(position.x, position.y)) as u8),
+            None => components::State(soup.is_alive(x, y) as u8),
+        };
         (position, state, Neighbors(0))
     });
 
@@ -57,12 +85,97 @@ pub fn spawn_cells_without_graphic(mut commands: Commands, grid: Res<Grid>) {
     commands.insert_resource(NextState(Some(SimulationState::Running)));
 }
 
-pub fn spawn_cells(mut commands: Commands, grid: Res<Grid>, asset_server: Res<AssetServer>) {
-    let start = Instant::now();
-    let width = grid.width.clone();
+/// Spawns the cells of a `--resume` snapshot with the states and neighbour counts it
+/// was saved with.
+pub fn spawn_cells_from_snapshot(
+    mut commands: Commands,
+    grid: Res<Grid>,
+    snapshot: Res<ResumedSnapshot>,
+) {
+    let width = grid.width;
+    println!("Resuming from generation {}", snapshot.0.generation);
+    let to_spawn =
+        snapshot
+            .0
+            .cells
+            .clone()
+            .into_iter()
+            .enumerate()
+            .map(move |(i, (state, neighbors))| {
+                let position = Position {
+                    x: (i as u32 % width) as i32,
+                    y: (i as u32 / width) as i32,
+                };
+                (position, components::State(state), Neighbors(neighbors))
+            });
+    commands.spawn_batch(to_spawn);
+    commands.remove_resource::<ResumedSnapshot>();
+
+    commands.insert_resource(NextState(Some(SimulationState::Running)));
+}
+
+/// Spawns one entity per chunk of the unbounded universe holding alive cells. The
+/// pattern is placed without clipping, only the soup is limited to the grid size.
+pub fn spawn_chunks_without_graphic(
+    mut commands: Commands,
+    grid: Res<Grid>,
+    soup: Res<Soup>,
+    initial_pattern: Option<Res<InitialPattern>>,
+) {
+    let alive_cells: Vec<(i32, i32)> = match initial_pattern {
+        Some(initial) => initial
+            .pattern
+            .cells
+            .iter()
+            .map(|(x, y)| (*x as i32 + initial.offset_x, *y as i32 + initial.offset_y))
+            .collect(),
+        None => {
+            println!("Soup seed: {}", soup.0.seed);
+            soup.0
+                .alive_cells(grid.width, grid.height)
+                .map(|(x, y)| (x as i32, y as i32))
+                .collect()
+        }
+    };
+
+    let mut chunks = Chunks::default();
+    for (x, y) in alive_cells {
+        let (key, (x, y)) = sparse::split(x, y);
+        chunks.0.entry(key).or_insert(EMPTY_CHUNK)[y] |= 1 << x;
+    }
+    commands.spawn_batch(
+        chunks
+            .0
+            .into_iter()
+            .map(|((x, y), cells)| (ChunkPosition { x, y }, ChunkCells(cells))),
+    );
+
+    commands.insert_resource(NextState(Some(SimulationState::Running)));
+}
+
+pub fn spawn_cells(
+    mut commands: Commands,
+    grid: Res<Grid>,
+    asset_server: Res<AssetServer>,
+    soup: Res<Soup>,
+    rule: Res<Rule>,
+    initial_pattern: Option<Res<InitialPattern>>,
+) {
+    let states = rule.0.states();
+    let topology = grid.topology;
+    let width = grid.width;
     let height = grid.height;
     let cells_to_spawn_count = width * height;
     let texture: Handle<Image> = asset_server.load("cell.png");
+    let pattern_cells = initial_pattern.map(|initial| {
+        initial
+            .pattern
+            .cells_at_offset(initial.offset_x, initial.offset_y, width, height)
+    });
+    if pattern_cells.is_none() {
+        println!("Soup seed: {}", soup.0.seed);
+    }
+    let soup = soup.0;
     let to_spawn = (0..cells_to_spawn_count).map(move |i| {
         let x = i % width;
         let y = i / width;
@@ -70,15 +183,25 @@ pub fn spawn_cells(mut commands: Commands, grid: Res<Grid>, asset_server: Res<As
             x: x as i32,
             y: y as i32,
         };
-        let mut rng = rand::thread_rng();
-        let state = components::State(rng.gen_bool(0.5));
+        let state = match &pattern_cells {
+            Some(cells) => components::State(cells.contains(&(position.x, position.y)) as u8),
+            None => components::State(soup.is_alive(x, y) as u8),
+        };
+        // Hexagon and triangle rows are offset from one another, triangles being half a
+        // cell wide.
+        let (center_x, center_y) = topology.center(position.x, position.y);
+        let scale = match topology {
+            Topology::Triangular => Vec3::new(0.5, 1.0, 1.0),
+            _ => Vec3::ONE,
+        };
         let sprite = SpriteBundle {
             sprite: Sprite {
-                color: if state.0 { Color::GREEN } else { Color::BLACK },
+                color: state.color(states),
                 ..default()
             },
             texture: texture.clone(),
-            transform: Transform::from_translation(Vec3::new(x as f32, y as f32, 0.0)),
+            transform: Transform::from_translation(Vec3::new(center_x, center_y, 0.0))
+                .with_scale(scale),
             ..default()
         };
         (CellBundle {
@@ -90,9 +213,6 @@ pub fn spawn_cells(mut commands: Commands, grid: Res<Grid>, asset_server: Res<As
     });
 
     commands.spawn_batch(to_spawn);
-    //println!("Spawning {:?} cells", cells_to_spawn_count);
-    let duration = start.elapsed();
-    //println!("Spawning cells took {:?}", duration);
 }
 
 pub fn initialize(mut commands: Commands) {
@@ -104,67 +224,176 @@ pub fn initialize(mut commands: Commands) {
 pub fn update_neighbors_brute_force_system(
     mut query: Query<(&mut Neighbors, &Position)>,
     grid: Res<Grid>,
+    offsets: Res<NeighborOffsets>,
     cell_positions: Res<CellPositions>,
+    batching: Res<Batching>,
+    mut instruments: Instruments,
 ) {
     let start = Instant::now();
 
-    query.par_iter_mut().for_each(|(mut neighbors, pos)| {
-        let mut count = 0;
-        for dx in -1..=1 {
-            for dy in -1..=1 {
-                if dx == 0 && dy == 0 {
-                    continue;
-                }
-
-                let x = pos.x + dx;
-                let y = pos.y + dy;
-
-                if x >= 0 && x < (grid.width as i32) && y >= 0 && y < (grid.height as i32) {
-                    if let Some(state) = cell_positions.map.get(&(x, y)) {
-                        if *state {
-                            count += 1;
+    let batch = instruments.batch("update_neighbors_brute_force_system");
+    query
+        .par_iter_mut()
+        .batching_strategy(batching.strategy())
+        .for_each(
+            batch.wrap(|(mut neighbors, pos): (Mut<Neighbors>, &Position)| {
+                let mut count = 0;
+                for ((dx, dy), weight) in offsets.0.at(pos.x, pos.y) {
+                    let neighbor =
+                        grid.boundary
+                            .resolve(pos.x + dx, pos.y + dy, grid.width, grid.height);
+
+                    if let Some((x, y)) = neighbor {
+                        if cell_positions.is_alive(x, y) {
+                            count += weight;
                         }
                     }
                 }
-            }
 
-            neighbors.0 = count;
+                neighbors.0 = count;
+            }),
+        );
+
+    instruments.finish("update_neighbors_brute_force_system", start);
+}
+
+/// Keeps `Neighbors` up to date from the cells whose `State` changed last generation, so
+/// the work follows the activity instead of the grid size. Must run before
+/// `sync_cell_positions`, the front buffer still holds the old states it diffs against.
+#[allow(clippy::too_many_arguments)]
+pub fn update_neighbors_incremental_system(
+    added: Query<(Entity, &Position), Added<Position>>,
+    changed: Query<(&Position, &components::State), Changed<components::State>>,
+    mut neighbors: Query<&mut Neighbors>,
+    mut cell_entities: ResMut<CellEntities>,
+    grid: Res<Grid>,
+    offsets: Res<NeighborOffsets>,
+    cell_positions: Res<CellPositions>,
+    mut instruments: Instruments,
+) {
+    let start = Instant::now();
+    for (entity, pos) in added.iter() {
+        cell_entities.set(pos.x, pos.y, entity);
+    }
+
+    for (pos, state) in changed.iter() {
+        let delta = state.is_alive() as i16 - cell_positions.is_alive(pos.x, pos.y) as i16;
+        if delta == 0 {
+            continue;
         }
-    });
 
-    let duration = start.elapsed();
-    // println!("Updating neighbors took {:?}", duration);
+        for ((x, y), weight) in
+            grid.boundary
+                .counted_by(pos.x, pos.y, grid.width, grid.height, &offsets.0)
+        {
+            if let Ok(mut neighbors) = neighbors.get_mut(cell_entities.get(x, y)) {
+                neighbors.0 = neighbors.0.wrapping_add_signed(delta * weight as i16);
+            }
+        }
+    }
+    instruments.finish("update_neighbors_incremental_system", start);
 }
 
+/// Applies the rule to every cell in parallel. Neighbour counts were taken from the
+/// `CellPositions` front buffer, so writing `State` here cannot affect other cells.
 pub fn update_cells_system(
     mut query: Query<(&mut components::State, &Neighbors, Option<&mut Sprite>)>,
-    mut cells_changed: ResMut<CellsChanged>,
     mut generations: ResMut<Generations>,
+    rule: Res<Rule>,
+    batching: Res<Batching>,
+    mut instruments: Instruments,
 ) {
-    for (mut state, neighbors, mut sprite) in query.iter_mut() {
-        let previous_state = state.0;
-        match (state.0, neighbors.0) {
-            (true, 2) | (true, 3) => (),
-            (false, 3) => {
-                state.0 = true;
-                if let Some(sprite) = sprite.as_mut() {
-                    sprite.color = Color::GREEN;
-                }
-            }
-            _ => {
-                state.0 = false;
+    let start = Instant::now();
+    let states = rule.0.states();
+    let batch = instruments.batch("update_cells_system");
+    query
+        .par_iter_mut()
+        .batching_strategy(batching.strategy())
+        .for_each(batch.wrap(
+            |(mut state, neighbors, mut sprite): (
+                Mut<components::State>,
+                &Neighbors,
+                Option<Mut<Sprite>>,
+            )| {
+                let next = components::State(rule.0.next(state.0, neighbors.0));
+
                 if let Some(sprite) = sprite.as_mut() {
-                    sprite.color = Color::BLACK;
+                    if *state != next {
+                        sprite.color = next.color(states);
+                    }
                 }
-            }
-        }
+                state.set_if_neq(next);
+            },
+        ));
+
+    generations.0 += 1;
+    instruments.finish("update_cells_system", start);
+}
 
-        if state.0 != previous_state {
-            cells_changed.0 = true;
+/// Copies every chunk into the `Chunks` front buffer and spawns empty chunk entities
+/// wherever activity is about to spill over a chunk edge.
+pub fn sync_chunks_system(
+    mut commands: Commands,
+    query: Query<(&ChunkPosition, &ChunkCells)>,
+    mut chunks: ResMut<Chunks>,
+    mut instruments: Instruments,
+) {
+    let start = Instant::now();
+    chunks.0.clear();
+    chunks.0.extend(
+        query
+            .iter()
+            .map(|(position, cells)| ((position.x, position.y), cells.0)),
+    );
+
+    for (x, y) in sparse::active_keys(chunks.0.iter()) {
+        if !chunks.0.contains_key(&(x, y)) {
+            commands.spawn((ChunkPosition { x, y }, ChunkCells(EMPTY_CHUNK)));
         }
     }
+    instruments.finish("sync_chunks_system", start);
+}
+
+/// Steps every chunk in parallel against the `Chunks` front buffer.
+pub fn update_chunks_system(
+    mut query: Query<(&ChunkPosition, &mut ChunkCells)>,
+    mut generations: ResMut<Generations>,
+    chunks: Res<Chunks>,
+    rule: Res<Rule>,
+    batching: Res<Batching>,
+    mut instruments: Instruments,
+) {
+    let start = Instant::now();
+    let chunk_at = |key| chunks.0.get(&key).copied().unwrap_or(EMPTY_CHUNK);
+    let batch = instruments.batch("update_chunks_system");
+    query
+        .par_iter_mut()
+        .batching_strategy(batching.strategy())
+        .for_each(
+            batch.wrap(|(position, mut cells): (&ChunkPosition, Mut<ChunkCells>)| {
+                let next = sparse::step_chunk(&rule.0, (position.x, position.y), chunk_at);
+                cells.set_if_neq(ChunkCells(next));
+            }),
+        );
 
     generations.0 += 1;
+    instruments.finish("update_chunks_system", start);
+}
+
+/// Despawns the chunks that died out. Only chunks that changed or were just spawned can
+/// have become empty.
+pub fn despawn_empty_chunks_system(
+    mut commands: Commands,
+    query: Query<(Entity, &ChunkCells), Changed<ChunkCells>>,
+    mut instruments: Instruments,
+) {
+    let start = Instant::now();
+    for (entity, cells) in query.iter() {
+        if sparse::is_empty(&cells.0) {
+            commands.entity(entity).despawn();
+        }
+    }
+    instruments.finish("despawn_empty_chunks_system", start);
 }
 
 pub fn handle_camera_system(
@@ -224,7 +453,7 @@ pub fn handle_cell_click_system(
     mouse_button_input: Res<Input<MouseButton>>,
     windows_query: Query<&Window, With<PrimaryWindow>>,
     placement_mode: Res<PlacementMode>,
-    mut cells_changed: ResMut<CellsChanged>,
+    rule: Res<Rule>,
 ) {
     let (camera, camera_transform) = camera_query.single();
     if let Some(position) = windows_query
@@ -240,15 +469,13 @@ pub fn handle_cell_click_system(
                 if x == pos.x && y == pos.y {
                     match *placement_mode {
                         PlacementMode::Single => {
-                            state.0 = !state.0;
-                            sprite.color = if state.0 { Color::GREEN } else { Color::BLACK };
-                            cells_changed.0 = true;
+                            state.0 = !state.is_alive() as u8;
+                            sprite.color = state.color(rule.0.states());
                         }
                         PlacementMode::Random => {
                             let mut rng = rand::thread_rng();
-                            state.0 = rng.gen_bool(0.5);
-                            sprite.color = if state.0 { Color::GREEN } else { Color::BLACK };
-                            cells_changed.0 = true;
+                            state.0 = rng.gen_bool(0.5) as u8;
+                            sprite.color = state.color(rule.0.states());
                         }
                         _ => {}
                     }
@@ -272,22 +499,135 @@ pub fn toggle_simulation_system(
     }
 }
 
+/// Runs a single generation. While rewound, `history_controls_system` replays the
+/// remembered generations instead.
 pub fn do_one_step_system(
     mut commands: Commands,
     simulation_state: Res<State<SimulationState>>,
     keyboard_input: Res<Input<KeyCode>>,
+    history: Option<Res<History>>,
+    cursor: Option<Res<HistoryCursor>>,
 ) {
-    if keyboard_input.just_pressed(KeyCode::Right) {
-        if *simulation_state == SimulationState::Paused {
-            commands.insert_resource(NextState(Some(SimulationState::Running)));
-            commands.insert_resource(NextState(Some(SimulationState::Paused)));
+    if let (Some(history), Some(cursor)) = (history, cursor) {
+        if cursor.generation < history.0.last() {
+            return;
+        }
+    }
+    if keyboard_input.just_pressed(KeyCode::Right) && *simulation_state == SimulationState::Paused {
+        commands.insert_resource(NextState(Some(SimulationState::Running)));
+        commands.insert_resource(NextState(Some(SimulationState::Paused)));
+    }
+}
+
+/// Moves the cursor for every `Rewind` sent and, in a window, for Left and Right, which
+/// step through the history, and Home and End, which jump to its ends. Stepping past the
+/// newest generation is left to `do_one_step_system`.
+pub fn history_controls_system(
+    keyboard_input: Option<Res<Input<KeyCode>>>,
+    mut rewinds: EventReader<Rewind>,
+    history: Option<Res<History>>,
+    mut cursor: ResMut<HistoryCursor>,
+) {
+    let Some(history) = history else {
+        rewinds.clear();
+        return;
+    };
+    let keys = [
+        (KeyCode::Left, Rewind::StepBack),
+        (KeyCode::Right, Rewind::StepForward),
+        (KeyCode::Home, Rewind::JumpTo(history.0.first())),
+        (KeyCode::End, Rewind::JumpTo(history.0.last())),
+    ];
+    let pressed = keyboard_input.iter().flat_map(|keyboard_input| {
+        keys.into_iter()
+            .filter(|(key, _)| keyboard_input.just_pressed(*key))
+            .map(|(_, rewind)| rewind)
+    });
+    for rewind in rewinds.read().copied().chain(pressed) {
+        match rewind {
+            Rewind::StepBack => cursor.step_back(),
+            Rewind::StepForward if cursor.generation < history.0.last() => cursor.step_forward(),
+            Rewind::StepForward => {}
+            Rewind::JumpTo(generation) => cursor.jump_to(generation),
         }
     }
 }
 
-pub fn start_measurement(mut commands: Commands) {
-    commands.insert_resource(SystemsMeasureTime(Instant::now()));
-    commands.insert_resource(GlobalTime(Instant::now()));
+/// Starts the history from the cells spawned at startup.
+pub fn start_history_system(
+    mut commands: Commands,
+    cells: Query<(&Position, &components::State)>,
+    grid: Res<Grid>,
+    generations: Res<Generations>,
+    budget: Res<HistoryBudget>,
+) {
+    let states = cell_states(cells.iter(), &grid);
+    commands.insert_resource(History(history::History::new(
+        budget.0,
+        generations.0,
+        states,
+    )));
+}
+
+/// Adds the generation the last step produced, first dropping the generations after
+/// the one it was stepped from if the run had been rewound.
+pub fn record_history_system(
+    cells: Query<(&Position, &components::State)>,
+    grid: Res<Grid>,
+    generations: Res<Generations>,
+    mut history: ResMut<History>,
+    mut cursor: ResMut<HistoryCursor>,
+) {
+    history.0.truncate(generations.0 - 1);
+    history.0.push(&cell_states(cells.iter(), &grid));
+    cursor.generation = generations.0;
+}
+
+/// Moves the cells to the generation the `HistoryCursor` points at, as far as the
+/// history still reaches, and pauses the run there.
+#[allow(clippy::too_many_arguments)]
+pub fn rewind_system(
+    mut commands: Commands,
+    mut cells: Query<(&Position, &mut components::State, Option<&mut Sprite>)>,
+    mut cursor: ResMut<HistoryCursor>,
+    mut generations: ResMut<Generations>,
+    history: Option<Res<History>>,
+    grid: Res<Grid>,
+    rule: Res<Rule>,
+    batching: Res<Batching>,
+) {
+    let (Some(history), Some(target)) = (history, cursor.target.take()) else {
+        return;
+    };
+    let target = target.clamp(history.0.first(), history.0.last());
+    let states = history.0.cells_at(target).expect("the target was clamped");
+    let state_count = rule.0.states();
+    cells
+        .par_iter_mut()
+        .batching_strategy(batching.strategy())
+        .for_each(|(position, mut state, mut sprite)| {
+            let index = (position.y as u32 * grid.width + position.x as u32) as usize;
+            let next = components::State(states[index]);
+            if let Some(sprite) = sprite.as_mut() {
+                if *state != next {
+                    sprite.color = next.color(state_count);
+                }
+            }
+            state.set_if_neq(next);
+        });
+
+    cursor.generation = target;
+    generations.0 = target;
+    commands.insert_resource(NextState(Some(SimulationState::Paused)));
+}
+
+/// Restarts the total time on the first frame, once the grid spawned at startup exists.
+pub fn start_clock_system(mut global_time: ResMut<GlobalTime>) {
+    global_time.0 = Instant::now();
+}
+
+pub fn start_measurement(mut systems_measure_time: ResMut<SystemsMeasureTime>) {
+    systems_measure_time.0 = Instant::now();
 }
 
 pub fn stop_measurement(
@@ -301,26 +641,179 @@ pub fn stop_measurement(
     //save_durations_to_file(&durations);
 }
 
+/// Counts the first generation, 0 unless the run was resumed, once the cells spawned at
+/// startup exist.
+pub fn record_initial_stats_system(
+    cells: Query<(&Position, &components::State)>,
+    chunks: Query<(&ChunkPosition, &ChunkCells)>,
+    generations: Res<Generations>,
+    mut activity: ResMut<ActivityLog>,
+) {
+    let mut record = GenerationStats::new(generations.0);
+    for (position, state) in cells.iter() {
+        record.count(position.x, position.y, state.0);
+    }
+    for (position, cells) in chunks.iter() {
+        record.count_chunk((position.x, position.y), &cells.0);
+    }
+    activity.0.push(record);
+}
+
+/// Compares the states `update_cells_system` just wrote with the `CellPositions` front
+/// buffer, which still holds the previous generation.
+pub fn record_stats_system(
+    cells: Query<(&Position, &components::State)>,
+    cell_positions: Res<CellPositions>,
+    generations: Res<Generations>,
+    durations: Res<Durations>,
+    mut activity: ResMut<ActivityLog>,
+) {
+    let mut record = GenerationStats::new(generations.0);
+    for (position, state) in cells.iter() {
+        record.count(position.x, position.y, state.0);
+        match (
+            cell_positions.is_alive(position.x, position.y),
+            state.is_alive(),
+        ) {
+            (false, true) => record.births += 1,
+            (true, false) => record.deaths += 1,
+            _ => {}
+        }
+    }
+    // The front buffer only tells alive from not alive, but a dying cell always moves on
+    // to the next state, so every cell that was dying has changed as well.
+    let dying_before = activity.0.last().map_or(0, |previous| previous.dying);
+    record.changed = record.births + record.deaths + dying_before;
+    record.duration = durations.0.last().copied();
+    activity.0.push(record);
+}
+
+/// Compares the chunks `update_chunks_system` just wrote with the `Chunks` front buffer.
+pub fn record_chunk_stats_system(
+    chunks: Query<(&ChunkPosition, &ChunkCells)>,
+    front: Res<Chunks>,
+    generations: Res<Generations>,
+    durations: Res<Durations>,
+    mut activity: ResMut<ActivityLog>,
+) {
+    let after = chunks
+        .iter()
+        .map(|(position, cells)| ((position.x, position.y), cells.0))
+        .collect();
+    let mut record = stats::chunk_stats(generations.0, &front.0, &after);
+    record.duration = durations.0.last().copied();
+    activity.0.push(record);
+}
+
+/// Hashes the generation the last step produced and sends `Stabilized` the first time
+/// one repeats.
+pub fn detect_cycles_system(
+    generations: Res<Generations>,
+    mut cycles: ResMut<Cycles>,
+    cells: Query<(&Position, &components::State)>,
+    chunks: Query<(&ChunkPosition, &ChunkCells)>,
+    extent: Res<Extent>,
+    mut stabilized: EventWriter<Stabilized>,
+) {
+    let cells: Vec<(i32, i32, u8)> = match extent.0 {
+        config::Extent::Bounded => cells
+            .iter()
+            .filter(|(_, state)| state.0 != DEAD)
+            .map(|(position, state)| (position.x, position.y, state.0))
+            .collect(),
+        config::Extent::Unbounded => chunks
+            .iter()
+            .flat_map(|(position, cells)| sparse::alive_cells((position.x, position.y), &cells.0))
+            .map(|(x, y)| (x, y, 1))
+            .collect(),
+    };
+    let hash = cycle::hash_cells(cells.iter().copied());
+
+    if let Some(stabilization) = cycles.detector.observe(generations.0, hash, cells.len()) {
+        stabilized.send(Stabilized(stabilization));
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 pub fn exit_after_n_generations_system(
     mut commands: Commands,
     generations: Res<Generations>,
+    max_generations: Res<MaxGenerations>,
     simulation_state: Res<State<SimulationState>>,
     durations: Res<Durations>,
     global_time: Res<GlobalTime>,
+    cells: Query<(&Position, &components::State, &Neighbors)>,
+    chunks: Query<(&ChunkPosition, &ChunkCells)>,
+    grid: Res<Grid>,
+    extent: Res<Extent>,
+    rule: Res<Rule>,
+    output_files: Res<OutputFiles>,
+    cycles: Option<Res<Cycles>>,
+    mut stabilized: EventReader<Stabilized>,
+    activity: Option<Res<ActivityLog>>,
+    (system_timings, tracing): (Option<Res<SystemTimings>>, Option<Res<Tracing>>),
 ) {
-    if generations.0 >= 100 {
-        if *simulation_state == SimulationState::Running {
-            println!("Exiting after 100 generations");
-            commands.insert_resource(NextState(Some(SimulationState::Exit)));
+    let mut stabilized_early = false;
+    for Stabilized(stabilization) in stabilized.read() {
+        println!(
+            "Stabilized at generation {}: {} with period {}",
+            stabilization.start(),
+            stabilization.kind(),
+            stabilization.period()
+        );
+        stabilized_early = cycles.as_ref().is_some_and(|cycles| cycles.stop);
+    }
+    if (generations.0 >= max_generations.0 || stabilized_early)
+        && *simulation_state == SimulationState::Running
+    {
+        // Writing the results is left out of the total time.
+        let duration = global_time.0.elapsed();
+        if stabilized_early {
+            println!("Exiting after {} generations, stabilized", generations.0);
+        } else {
+            println!("Exiting after {} generations", max_generations.0);
+        }
+        commands.insert_resource(NextState(Some(SimulationState::Exit)));
 
-            save_durations_to_file(&durations);
+        if let Some(filename) = &output_files.durations {
+            save_durations_to_file(&durations, filename);
+        }
+        if let (Some(cycles), Some(filename)) = (&cycles, &output_files.stabilization) {
+            cycle::save(cycles.detector.result(), filename).expect("Unable to write data");
+        }
+        if let (Some(activity), Some(filename)) = (&activity, &output_files.stats) {
+            stats::save(&activity.0, filename).expect("Unable to write data");
+        }
+        if let (Some(timings), Some(filename)) = (&system_timings, &output_files.system_timings) {
+            timings.0.save(filename).expect("Unable to write data");
+        }
+        if let (Some(tracing), Some(filename)) = (&tracing, &output_files.trace) {
+            tracing.0.save(filename).expect("Unable to write data");
+        }
+        if let Some(filename) = &output_files.pattern {
+            match extent.0 {
+                config::Extent::Bounded => save_cells_to_pattern_file(
+                    cells.iter().map(|(position, state, _)| (position, state)),
+                    &grid,
+                    &rule,
+                    filename,
+                ),
+                config::Extent::Unbounded => {
+                    save_chunks_to_pattern_file(chunks.iter(), &rule, filename)
+                }
+            }
+        }
 
-            let duration = global_time.0.elapsed();
-            println!("Total time: {:?}", duration);
+        if let Some(filename) = &output_files.snapshot {
+            save_cells_to_snapshot_file(cells.iter(), &grid, &rule, generations.0, filename);
+        }
 
-            std::fs::write("total_time_ecs.txt", format!("{:?}", duration)).unwrap();
+        println!("Total time: {:?}", duration);
 
-            std::process::exit(0);
+        if let Some(filename) = &output_files.total_time {
+            std::fs::write(filename, format!("{:?}", duration)).unwrap();
         }
+
+        std::process::exit(0);
     }
 }
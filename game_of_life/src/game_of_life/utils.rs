@@ -1,9 +1,12 @@
 use std::io::Write;
 
-use super::resources::Durations;
+use game_of_life_core::{sparse, Pattern, Snapshot};
 
-pub fn save_durations_to_file(durations: &Durations) {
-    let mut file = std::fs::File::create("durations_ecs.txt").unwrap();
+use super::components::{ChunkCells, ChunkPosition, Neighbors, Position, State};
+use super::resources::{Durations, Grid, Rule};
+
+pub fn save_durations_to_file(durations: &Durations, filename: &str) {
+    let mut file = std::fs::File::create(filename).unwrap();
     for duration in durations.0.iter() {
         let duration_str = format!("{:?}", duration);
         file.write_all(duration_str.as_bytes())
@@ -11,3 +14,69 @@ pub fn save_durations_to_file(durations: &Durations) {
         file.write_all(b"\n").expect("Unable to write data");
     }
 }
+
+/// Every cell's state at `y * width + x`.
+pub fn cell_states<'a>(
+    cells: impl Iterator<Item = (&'a Position, &'a State)>,
+    grid: &Grid,
+) -> Vec<u8> {
+    let mut states = vec![0; (grid.width * grid.height) as usize];
+    for (position, state) in cells {
+        states[(position.y as u32 * grid.width + position.x as u32) as usize] = state.0;
+    }
+    states
+}
+
+pub fn save_cells_to_pattern_file<'a>(
+    cells: impl Iterator<Item = (&'a Position, &'a State)>,
+    grid: &Grid,
+    rule: &Rule,
+    filename: &str,
+) {
+    let alive_cells = cells
+        .filter(|(_, state)| state.is_alive())
+        .map(|(position, _)| (position.x, position.y));
+    let pattern = Pattern::from_grid(grid.width, grid.height, alive_cells, Some(rule.0))
+        .with_topology(grid.topology);
+    pattern.save(filename).expect("Unable to write data");
+}
+
+/// Saves the alive cells of the unbounded universe, moved so their bounding box starts
+/// at `(0, 0)`.
+pub fn save_chunks_to_pattern_file<'a>(
+    chunks: impl Iterator<Item = (&'a ChunkPosition, &'a ChunkCells)>,
+    rule: &Rule,
+    filename: &str,
+) {
+    let alive_cells: Vec<(i32, i32)> = chunks
+        .flat_map(|(position, cells)| sparse::alive_cells((position.x, position.y), &cells.0))
+        .collect();
+    let pattern = Pattern::from_cells(alive_cells, Some(rule.0));
+    pattern.save(filename).expect("Unable to write data");
+}
+
+/// Saves every cell's state and neighbour count along with the grid and rule, so the run
+/// can be picked up again with `--resume`.
+pub fn save_cells_to_snapshot_file<'a>(
+    cells: impl Iterator<Item = (&'a Position, &'a State, &'a Neighbors)>,
+    grid: &Grid,
+    rule: &Rule,
+    generation: u64,
+    filename: &str,
+) {
+    let mut snapshot = Snapshot {
+        width: grid.width,
+        height: grid.height,
+        boundary: grid.boundary,
+        topology: grid.topology,
+        rule: rule.0,
+        neighborhood: grid.neighborhood.clone(),
+        generation,
+        cells: vec![(0, 0); (grid.width * grid.height) as usize],
+    };
+    for (position, state, neighbors) in cells {
+        let index = (position.y as u32 * grid.width + position.x as u32) as usize;
+        snapshot.cells[index] = (state.0, neighbors.0);
+    }
+    snapshot.save(filename).expect("Unable to write data");
+}
@@ -1,22 +1,27 @@
-use std::time::Duration;
-
-use bevy::{app::ScheduleRunnerPlugin, prelude::*};
+use bevy::prelude::*;
+use game_of_life_core::Config;
 
 mod game_of_life;
 mod gas_sim;
 
 fn main() {
+    let config = Config {
+        durations_output: Some("durations_ecs.txt".to_string()),
+        total_time_output: Some("total_time_ecs.txt".to_string()),
+        ..default()
+    }
+    .parse_env_or_exit();
+
+    // Only the compute pool runs systems and parallel queries, so `--threads` sizes it
+    // exactly rather than sharing a total with the IO and async pools.
+    let mut task_pool_options = TaskPoolOptions::default();
+    if let Some(threads) = config.compute_threads() {
+        task_pool_options.compute.min_threads = threads;
+        task_pool_options.compute.max_threads = threads;
+    }
+
     App::new()
-        // .add_plugins(
-        //     MinimalPlugins
-        //         .set(ScheduleRunnerPlugin::run_loop(Duration::from_secs_f64(
-        //             1.0 / 60.0,
-        //         )))
-        //         .set(TaskPoolPlugin {
-        //             task_pool_options: TaskPoolOptions::with_num_threads(12),
-        //         }),
-        // )
-        .add_plugins(MinimalPlugins)
+        .add_plugins(MinimalPlugins.set(TaskPoolPlugin { task_pool_options }))
         // .add_plugins(DefaultPlugins.set(WindowPlugin {
         //     primary_window: Some(Window {
         //         title: "Gas Sim".to_string(),
@@ -25,7 +30,7 @@ fn main() {
         //     }),
         //     ..default()
         // }))
-        .add_plugins((game_of_life::GameOfLifePlugin,))
+        .add_plugins((game_of_life::GameOfLifePlugin { config },))
         // .add_plugins(gas_sim::GasSimPlugin)
         .run();
 }